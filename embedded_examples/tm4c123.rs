@@ -72,7 +72,7 @@ fn main() -> ! {
     let cs = porta.pa3.into_push_pull_output();
     let spi_interface = SPIInterface::new(spi, dc, cs);
 
-    let mut disp = display::Ssd1322::new(spi_interface);
+    let mut disp: display::Ssd1322<_> = display::Ssd1322::new(spi_interface);
 
     // reset and init
     disp.reset(&mut res, &mut delay).unwrap();
@@ -0,0 +1,262 @@
+//! color converting draw target module
+use core::marker::PhantomData;
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point},
+    pixelcolor::{Gray4, Gray8, GrayColor, PixelColor},
+    primitives::{PointsIter, Rectangle},
+    Pixel,
+};
+
+/// 4x4 Bayer matrix of the ordered dithering thresholds.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Draws `Gray8` pixels onto a `Gray4` draw target such as `Ssd1322`, rounding each pixel to the
+/// nearest of the 16 gray levels. This lets code that renders in 8 bit grayscale, e.g.
+/// anti-aliased font rasterizers, draw onto the display directly.
+///
+/// ```ignore
+/// let mut target = Gray8Target::new(&mut disp);
+/// Pixel(Point::new(0, 0), Gray8::new(0x80)).draw(&mut target)?;
+/// ```
+#[derive(Debug)]
+pub struct Gray8Target<'a, T> {
+    target: &'a mut T,
+}
+
+impl<'a, T> Gray8Target<'a, T>
+where
+    T: DrawTarget<Color = Gray4>,
+{
+    /// Creates an adapter drawing onto `target`.
+    pub fn new(target: &'a mut T) -> Self {
+        Self { target }
+    }
+
+    /// Returns the draw target.
+    pub fn inner(&mut self) -> &mut T {
+        self.target
+    }
+}
+
+impl<T> DrawTarget for Gray8Target<'_, T>
+where
+    T: DrawTarget<Color = Gray4>,
+{
+    type Color = Gray8;
+    type Error = T::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, Gray4::from(color))),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.target
+            .fill_contiguous(area, colors.into_iter().map(Gray4::from))
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.target.fill_solid(area, color.into())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.target.clear(color.into())
+    }
+}
+
+impl<T> Dimensions for Gray8Target<'_, T>
+where
+    T: DrawTarget<Color = Gray4>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+/// How colors are reduced to the 16 gray levels of the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dithering {
+    /// Each pixel is rounded to the nearest gray level.
+    None,
+    /// Ordered dithering with a 4x4 Bayer matrix, which shows gradients and photos with less
+    /// banding.
+    Bayer,
+}
+
+impl Dithering {
+    /// Returns the gray level of `color` at `point`.
+    fn gray4(self, point: Point, color: Gray8) -> Gray4 {
+        match self {
+            Dithering::None => color.into(),
+            Dithering::Bayer => {
+                let threshold = BAYER[(point.y & 3) as usize][(point.x & 3) as usize];
+                // The threshold is spread over one gray level step of 255 / 15 in luma.
+                let offset = (u16::from(threshold) * 2 + 1) * 255 / 32;
+                Gray4::new(((u16::from(color.luma()) * 15 + offset) / 255) as u8)
+            }
+        }
+    }
+}
+
+/// Draws RGB pixels, e.g. `Rgb565` or `Rgb888`, onto a `Gray4` draw target such as `Ssd1322`,
+/// so color UI assets and image decoders can be reused on the display. Colors are converted to
+/// their luma (ITU-R BT.601) and then reduced to the 16 gray levels as set by `Dithering`.
+///
+/// ```ignore
+/// let mut target = RgbTarget::<_, Rgb565>::new(&mut disp, Dithering::Bayer);
+/// image.draw(&mut target)?;
+/// ```
+#[derive(Debug)]
+pub struct RgbTarget<'a, T, C> {
+    target: &'a mut T,
+    dithering: Dithering,
+    color: PhantomData<C>,
+}
+
+impl<'a, T, C> RgbTarget<'a, T, C>
+where
+    T: DrawTarget<Color = Gray4>,
+    C: PixelColor + Into<Gray8>,
+{
+    /// Creates an adapter drawing onto `target`.
+    pub fn new(target: &'a mut T, dithering: Dithering) -> Self {
+        Self {
+            target,
+            dithering,
+            color: PhantomData,
+        }
+    }
+
+    /// Returns the draw target.
+    pub fn inner(&mut self) -> &mut T {
+        self.target
+    }
+}
+
+impl<T, C> DrawTarget for RgbTarget<'_, T, C>
+where
+    T: DrawTarget<Color = Gray4>,
+    C: PixelColor + Into<Gray8>,
+{
+    type Color = C;
+    type Error = T::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let dithering = self.dithering;
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, dithering.gray4(point, color.into()))),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let dithering = self.dithering;
+        self.target.fill_contiguous(
+            area,
+            area.points()
+                .zip(colors)
+                .map(|(point, color)| dithering.gray4(point, color.into())),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        match self.dithering {
+            Dithering::None => self
+                .target
+                .fill_solid(area, self.dithering.gray4(area.top_left, color.into())),
+            Dithering::Bayer => self.fill_contiguous(area, core::iter::repeat(color)),
+        }
+    }
+}
+
+impl<T, C> Dimensions for RgbTarget<'_, T, C>
+where
+    T: DrawTarget<Color = Gray4>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Ssd1322;
+    use crate::mock::RecordingInterface;
+    use embedded_graphics::prelude::*;
+
+    #[test]
+    /// Tests that `Gray8` pixels are rounded to the nearest gray level.
+    fn gray8_target() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let mut target = Gray8Target::new(&mut disp);
+        assert_eq!(target.bounding_box().size, Size::new(256, 64));
+
+        target
+            .draw_iter([
+                Pixel(Point::new(0, 0), Gray8::new(0x80)),
+                Pixel(Point::new(1, 0), Gray8::new(0x07)),
+                Pixel(Point::new(2, 0), Gray8::WHITE),
+            ])
+            .unwrap();
+        target
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 1), Size::new(2, 1)),
+                Gray8::new(0x11),
+            )
+            .unwrap();
+
+        assert_eq!(disp.get_pixel(Point::new(0, 0)), Some(Gray4::new(0x08)));
+        assert_eq!(disp.get_pixel(Point::new(1, 0)), Some(Gray4::BLACK));
+        assert_eq!(disp.get_pixel(Point::new(2, 0)), Some(Gray4::WHITE));
+        assert_eq!(disp.get_pixel(Point::new(1, 1)), Some(Gray4::new(0x01)));
+    }
+
+    #[test]
+    /// Tests that RGB pixels are converted to their luma, with and without dithering.
+    fn rgb_target() {
+        use embedded_graphics::pixelcolor::{Rgb565, Rgb888};
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let mut target = RgbTarget::<_, Rgb565>::new(&mut disp, Dithering::None);
+        Pixel(Point::new(0, 0), Rgb565::GREEN)
+            .draw(&mut target)
+            .unwrap();
+        assert_eq!(disp.get_pixel(Point::new(0, 0)), Some(Gray4::new(0x09)));
+
+        // A gray between two levels is shown as a mix of both.
+        let mut target = RgbTarget::<_, Rgb888>::new(&mut disp, Dithering::Bayer);
+        target
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 0), Size::new(4, 4)),
+                Rgb888::new(94, 94, 94),
+            )
+            .unwrap();
+        let levels = Rectangle::new(Point::new(0, 0), Size::new(4, 4))
+            .points()
+            .map(|point| disp.get_pixel(point).unwrap().luma());
+        let (min, max, sum) = levels.fold((15, 0, 0), |(min, max, sum), l| {
+            (min.min(l), max.max(l), sum + u32::from(l))
+        });
+        assert_eq!((min, max), (5, 6));
+        assert_eq!(sum, 6 * 16 - 8);
+    }
+}
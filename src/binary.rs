@@ -0,0 +1,242 @@
+//! 1 bit per pixel framebuffer mode module
+use crate::config::DisplayConfig;
+use crate::display::Ssd1322;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+#[cfg(feature = "graphics")]
+use embedded_graphics::{draw_target::DrawTarget, pixelcolor::BinaryColor, Pixel};
+
+/// The framebuffer stores 1 bit per pixel, which is expanded to two gray levels when it is
+/// flushed, e.g. for text-only devices on MCUs that cannot spare the RAM of the 4 bit
+/// framebuffer. A 256x64 panel needs 2 KB instead of 8 KB.
+///
+/// The framebuffer holds `(WIDTH + 7) / 8 * HEIGHT` bytes, each byte covers 8 horizontally
+/// adjacent pixels with the left pixel in the most significant bit. With the `graphics` feature
+/// `Ssd1322` implements `DrawTarget` with `BinaryColor` in this mode.
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryMode {
+    levels: [u8; 2],
+}
+
+impl Default for BinaryMode {
+    fn default() -> Self {
+        Self {
+            levels: [0x00, 0x0F],
+        }
+    }
+}
+
+/// Checks at compile time that a 1 bit per pixel framebuffer of `N` bytes fits a `WIDTH` x
+/// `HEIGHT` panel.
+struct BinaryBufferSize<const WIDTH: usize, const HEIGHT: usize, const N: usize>;
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> BinaryBufferSize<WIDTH, HEIGHT, N> {
+    const CHECK: () = assert!(
+        N == WIDTH.div_ceil(8) * HEIGHT,
+        "the buffer size must be (WIDTH + 7) / 8 * HEIGHT bytes"
+    );
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>
+    Ssd1322<DI, WIDTH, HEIGHT, [u8; N], BinaryMode>
+{
+    /// Creates the SSD1322 Display with a 1 bit per pixel framebuffer.
+    ///
+    /// The panel is assumed to be centred in the GDDRAM. The device needs to be reset before use.
+    pub fn new_binary(display: DI) -> Self {
+        Self::new_binary_with_config(display, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display with a 1 bit per pixel framebuffer for a panel wired as
+    /// described by `config`.
+    ///
+    /// The configuration is validated by `init`. The device needs to be reset before use.
+    pub fn new_binary_with_config(display: DI, config: DisplayConfig) -> Self {
+        let () = BinaryBufferSize::<WIDTH, HEIGHT, N>::CHECK;
+
+        Self::from_parts(display, [0; N], config, BinaryMode::default())
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> Ssd1322<DI, WIDTH, HEIGHT, B, MODE> {
+    /// Converts the driver into the 1 bit per pixel mode with a new, cleared framebuffer.
+    ///
+    /// The display configuration and the content shown on the display are kept, the content is
+    /// replaced by the next `flush_all`.
+    pub fn into_binary_mode<const N: usize>(
+        self,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, [u8; N], BinaryMode> {
+        let () = BinaryBufferSize::<WIDTH, HEIGHT, N>::CHECK;
+
+        self.into_mode([0; N], BinaryMode::default())
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B> Ssd1322<DI, WIDTH, HEIGHT, B, BinaryMode> {
+    /// Number of framebuffer bytes per row.
+    const BINARY_ROW_BYTES: usize = WIDTH.div_ceil(8);
+
+    /// Sets the gray levels (0 to 15) that cleared and set pixels are shown with. The levels are
+    /// used from the next flush, call `flush_all` to apply them to the whole display.
+    pub fn set_levels(&mut self, off: u8, on: u8) {
+        self.mode.levels = [off & 0x0F, on & 0x0F];
+    }
+
+    /// Returns the gray levels of cleared and set pixels.
+    pub fn levels(&self) -> (u8, u8) {
+        (self.mode.levels[0], self.mode.levels[1])
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B> Ssd1322<DI, WIDTH, HEIGHT, B, BinaryMode>
+where
+    B: AsMut<[u8]>,
+{
+    /// Sets or clears the pixel at `x`, `y`. Pixels outside of the display are ignored.
+    pub fn set_pixel(&mut self, x: u16, y: u16, on: bool) {
+        if let Some((x, y)) = self.physical_xy(x.into(), y.into()) {
+            self.write_bit(x, y, on);
+        }
+    }
+
+    fn write_bit(&mut self, x: usize, y: usize, on: bool) {
+        let byte = &mut self.buffer.as_mut()[y * Self::BINARY_ROW_BYTES + x / 8];
+        let mask = 0x80 >> (x % 8);
+        let new_val = if on { *byte | mask } else { *byte & !mask };
+
+        if *byte != new_val {
+            *byte = new_val;
+            // The changed areas are tracked in bytes of the 4 bit GDDRAM data.
+            self.dirty.add((x / 2) as u8, y as u8);
+        }
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B> Ssd1322<DI, WIDTH, HEIGHT, B, BinaryMode>
+where
+    DI: WriteOnlyDataCommand,
+    B: AsRef<[u8]>,
+{
+    /// Flushes the changed parts of the framebuffer, expanded to the configured gray levels.
+    pub fn flush(&mut self) -> Result<(), DisplayError> {
+        // The areas are taken one by one, a failed one is put back for the next flush.
+        while let Some((cols, rows)) = self.dirty.pop() {
+            let result = self.write_levels(
+                [usize::from(cols[0]) / 2, usize::from(cols[1]) / 2],
+                [usize::from(rows[0]), usize::from(rows[1])],
+            );
+            if let Err(error) = result {
+                self.dirty.add_area((cols, rows));
+                return Err(error);
+            }
+        }
+
+        self.dirty.clear();
+
+        Ok(())
+    }
+
+    /// Flushes the entire framebuffer, expanded to the configured gray levels.
+    pub fn flush_all(&mut self) -> Result<(), DisplayError> {
+        self.write_levels([0, WIDTH / 4 - 1], [0, HEIGHT - 1])?;
+
+        self.dirty.clear();
+
+        Ok(())
+    }
+
+    /// Writes the column addresses `x` of the rows `y` of the framebuffer.
+    fn write_levels(&mut self, x: [usize; 2], y: [usize; 2]) -> Result<(), DisplayError> {
+        let levels = self.mode.levels;
+        let level = move |buffer: &B, x: usize, y: usize| {
+            let byte = buffer.as_ref()[y * Self::BINARY_ROW_BYTES + x / 8];
+            levels[usize::from(byte & (0x80 >> (x % 8)) != 0)]
+        };
+
+        self.write_window(x, y, |buffer, row, i| {
+            let (x, y) = (x[0] * 4 + i * 2, y[0] + row);
+            (level(buffer, x, y) << 4) | level(buffer, x + 1, y)
+        })
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B> DrawTarget
+    for Ssd1322<DI, WIDTH, HEIGHT, B, BinaryMode>
+where
+    B: AsMut<[u8]>,
+{
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels.into_iter() {
+            // `DrawTarget` implementation are required to discard any out of bounds pixels
+            // without returning an error or causing a panic.
+            if let Some((x, y)) = self.to_physical(coord) {
+                self.write_bit(x, y, color.is_on());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    #[test]
+    /// Tests that set pixels are expanded to the configured gray levels when flushed.
+    fn binary_flush() {
+        let mut disp: Ssd1322<_, 256, 64, [u8; 2048], BinaryMode> =
+            Ssd1322::new_binary(RecordingInterface::<8300>::new());
+
+        disp.set_pixel(5, 0, true);
+        disp.set_pixel(6, 0, true);
+        disp.set_pixel(256, 0, true);
+        assert_eq!(disp.buffer[0], 0x06);
+
+        disp.flush().unwrap();
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(window.columns, [0x1D, 0x1D]);
+        assert_eq!(window.data, [0x0F, 0xF0]);
+
+        disp.set_levels(0x01, 0x08);
+        assert_eq!(disp.levels(), (0x01, 0x08));
+        disp.set_pixel(6, 0, false);
+        disp.flush().unwrap();
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(window.data, [0x18, 0x11]);
+
+        disp.display.clear();
+        disp.flush_all().unwrap();
+        assert!(!disp.display.is_overflowed());
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(window.data.len(), 8192);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    /// Tests drawing with `BinaryColor`.
+    fn binary_draw() {
+        use embedded_graphics::prelude::*;
+
+        let mut disp: Ssd1322<_, 256, 64, [u8; 2048], BinaryMode> =
+            Ssd1322::new_binary(RecordingInterface::<64>::new());
+
+        Pixel(Point::new(255, 63), BinaryColor::On)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(disp.buffer[2047], 0x01);
+
+        disp.clear(BinaryColor::On).unwrap();
+        assert!(disp.buffer.iter().all(|&b| b == 0xFF));
+        assert_eq!(disp.dirty.union(), Some(([0, 127], [0, 63])));
+        disp.flush().unwrap();
+        assert!(disp.dirty.is_empty());
+    }
+}
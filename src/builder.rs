@@ -0,0 +1,447 @@
+//! driver builder module
+use crate::binary::BinaryMode;
+use crate::config::{
+    Brightness, DataWidth, DisplayClock, DisplayConfig, DisplayRotation, GpioState, InitConfig,
+    NibbleOrder, PanelPreset, PhaseLength, RemapConfig, ResetConfig, RetryPolicy, VddSource, Vsl,
+};
+use crate::display::Ssd1322;
+use crate::mode::{BasicMode, DirectMode, UntrackedMode};
+use crate::terminal::TerminalMode;
+
+/// Builds a configured [`Ssd1322`] driver.
+///
+/// The panel size is taken from the driver type, every other setting defaults to the value used
+/// by [`DisplayConfig::for_panel`].
+///
+/// ```ignore
+/// let mut disp: Ssd1322<_, 256, 32, [u8; 4096]> = Ssd1322Builder::new()
+///     .col_offset(0x1C)
+///     .contrast(0x7F)
+///     .build(interface);
+/// disp.init()?;
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ssd1322Builder {
+    col_offset: Option<u8>,
+    row_offset: Option<u8>,
+    display_rows: Option<u8>,
+    clock: Option<u8>,
+    remap: Option<[u8; 2]>,
+    gpio: Option<[GpioState; 2]>,
+    nibble_order: Option<NibbleOrder>,
+    contrast: Option<u8>,
+    master_current: Option<u8>,
+    init: Option<InitConfig>,
+    phase_length: Option<PhaseLength>,
+    vsl: Option<Vsl>,
+    vdd: Option<VddSource>,
+    rotation: Option<DisplayRotation>,
+    mirror: Option<(bool, bool)>,
+    max_transfer: Option<usize>,
+    data_width: Option<DataWidth>,
+    reset: Option<ResetConfig>,
+    retry: Option<RetryPolicy>,
+}
+
+impl Ssd1322Builder {
+    /// Creates a builder with every setting at its default.
+    pub const fn new() -> Self {
+        Self {
+            col_offset: None,
+            row_offset: None,
+            display_rows: None,
+            clock: None,
+            remap: None,
+            gpio: None,
+            nibble_order: None,
+            contrast: None,
+            master_current: None,
+            init: None,
+            phase_length: None,
+            vsl: None,
+            vdd: None,
+            rotation: None,
+            mirror: None,
+            max_transfer: None,
+            data_width: None,
+            reset: None,
+            retry: None,
+        }
+    }
+
+    /// Sets the GDDRAM column address of the first panel column.
+    pub const fn col_offset(mut self, col_offset: u8) -> Self {
+        self.col_offset = Some(col_offset);
+        self
+    }
+
+    /// Sets the display offset between the first COM line and the first panel row.
+    pub const fn row_offset(mut self, row_offset: u8) -> Self {
+        self.row_offset = Some(row_offset);
+        self
+    }
+
+    /// Sets the number of panel rows, for a framebuffer that is taller than the panel.
+    pub const fn display_rows(mut self, display_rows: u8) -> Self {
+        self.display_rows = Some(display_rows);
+        self
+    }
+
+    /// Sets the front clock divider and oscillator frequency byte.
+    pub const fn clock(mut self, clock: u8) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Sets the front clock divider and oscillator frequency.
+    pub const fn display_clock(self, clock: DisplayClock) -> Self {
+        self.clock(clock.byte())
+    }
+
+    /// Sets the two remap bytes.
+    pub const fn remap(mut self, a: u8, b: u8) -> Self {
+        self.remap = Some([a, b]);
+        self
+    }
+
+    /// Sets the remap bytes from their bits.
+    pub const fn remap_config(self, remap: RemapConfig) -> Self {
+        let [a, b] = remap.bytes();
+        self.remap(a, b)
+    }
+
+    /// Sets the modes of the GPIO0 and GPIO1 pins, e.g. to switch on the panel supply during
+    /// `init`.
+    pub const fn gpio(mut self, gpio0: GpioState, gpio1: GpioState) -> Self {
+        self.gpio = Some([gpio0, gpio1]);
+        self
+    }
+
+    /// Sets the order of the two pixels of each byte, for panels that show pixel pairs swapped.
+    pub const fn nibble_order(mut self, nibble_order: NibbleOrder) -> Self {
+        self.nibble_order = Some(nibble_order);
+        self
+    }
+
+    /// Sets the contrast current.
+    pub const fn contrast(mut self, contrast: u8) -> Self {
+        self.contrast = Some(contrast);
+        self
+    }
+
+    /// Sets the master current, which is limited to its 4 bits.
+    pub const fn master_current(mut self, master_current: u8) -> Self {
+        self.master_current = Some(master_current & 0x0F);
+        self
+    }
+
+    /// Sets the contrast and master current from a brightness.
+    pub const fn brightness(self, brightness: Brightness) -> Self {
+        self.contrast(brightness.contrast())
+            .master_current(brightness.master_current())
+    }
+
+    /// Sets the driving registers programmed by `init`, e.g. from a vendor's init sequence.
+    /// `phase_length` and `vsl` override the ones of `init`.
+    pub const fn init_config(mut self, init: InitConfig) -> Self {
+        self.init = Some(init);
+        self
+    }
+
+    /// Sets the driving registers and the contrast recommended for a module.
+    pub const fn preset(self, preset: PanelPreset) -> Self {
+        self.init_config(preset.init_config())
+            .contrast(preset.contrast())
+    }
+
+    /// Sets the reset and pre-charge phase lengths.
+    pub const fn phase_length(mut self, phase_length: PhaseLength) -> Self {
+        self.phase_length = Some(phase_length);
+        self
+    }
+
+    /// Selects the segment low voltage source.
+    pub const fn vsl(mut self, vsl: Vsl) -> Self {
+        self.vsl = Some(vsl);
+        self
+    }
+
+    /// Selects the VDD source.
+    pub const fn vdd_source(mut self, vdd: VddSource) -> Self {
+        self.vdd = Some(vdd);
+        self
+    }
+
+    /// Sets the display rotation.
+    pub const fn rotation(mut self, rotation: DisplayRotation) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    /// Mirrors the content horizontally and/or vertically.
+    pub const fn mirror(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.mirror = Some((horizontal, vertical));
+        self
+    }
+
+    /// Limits the number of bytes the flush methods send in one data transfer.
+    pub const fn max_transfer(mut self, max_transfer: usize) -> Self {
+        self.max_transfer = Some(max_transfer);
+        self
+    }
+
+    /// Sets the width of the words the flush methods send, e.g. 16 bit words for a faster DMA.
+    pub const fn data_width(mut self, data_width: DataWidth) -> Self {
+        self.data_width = Some(data_width);
+        self
+    }
+
+    /// Sets the timing and polarity of the reset line, e.g. for a board that inverts it.
+    pub const fn reset(mut self, reset: ResetConfig) -> Self {
+        self.reset = Some(reset);
+        self
+    }
+
+    /// Sets how the flush methods recover from a failed transfer.
+    pub const fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Returns the configuration for a `width` x `height` framebuffer.
+    pub const fn config(&self, width: usize, height: usize) -> DisplayConfig {
+        let rows = match self.display_rows {
+            Some(rows) => rows as usize,
+            None => height,
+        };
+        let defaults = DisplayConfig::for_panel(width, rows);
+
+        DisplayConfig {
+            col_offset: unwrap_or(self.col_offset, defaults.col_offset),
+            row_offset: unwrap_or(self.row_offset, defaults.row_offset),
+            display_rows: defaults.display_rows,
+            clock: unwrap_or(self.clock, defaults.clock),
+            remap: match self.remap {
+                Some(remap) => remap,
+                None => defaults.remap,
+            },
+            gpio: match self.gpio {
+                Some(gpio) => gpio,
+                None => defaults.gpio,
+            },
+            nibble_order: match self.nibble_order {
+                Some(nibble_order) => nibble_order,
+                None => defaults.nibble_order,
+            },
+            contrast: unwrap_or(self.contrast, defaults.contrast),
+            master_current: unwrap_or(self.master_current, defaults.master_current),
+            init: self.init_registers(defaults.init),
+            rotation: match self.rotation {
+                Some(rotation) => rotation,
+                None => defaults.rotation,
+            },
+            mirror_horizontal: match self.mirror {
+                Some((horizontal, _)) => horizontal,
+                None => defaults.mirror_horizontal,
+            },
+            mirror_vertical: match self.mirror {
+                Some((_, vertical)) => vertical,
+                None => defaults.mirror_vertical,
+            },
+            max_transfer: match self.max_transfer {
+                Some(max_transfer) => max_transfer,
+                None => defaults.max_transfer,
+            },
+            data_width: match self.data_width {
+                Some(data_width) => data_width,
+                None => defaults.data_width,
+            },
+            reset: match self.reset {
+                Some(reset) => reset,
+                None => defaults.reset,
+            },
+            retry: match self.retry {
+                Some(retry) => retry,
+                None => defaults.retry,
+            },
+        }
+    }
+
+    /// Creates the driver. The device needs to be reset and initialized before use.
+    pub fn build<DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>(
+        self,
+        display: DI,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, [u8; N]> {
+        Ssd1322::new_with_config(display, self.config(WIDTH, HEIGHT))
+    }
+
+    /// Creates the driver without change tracking, see [`UntrackedMode`]. The device needs to be
+    /// reset and initialized before use.
+    pub fn build_untracked<DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>(
+        self,
+        display: DI,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, [u8; N], UntrackedMode> {
+        Ssd1322::new_untracked_with_config(display, self.config(WIDTH, HEIGHT))
+    }
+
+    /// Creates the driver with a 1 bit per pixel framebuffer. The device needs to be reset and
+    /// initialized before use.
+    pub fn build_binary<DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>(
+        self,
+        display: DI,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, [u8; N], BinaryMode> {
+        Ssd1322::new_binary_with_config(display, self.config(WIDTH, HEIGHT))
+    }
+
+    /// Creates the driver in basic mode, which only sends commands. The device needs to be reset
+    /// and initialized before use.
+    pub fn build_basic<DI, const WIDTH: usize, const HEIGHT: usize>(
+        self,
+        display: DI,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, (), BasicMode> {
+        Ssd1322::new_basic_with_config(display, self.config(WIDTH, HEIGHT))
+    }
+
+    /// Creates the driver in direct mode, without a framebuffer. The device needs to be reset
+    /// and initialized before use.
+    pub fn build_direct<DI, const WIDTH: usize, const HEIGHT: usize>(
+        self,
+        display: DI,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, (), DirectMode> {
+        Ssd1322::new_direct_with_config(display, self.config(WIDTH, HEIGHT))
+    }
+
+    /// Creates the driver in terminal mode. The device needs to be reset and initialized before
+    /// use.
+    pub fn build_terminal<DI, const WIDTH: usize, const HEIGHT: usize>(
+        self,
+        display: DI,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, (), TerminalMode> {
+        Ssd1322::new_terminal_with_config(display, self.config(WIDTH, HEIGHT))
+    }
+
+    /// Creates the driver using an external framebuffer. The device needs to be reset and
+    /// initialized before use.
+    pub fn build_with_buffer<DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>(
+        self,
+        display: DI,
+        buffer: &mut [u8; N],
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, &mut [u8; N]> {
+        Ssd1322::new_with_buffer_and_config(display, buffer, self.config(WIDTH, HEIGHT))
+    }
+}
+
+impl Ssd1322Builder {
+    /// Returns the driving registers, with the phase lengths, VSL and VDD source overridden if
+    /// set.
+    const fn init_registers(&self, defaults: InitConfig) -> InitConfig {
+        let mut init = match self.init {
+            Some(init) => init,
+            None => defaults,
+        };
+        if let Some(phase_length) = self.phase_length {
+            init.phase_length = phase_length;
+        }
+        if let Some(vsl) = self.vsl {
+            init.vsl = vsl;
+        }
+        if let Some(vdd) = self.vdd {
+            init.vdd = vdd;
+        }
+        init
+    }
+}
+
+const fn unwrap_or(value: Option<u8>, default: u8) -> u8 {
+    match value {
+        Some(value) => value,
+        None => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    #[test]
+    /// Tests that unset values fall back to the panel defaults and set values override them.
+    fn builder_config() {
+        let config = Ssd1322Builder::new()
+            .contrast(0x7F)
+            .remap(0x06, 0x11)
+            .config(256, 64);
+
+        assert_eq!(config.col_offset, 0x1C);
+        assert_eq!(config.clock, 0x91);
+        assert_eq!(config.remap, [0x06, 0x11]);
+        assert_eq!(config.contrast, 0x7F);
+        assert_eq!(config.gpio, [GpioState::InputDisabled; 2]);
+
+        let config = Ssd1322Builder::new()
+            .display_clock(DisplayClock::new(1, 0x0F).unwrap())
+            .vsl(Vsl::Internal)
+            .vdd_source(VddSource::External)
+            .config(256, 64);
+        assert_eq!(config.clock, 0xF0);
+        assert_eq!(config.init.phase_length, PhaseLength::DEFAULT);
+        assert_eq!(config.init.vsl, Vsl::Internal);
+        assert_eq!(config.init.vdd, VddSource::External);
+
+        let init = InitConfig {
+            vcomh: 0x04,
+            ..InitConfig::DEFAULT
+        };
+        let config = Ssd1322Builder::new()
+            .vsl(Vsl::Internal)
+            .init_config(init)
+            .config(256, 64);
+        assert_eq!(config.init.vcomh, 0x04);
+        assert_eq!(config.init.vsl, Vsl::Internal);
+
+        let config = Ssd1322Builder::new()
+            .preset(PanelPreset::Nhd312)
+            .config(256, 64);
+        assert_eq!(config.init.enhancement_b, [0x82, 0x20]);
+        assert_eq!(config.contrast, 0x9F);
+
+        let config = Ssd1322Builder::new().col_offset(0).config(480, 128);
+        assert_eq!(config.col_offset, 0);
+        assert_eq!(config.remap, [0x14, 0x01]);
+    }
+
+    #[test]
+    /// Tests that each preset initializes the display with its own driving registers.
+    fn preset_init() {
+        let presets = [
+            PanelPreset::Nhd312,
+            PanelPreset::ErOledM032,
+            PanelPreset::MidasMdob256064,
+        ];
+        let ifaces = presets.map(|preset| {
+            let mut disp: Ssd1322<_> = Ssd1322Builder::new()
+                .preset(preset)
+                .build(RecordingInterface::<256>::new());
+            disp.init().unwrap();
+            disp.release()
+        });
+
+        assert!(ifaces[0].has_command(&[0xB1, 0xE2]));
+        assert!(ifaces[0].has_command(&[0xD1, 0x82, 0x20]));
+        assert!(ifaces[0].has_command(&[0xC1, 0x9F]));
+        assert!(ifaces[1].has_command(&[0xB1, 0xE8]));
+        assert!(ifaces[1].has_command(&[0xD1, 0xA2, 0x20]));
+        assert!(ifaces[1].has_command(&[0xC1, 0xFF]));
+        assert!(ifaces[2].has_command(&[0xB1, 0x74]));
+        assert!(ifaces[2].has_command(&[0xB4, 0xA2, 0xFD]));
+        assert!(ifaces[2].has_command(&[0xBB, 0x17]));
+        assert!(ifaces[2].has_command(&[0xBE, 0x04]));
+        assert!(ifaces[2].has_command(&[0xC1, 0x7F]));
+        for (i, a) in ifaces.iter().enumerate() {
+            assert!(!a.is_overflowed());
+            for b in &ifaces[i + 1..] {
+                assert!(!a.sent().eq(b.sent()));
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! cloned display module
+use crate::display::Ssd1322;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+#[cfg(feature = "graphics")]
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Size},
+    primitives::Rectangle,
+    Pixel,
+};
+
+/// Shows the same content on several panels, e.g. for signage with repeated displays. There is
+/// a single framebuffer, which each flush sends to the interface of every panel in turn.
+///
+/// The driver holds the framebuffer and the interface of the first panel, the interfaces of the
+/// `N` other panels are swapped in to send the same commands and data to them. All panels share
+/// the configuration of the driver.
+///
+/// ```ignore
+/// let mut clone = CloneDisplay::new(Ssd1322::new(first), [second, third]);
+/// clone.init()?;
+/// Text::new("Hello", Point::new(0, 10), style).draw(&mut clone)?;
+/// clone.flush()?;
+/// ```
+pub struct CloneDisplay<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize> {
+    display: Ssd1322<DI, WIDTH, HEIGHT, B>,
+    others: [DI; N],
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize>
+    CloneDisplay<DI, WIDTH, HEIGHT, B, N>
+{
+    /// Creates the display from the driver of the first panel and the interfaces of the others.
+    pub fn new(display: Ssd1322<DI, WIDTH, HEIGHT, B>, others: [DI; N]) -> Self {
+        Self { display, others }
+    }
+
+    /// Returns the driver, which holds the framebuffer.
+    pub fn display(&self) -> &Ssd1322<DI, WIDTH, HEIGHT, B> {
+        &self.display
+    }
+
+    /// Returns the driver, e.g. to draw with `set_pixel`. Commands sent to it only reach the
+    /// first panel, see `for_each`.
+    pub fn display_mut(&mut self) -> &mut Ssd1322<DI, WIDTH, HEIGHT, B> {
+        &mut self.display
+    }
+
+    /// Returns the driver and the interfaces of the other panels.
+    pub fn release(self) -> (Ssd1322<DI, WIDTH, HEIGHT, B>, [DI; N]) {
+        (self.display, self.others)
+    }
+
+    /// Calls `f` with the driver for every panel, e.g. to change the brightness of all of them.
+    ///
+    /// Each call starts from the same changed areas, start line and command lock, so the
+    /// flushes, scrolling and page flipping show the same on every panel. `flush_diff` and the
+    /// resumable flushes are not supported, as they keep state outside of the driver.
+    pub fn for_each<F>(&mut self, mut f: F) -> Result<(), DisplayError>
+    where
+        F: FnMut(&mut Ssd1322<DI, WIDTH, HEIGHT, B>) -> Result<(), DisplayError>,
+    {
+        let display = &mut self.display;
+        let dirty = display.dirty.clone();
+        let state = (display.num_changed, display.start_line, display.locked);
+        f(display)?;
+
+        for other in self.others.iter_mut() {
+            display.dirty.clone_from(&dirty);
+            (display.num_changed, display.start_line, display.locked) = state;
+            core::mem::swap(&mut display.display, other);
+            let result = f(display);
+            core::mem::swap(&mut display.display, other);
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize>
+    CloneDisplay<DI, WIDTH, HEIGHT, B, N>
+where
+    DI: WriteOnlyDataCommand,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Initializes every panel.
+    pub fn init(&mut self) -> Result<(), DisplayError> {
+        self.for_each(|display| display.init())
+    }
+
+    /// Flushes the changed portion of the framebuffer to every panel.
+    pub fn flush(&mut self) -> Result<(), DisplayError> {
+        self.for_each(|display| display.flush())
+    }
+
+    /// Flushes the entire framebuffer to every panel.
+    pub fn flush_all(&mut self) -> Result<(), DisplayError> {
+        self.for_each(|display| display.flush_all())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize> DrawTarget
+    for CloneDisplay<DI, WIDTH, HEIGHT, B, N>
+where
+    Ssd1322<DI, WIDTH, HEIGHT, B>: DrawTarget,
+{
+    type Color = <Ssd1322<DI, WIDTH, HEIGHT, B> as DrawTarget>::Color;
+    type Error = <Ssd1322<DI, WIDTH, HEIGHT, B> as DrawTarget>::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display.draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.display.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.clear(color)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize> OriginDimensions
+    for CloneDisplay<DI, WIDTH, HEIGHT, B, N>
+{
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    /// Returns the number of GDDRAM bytes written to a panel.
+    fn gddram_bytes(iface: &RecordingInterface) -> usize {
+        iface.windows().map(|window| window.data.len()).sum()
+    }
+
+    #[test]
+    /// Tests that every panel is sent the changes of the framebuffer.
+    fn clone_display() {
+        let mut clone = CloneDisplay::new(
+            Ssd1322::<_>::new(RecordingInterface::new()),
+            [RecordingInterface::new(), RecordingInterface::new()],
+        );
+
+        clone.display_mut().set_pixel(0, 0, 0x0F);
+        clone.display_mut().set_pixel(100, 20, 0x0F);
+        clone.flush().unwrap();
+        clone.for_each(|display| display.scroll_to_line(8)).unwrap();
+
+        let (display, others) = clone.release();
+        assert_eq!(display.start_line(), 8);
+        let [first, second] = others;
+        let written = gddram_bytes(&display.display);
+        assert!(written > 0);
+        assert_eq!(gddram_bytes(&first), written);
+        assert_eq!(gddram_bytes(&second), written);
+        assert!(first.has_command(&[0xA1, 8]));
+    }
+}
@@ -1,65 +1,112 @@
+//! ssd1322 command module
+use crate::config::{DisplayClock, GpioState, GrayScaleQuality, PhaseLength, VddSource, Vsl};
 use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
 
-/// ssd1322 Commands
-
-/// Commands - subset of the supported commands
-#[derive(Debug)]
-#[allow(dead_code)]
+/// The commands of the SSD1322, see `Ssd1322::send_command` and `Ssd1322::send_commands`. The
+/// arguments are the bytes of the datasheet unless a typed argument is taken.
+#[derive(Debug, Clone, Copy)]
 pub enum Command {
+    /// Unlocks the command interface (FDh).
     Unlock,
+    /// Locks the command interface, every command but `Unlock` is ignored (FDh).
+    Lock,
+    /// Sets the first and last column address of the address window (15h).
     SetColumnAddress(u8, u8),
+    /// Sets the first and last row of the address window (75h).
     SetRowAddress(u8, u8),
-    SetDisplayClock(u8),
+    /// Sets the front clock divider and oscillator frequency (B3h).
+    SetDisplayClock(DisplayClock),
+    /// Sets the mux ratio, the number of driven rows minus 1 (CAh).
     SetMuxRatio(u8),
+    /// Sets the vertical shift of the COM lines (A2h).
     SetDisplayOffset(u8),
+    /// Sets the GDDRAM row shown at the top (A1h).
     SetStartLine(u8),
+    /// Sets the remap and dual COM line mode bytes (A0h).
     SetRemapFormat(u8, u8),
-    SetGPIO(u8),
-    SetFunctionSelection(u8),
-    SetDisplayEnhancementA(u8, u8),
+    /// Sets the modes of the GPIO0 and GPIO1 pins (B5h).
+    SetGPIO(GpioState, GpioState),
+    /// Selects the internal VDD regulator or an external VDD (ABh).
+    SetFunctionSelection(VddSource),
+    /// Selects the VSL source and the display quality of the low gray levels (B4h).
+    SetDisplayEnhancementA(Vsl, GrayScaleQuality),
+    /// Sets the contrast current (C1h).
     SetContrastCurrent(u8),
+    /// Sets the master contrast current control, 0x00 to 0x0F (C7h).
     SetMasterCurrent(u8),
+    /// Sets the pulse widths of gray levels 1 to 15 (B8h).
+    SetGrayScaleTable([u8; 15]),
+    /// Selects the default linear gray scale table (B9h).
     SetLinearGrayScaleTable,
-    SetPhaseLength(u8),
+    /// Enables the gray scale table set by `SetGrayScaleTable` (00h).
+    EnableGrayScaleTable,
+    /// Sets the reset and pre-charge phase lengths (B1h).
+    SetPhaseLength(PhaseLength),
+    /// Sets the display enhancement B bytes (D1h).
     SetDisplayEnhancementB(u8, u8),
+    /// Sets the pre-charge voltage, 0x00 to 0x1F (BBh).
     SetPrechargeVoltage(u8),
+    /// Sets the second pre-charge period, 1 to 15 display clocks (B6h).
     SetPrechargePeriod(u8),
+    /// Sets the COM deselect voltage level, 0x00 to 0x07 (BEh).
     SetVCOMH(u8),
+    /// Shows the GDDRAM content (A6h).
     NormalDisplayMode,
+    /// Shows the GDDRAM content with inverted gray levels (A7h).
+    InverseDisplayMode,
+    /// Shows every pixel at gray level 15, regardless of the GDDRAM (A5h).
     AllPixelsOn,
+    /// Shows every pixel at gray level 0, regardless of the GDDRAM (A4h).
     AllPixelsOff,
+    /// Only drives the rows from the first to the second argument (A8h).
+    EnterPartialDisplay(u8, u8),
+    /// Drives the full panel again (A9h).
     ExitPartialDisplay,
+    /// Starts writing data to the address window (5Ch).
     WriteRAM,
+    /// Starts reading data from the address window (5Dh).
+    ReadRAM,
+    /// Turns the display on, leaving the sleep mode (AFh).
     DisplayOn,
+    /// Turns the display off and enters the sleep mode (AEh).
     DisplayOff,
+    /// Does nothing (E3h).
+    Nop,
 }
 
+/// Longest command, the gray scale table with its 15 data bytes.
+const MAX_COMMAND_LEN: usize = 16;
+
 impl Command {
     /// Send command to ssd1322
-    pub fn send<DI>(self, iface: &mut DI) -> Result<(), DisplayError>
+    pub(crate) fn send<DI>(self, iface: &mut DI) -> Result<(), DisplayError>
     where
         DI: WriteOnlyDataCommand,
     {
-        let mut handle_command = |data: &[u8]| {
-            // Send command over the interface
-            let _ = iface.send_commands(DataFormat::U8(&data[0..1]));
+        send_all(core::iter::once(self), iface)
+    }
 
-            // If the command has any data portion then send that also
-            if data.len() > 1 {
-                let _ = iface.send_data(DataFormat::U8(&data[1..data.len()]));
-            }
+    /// Returns the command byte followed by the data bytes, and the number of bytes.
+    fn encode(self) -> ([u8; MAX_COMMAND_LEN], usize) {
+        let handle_command = |data: &[u8]| {
+            let mut bytes = [0; MAX_COMMAND_LEN];
+            bytes[..data.len()].copy_from_slice(data);
+            (bytes, data.len())
         };
 
         match self {
             // Set command unlock
             Command::Unlock => handle_command(&[0xFD, 0x12]),
 
+            // Set command lock
+            Command::Lock => handle_command(&[0xFD, 0x16]),
+
             // Set the bounding box
             Command::SetColumnAddress(a, b) => handle_command(&[0x15, a, b]),
             Command::SetRowAddress(a, b) => handle_command(&[0x75, a, b]),
 
             // Set the divide and osc freq
-            Command::SetDisplayClock(a) => handle_command(&[0xB3, a]),
+            Command::SetDisplayClock(clock) => handle_command(&[0xB3, clock.byte()]),
 
             // Set the Multiplex ratio
             Command::SetMuxRatio(a) => handle_command(&[0xCA, a]),
@@ -74,13 +121,31 @@ impl Command {
             Command::SetRemapFormat(a, b) => handle_command(&[0xA0, a, b]),
 
             // GPIO pins
-            Command::SetGPIO(a) => handle_command(&[0xB5, a]),
+            Command::SetGPIO(gpio0, gpio1) => {
+                handle_command(&[0xB5, (gpio1 as u8) << 2 | gpio0 as u8])
+            }
 
             // Function selection
-            Command::SetFunctionSelection(a) => handle_command(&[0xAB, a]),
+            Command::SetFunctionSelection(vdd) => handle_command(&[
+                0xAB,
+                match vdd {
+                    VddSource::Internal => 0x01,
+                    VddSource::External => 0x00,
+                },
+            ]),
 
             // Set Display Enhancement A
-            Command::SetDisplayEnhancementA(a, b) => handle_command(&[0xB4, a, b]),
+            Command::SetDisplayEnhancementA(vsl, quality) => {
+                let vsl = match vsl {
+                    Vsl::External => 0xA0,
+                    Vsl::Internal => 0xA2,
+                };
+                let quality = match quality {
+                    GrayScaleQuality::Normal => 0xB5,
+                    GrayScaleQuality::Enhanced => 0xFD,
+                };
+                handle_command(&[0xB4, vsl, quality])
+            }
 
             // Set Contrast current
             Command::SetContrastCurrent(a) => handle_command(&[0xC1, a]),
@@ -88,11 +153,21 @@ impl Command {
             // Set Master current
             Command::SetMasterCurrent(a) => handle_command(&[0xC7, a]),
 
+            // Set gray scale table
+            Command::SetGrayScaleTable(table) => {
+                let mut data = [0xB8; 16];
+                data[1..].copy_from_slice(&table);
+                handle_command(&data)
+            }
+
+            // Enable the gray scale table
+            Command::EnableGrayScaleTable => handle_command(&[0x00]),
+
             // Set linear gray scale table
             Command::SetLinearGrayScaleTable => handle_command(&[0xB9]),
 
             // Set phase length
-            Command::SetPhaseLength(a) => handle_command(&[0xB1, a]),
+            Command::SetPhaseLength(phases) => handle_command(&[0xB1, phases.byte()]),
 
             // Set Display Enhancement B
             Command::SetDisplayEnhancementB(a, b) => handle_command(&[0xD1, a, b]),
@@ -109,25 +184,133 @@ impl Command {
             // Set normal display mode
             Command::NormalDisplayMode => handle_command(&[0xA6]),
 
+            // Set inverse display mode
+            Command::InverseDisplayMode => handle_command(&[0xA7]),
+
             // Set all pixels off
             Command::AllPixelsOff => handle_command(&[0xA4]),
 
             // Set all pixels on
             Command::AllPixelsOn => handle_command(&[0xA5]),
 
+            // Enter partial display
+            Command::EnterPartialDisplay(a, b) => handle_command(&[0xA8, a, b]),
+
             // Exit partial display
             Command::ExitPartialDisplay => handle_command(&[0xA9]),
 
             // Write the data following this command
             Command::WriteRAM => handle_command(&[0x5C]),
 
+            // Read the data following this command
+            Command::ReadRAM => handle_command(&[0x5D]),
+
             // Sleep mode off
             Command::DisplayOn => handle_command(&[0xAF]),
 
             // Sleep mode on
             Command::DisplayOff => handle_command(&[0xAE]),
-        };
 
-        Ok(())
+            // No operation
+            Command::Nop => handle_command(&[0xE3]),
+        }
+    }
+}
+
+/// Sends the `commands` to ssd1322. The command bytes of consecutive commands are sent together
+/// until a command has data, so the D/C line only changes between commands and data.
+pub(crate) fn send_all<DI, I>(commands: I, iface: &mut DI) -> Result<(), DisplayError>
+where
+    DI: WriteOnlyDataCommand,
+    I: IntoIterator<Item = Command>,
+{
+    let mut pending = [0; MAX_COMMAND_LEN];
+    let mut len = 0;
+    for command in commands {
+        let (bytes, command_len) = command.encode();
+        if len == pending.len() {
+            iface.send_commands(DataFormat::U8(&pending))?;
+            len = 0;
+        }
+        pending[len] = bytes[0];
+        len += 1;
+
+        // If the command has any data portion then send that also
+        if command_len > 1 {
+            iface.send_commands(DataFormat::U8(&pending[..len]))?;
+            len = 0;
+            iface.send_data(DataFormat::U8(&bytes[1..command_len]))?;
+        }
+    }
+
+    if len > 0 {
+        iface.send_commands(DataFormat::U8(&pending[..len]))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    #[test]
+    /// Tests the bytes of commands without and with typed arguments.
+    fn command_bytes() {
+        let mut iface = RecordingInterface::<8>::new();
+        Command::Nop.send(&mut iface).unwrap();
+        assert!(iface.sent().eq([(false, 0xE3)]));
+
+        let mut iface = RecordingInterface::<8>::new();
+        Command::SetDisplayEnhancementA(Vsl::Internal, GrayScaleQuality::Enhanced)
+            .send(&mut iface)
+            .unwrap();
+        assert!(iface.sent().eq([(false, 0xB4), (true, 0xA2), (true, 0xFD)]));
+
+        let mut iface = RecordingInterface::<8>::new();
+        Command::SetDisplayEnhancementA(Vsl::External, GrayScaleQuality::Normal)
+            .send(&mut iface)
+            .unwrap();
+        assert!(iface.sent().eq([(false, 0xB4), (true, 0xA0), (true, 0xB5)]));
+
+        let mut iface = RecordingInterface::<8>::new();
+        Command::SetGPIO(GpioState::OutputHigh, GpioState::InputEnabled)
+            .send(&mut iface)
+            .unwrap();
+        assert!(iface.sent().eq([(false, 0xB5), (true, 0x07)]));
+
+        let mut iface = RecordingInterface::<8>::new();
+        Command::SetFunctionSelection(VddSource::External)
+            .send(&mut iface)
+            .unwrap();
+        assert!(iface.sent().eq([(false, 0xAB), (true, 0x00)]));
+    }
+
+    #[test]
+    /// Tests that the command bytes of consecutive commands are sent together.
+    fn send_all_batches() {
+        let mut iface = RecordingInterface::<8>::new();
+        send_all(
+            [
+                Command::DisplayOff,
+                Command::NormalDisplayMode,
+                Command::SetMuxRatio(0x3F),
+                Command::DisplayOn,
+                Command::Nop,
+            ],
+            &mut iface,
+        )
+        .unwrap();
+
+        assert!(iface.sent().eq([
+            (false, 0xAE),
+            (false, 0xA6),
+            (false, 0xCA),
+            (true, 0x3F),
+            (false, 0xAF),
+            (false, 0xE3),
+        ]));
+        assert_eq!(iface.transfers(), 3);
     }
 }
@@ -0,0 +1,710 @@
+//! panel configuration module
+use display_interface::DisplayError;
+
+/// Describes how a panel is wired to the SSD1322 GDDRAM and the register values `init` programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayConfig {
+    /// GDDRAM column address of the first panel column. Each column address covers 4 pixels.
+    pub col_offset: u8,
+    /// Vertical shift, in rows, between the first COM line and the first panel row. This is
+    /// programmed as the display offset.
+    pub row_offset: u8,
+    /// Number of rows of the panel, programmed as the mux ratio. This is usually the framebuffer
+    /// height, a smaller value makes the framebuffer a virtual one that is panned with
+    /// `Ssd1322::pan_to`.
+    pub display_rows: u8,
+    /// Front clock divider and oscillator frequency (command B3h), see `DisplayClock` for the
+    /// fields. `init` rejects a divider above 1024.
+    pub clock: u8,
+    /// Remap and dual COM line mode bytes (command A0h), see `RemapConfig` for the bits.
+    pub remap: [u8; 2],
+    /// Modes of the GPIO0 and GPIO1 pins (command B5h), see `Ssd1322::set_gpio`.
+    pub gpio: [GpioState; 2],
+    /// Order of the two pixels of each byte sent to the GDDRAM.
+    pub nibble_order: NibbleOrder,
+    /// Contrast current (command C1h).
+    pub contrast: u8,
+    /// Master contrast current control, 0x00 to 0x0F (command C7h).
+    pub master_current: u8,
+    /// The driving registers programmed by `init`, see `Ssd1322::init_with`.
+    pub init: InitConfig,
+    /// Display rotation.
+    pub rotation: DisplayRotation,
+    /// Mirrors the content horizontally using the column address remap.
+    pub mirror_horizontal: bool,
+    /// Mirrors the content vertically using the COM scan direction.
+    pub mirror_vertical: bool,
+    /// Largest number of bytes the flush methods send in one data transfer, for interfaces or
+    /// DMA engines that limit the length of a transfer. `usize::MAX` doesn't split the
+    /// transfers, except for `DataWidth::U16`, and 0 is treated as 1.
+    pub max_transfer: usize,
+    /// Width of the words the flush methods send, see `DataWidth`.
+    pub data_width: DataWidth,
+    /// Timing and polarity of the reset line driven by `Ssd1322::reset`.
+    pub reset: ResetConfig,
+    /// How `flush` and `flush_all` recover from a failed transfer, see `RetryPolicy`.
+    pub retry: RetryPolicy,
+}
+
+impl DisplayConfig {
+    /// Returns the configuration of a `width` x `height` panel that is centred in the 480 pixel
+    /// wide GDDRAM, which is how most modules (e.g. 256 pixel wide panels at column 0x1C) are
+    /// wired.
+    pub const fn for_panel(width: usize, height: usize) -> Self {
+        Self {
+            col_offset: (120_usize.saturating_sub(width / 4) / 2) as u8,
+            row_offset: 0,
+            display_rows: height as u8,
+            clock: 0x91,
+            // Dual COM line mode is only available up to a mux ratio of 64, so taller panels
+            // disable it.
+            remap: RemapConfig::new()
+                .nibble_remap(true)
+                .com_scan_reversed(true)
+                .dual_com(height <= 64)
+                .bytes(),
+            gpio: [GpioState::InputDisabled; 2],
+            nibble_order: NibbleOrder::LeftUpper,
+            contrast: 0xCF,
+            master_current: 0x0F,
+            init: InitConfig::DEFAULT,
+            rotation: DisplayRotation::Rotate0,
+            mirror_horizontal: false,
+            mirror_vertical: false,
+            max_transfer: usize::MAX,
+            data_width: DataWidth::U8,
+            reset: ResetConfig::DEFAULT,
+            retry: RetryPolicy::NONE,
+        }
+    }
+}
+
+/// The driving registers that `init` programs besides the ones of `DisplayConfig`, e.g. to use
+/// the init sequence a panel vendor recommends. The defaults suit most 256x64 modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitConfig {
+    /// Reset and pre-charge phase lengths (command B1h).
+    pub phase_length: PhaseLength,
+    /// Segment low voltage source (command B4h).
+    pub vsl: Vsl,
+    /// Display quality of the low gray levels (command B4h).
+    pub gray_scale_quality: GrayScaleQuality,
+    /// Source of the core logic supply VDD (command ABh).
+    pub vdd: VddSource,
+    /// Display enhancement B bytes (command D1h).
+    pub enhancement_b: [u8; 2],
+    /// Pre-charge voltage, 0x00 to 0x1F (command BBh).
+    pub precharge_voltage: u8,
+    /// Second pre-charge period, 1 to 15 display clocks (command B6h).
+    pub precharge_period: u8,
+    /// COM deselect voltage level, 0x00 to 0x07 (command BEh).
+    pub vcomh: u8,
+}
+
+impl InitConfig {
+    /// The registers programmed by `init` with the default configuration.
+    pub const DEFAULT: InitConfig = InitConfig {
+        phase_length: PhaseLength::DEFAULT,
+        vsl: Vsl::External,
+        gray_scale_quality: GrayScaleQuality::Enhanced,
+        vdd: VddSource::Internal,
+        enhancement_b: [0xA2, 0x20],
+        precharge_voltage: 0x1F,
+        precharge_period: 0x08,
+        vcomh: 0x07,
+    };
+
+    /// Returns the registers that set how the pixels are driven.
+    pub const fn drive(&self) -> DriveConfig {
+        DriveConfig {
+            phase_length: self.phase_length,
+            precharge_voltage: self.precharge_voltage,
+            precharge_period: self.precharge_period,
+            vcomh: self.vcomh,
+        }
+    }
+
+    /// Returns true if every register is in range.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.drive().is_valid()
+    }
+}
+
+impl Default for InitConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The registers that set how the pixels are driven, which `Ssd1322::set_drive_config` changes
+/// at runtime, e.g. to tune ghosting or the uniformity of a panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriveConfig {
+    /// Reset and pre-charge phase lengths (command B1h).
+    pub phase_length: PhaseLength,
+    /// Pre-charge voltage, 0x00 to 0x1F (command BBh).
+    pub precharge_voltage: u8,
+    /// Second pre-charge period, 1 to 15 display clocks (command B6h).
+    pub precharge_period: u8,
+    /// COM deselect voltage level, 0x00 to 0x07 (command BEh).
+    pub vcomh: u8,
+}
+
+impl DriveConfig {
+    /// The registers programmed by `init` with the default configuration.
+    pub const DEFAULT: DriveConfig = InitConfig::DEFAULT.drive();
+
+    /// Returns true if every register is in range.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.precharge_voltage <= 0x1F
+            && (1..=0x0F).contains(&self.precharge_period)
+            && self.vcomh <= 0x07
+    }
+}
+
+impl Default for DriveConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Init presets of common modules, with the driving registers and contrast of the init sequence
+/// in the vendor's datasheet, see `Ssd1322Builder::preset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelPreset {
+    /// Newhaven Display NHD-3.12-25664UCB2 and the other NHD-3.12 256x64 modules.
+    Nhd312,
+    /// EastRising (buydisplay) ER-OLEDM032-1 256x64 modules.
+    ErOledM032,
+    /// Midas MDOB256064 256x64 modules.
+    MidasMdob256064,
+}
+
+impl PanelPreset {
+    /// Returns the driving registers of the preset.
+    pub const fn init_config(self) -> InitConfig {
+        match self {
+            PanelPreset::Nhd312 => InitConfig {
+                enhancement_b: [0x82, 0x20],
+                ..InitConfig::DEFAULT
+            },
+            PanelPreset::ErOledM032 => InitConfig {
+                phase_length: PhaseLength { byte: 0xE8 },
+                ..InitConfig::DEFAULT
+            },
+            PanelPreset::MidasMdob256064 => InitConfig {
+                phase_length: PhaseLength { byte: 0x74 },
+                vsl: Vsl::Internal,
+                precharge_voltage: 0x17,
+                vcomh: 0x04,
+                ..InitConfig::DEFAULT
+            },
+        }
+    }
+
+    /// Returns the contrast current of the preset.
+    pub const fn contrast(self) -> u8 {
+        match self {
+            PanelPreset::Nhd312 => 0x9F,
+            PanelPreset::ErOledM032 => 0xFF,
+            PanelPreset::MidasMdob256064 => 0x7F,
+        }
+    }
+}
+
+/// Front clock divider and oscillator frequency of the display clock (command B3h).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayClock {
+    byte: u8,
+}
+
+impl DisplayClock {
+    /// The clock used by `init` with the default configuration, the front clock divided by 2 at
+    /// oscillator frequency 9.
+    pub const DEFAULT: DisplayClock = DisplayClock { byte: 0x91 };
+
+    /// Creates the clock setting from the front clock divider, a power of 2 from 1 to 1024, and
+    /// the oscillator frequency setting from 0 to 15.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if either is out of range.
+    pub const fn new(divider: u16, frequency: u8) -> Result<Self, DisplayError> {
+        if !divider.is_power_of_two() || divider > 1024 || frequency > 0x0F {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        Ok(Self {
+            byte: (frequency << 4) | divider.trailing_zeros() as u8,
+        })
+    }
+
+    /// Creates the clock setting from the command byte, with the oscillator frequency in the
+    /// upper nibble and the log2 of the divider in the lower nibble.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if the divider is above 1024.
+    pub const fn from_byte(byte: u8) -> Result<Self, DisplayError> {
+        if byte & 0x0F > 10 {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        Ok(Self { byte })
+    }
+
+    /// Returns the command byte.
+    pub const fn byte(self) -> u8 {
+        self.byte
+    }
+
+    /// Returns the front clock divider, a power of 2 from 1 to 1024.
+    pub const fn divider(self) -> u16 {
+        1 << (self.byte & 0x0F)
+    }
+
+    /// Returns the oscillator frequency setting from 0 to 15.
+    pub const fn frequency(self) -> u8 {
+        self.byte >> 4
+    }
+
+    /// Estimates the frame rate in Hz, e.g. to trade flicker against power, as
+    /// `oscillator_hz / (divider * row_clocks * rows)`.
+    ///
+    /// `oscillator_hz` is the oscillator frequency at the `frequency` setting, which varies
+    /// between chips, see the datasheet or measure the CLK pin. `row_clocks` is the number of
+    /// display clocks per row, the lengths of `PhaseLength` plus the current drive period, which
+    /// is about the pulse width of gray level 15. `rows` is the mux ratio.
+    ///
+    /// Returns 0 if a frame takes no clocks or more than `u32::MAX`.
+    pub const fn frame_rate(self, oscillator_hz: u32, row_clocks: u32, rows: u8) -> u32 {
+        let clocks = match (self.divider() as u32).checked_mul(row_clocks) {
+            Some(clocks) => clocks.checked_mul(rows as u32),
+            None => None,
+        };
+
+        match clocks {
+            Some(clocks) if clocks > 0 => oscillator_hz / clocks,
+            _ => 0,
+        }
+    }
+}
+
+/// Lengths of the reset phase (phase 1) and the first pre-charge phase (phase 2) of each pixel
+/// drive cycle, in display clocks (command B1h).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseLength {
+    byte: u8,
+}
+
+impl PhaseLength {
+    /// The phase lengths used by `init` with the default configuration, 5 and 14 display clocks.
+    pub const DEFAULT: PhaseLength = PhaseLength { byte: 0xE2 };
+
+    /// Creates the phase lengths from the reset phase, an odd number of display clocks from 5 to
+    /// 31, and the pre-charge phase, from 3 to 15 display clocks.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if either is out of range.
+    pub const fn new(phase1: u8, phase2: u8) -> Result<Self, DisplayError> {
+        if phase1 < 5 || phase1 > 31 || phase1.is_multiple_of(2) || phase2 < 3 || phase2 > 15 {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        Ok(Self {
+            byte: (phase2 << 4) | ((phase1 - 1) / 2),
+        })
+    }
+
+    /// Returns the command byte.
+    pub const fn byte(self) -> u8 {
+        self.byte
+    }
+
+    /// Returns the length of the reset phase, in display clocks.
+    pub const fn phase1(self) -> u8 {
+        (self.byte & 0x0F) * 2 + 1
+    }
+
+    /// Returns the length of the pre-charge phase, in display clocks.
+    pub const fn phase2(self) -> u8 {
+        self.byte >> 4
+    }
+}
+
+/// Composes the two bytes of the remap and dual COM line mode command (A0h).
+///
+/// ```ignore
+/// let remap = RemapConfig::new()
+///     .nibble_remap(true)
+///     .com_scan_reversed(true)
+///     .dual_com(true);
+/// assert_eq!(remap.bytes(), [0x14, 0x11]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemapConfig {
+    bytes: [u8; 2],
+}
+
+impl RemapConfig {
+    /// Creates the configuration with every bit cleared: horizontal address increment, no
+    /// remaps, COM0 scanned first, no COM split and the dual COM line mode disabled.
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0x00, 0x01],
+        }
+    }
+
+    /// Increments the row address after each byte instead of the column address.
+    pub const fn vertical_address_increment(self, enabled: bool) -> Self {
+        self.with_bit(0, 0x01, enabled)
+    }
+
+    /// Maps the column addresses in reverse, mirroring the columns.
+    pub const fn column_remap(self, enabled: bool) -> Self {
+        self.with_bit(0, 0x02, enabled)
+    }
+
+    /// Swaps the order of the 4 nibbles of each column address.
+    pub const fn nibble_remap(self, enabled: bool) -> Self {
+        self.with_bit(0, 0x04, enabled)
+    }
+
+    /// Scans from the last COM line to COM0, mirroring the rows.
+    pub const fn com_scan_reversed(self, enabled: bool) -> Self {
+        self.with_bit(0, 0x10, enabled)
+    }
+
+    /// Splits the COM lines into odd and even ones, for panels wired that way.
+    pub const fn com_split(self, enabled: bool) -> Self {
+        self.with_bit(0, 0x20, enabled)
+    }
+
+    /// Enables the dual COM line mode, which is only available up to a mux ratio of 64.
+    pub const fn dual_com(self, enabled: bool) -> Self {
+        self.with_bit(1, 0x10, enabled)
+    }
+
+    /// Returns the command bytes.
+    pub const fn bytes(self) -> [u8; 2] {
+        self.bytes
+    }
+
+    const fn with_bit(mut self, byte: usize, mask: u8, enabled: bool) -> Self {
+        if enabled {
+            self.bytes[byte] |= mask;
+        } else {
+            self.bytes[byte] &= !mask;
+        }
+        self
+    }
+}
+
+impl Default for RemapConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Order of the two horizontally adjacent pixels in each byte of the framebuffer and the data
+/// sent to the GDDRAM. Modules whose panels show every pixel pair swapped use `LeftLower`.
+///
+/// The nibble remap of `RemapConfig` reverses all 4 pixels of a column address rather than a
+/// pair, so this is applied when packing the pixels instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NibbleOrder {
+    /// The left pixel is in the upper nibble, the default.
+    LeftUpper,
+    /// The left pixel is in the lower nibble.
+    LeftLower,
+}
+
+impl NibbleOrder {
+    /// Converts a byte with the left pixel in the upper nibble to this order, or back.
+    pub(crate) const fn arrange(self, byte: u8) -> u8 {
+        match self {
+            NibbleOrder::LeftUpper => byte,
+            NibbleOrder::LeftLower => byte.rotate_left(4),
+        }
+    }
+}
+
+/// Width of the words in which the flush methods send the framebuffer to the interface.
+///
+/// display-interface 0.4 has no 32 bit format, so 16 bit words are the widest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataWidth {
+    /// Bytes, as `DataFormat::U8`, which every interface takes. The default.
+    U8,
+    /// Big endian 16 bit words, as `DataFormat::U16BE`, for interfaces whose bus or DMA is
+    /// faster with 16 bit frames. The bytes reach the GDDRAM in the same order.
+    ///
+    /// The words are collected on the stack, so a transfer is at most 128 words (256 bytes)
+    /// long, even if `DisplayConfig::max_transfer` is larger.
+    U16,
+}
+
+/// Timing and polarity of the reset line, see `Ssd1322::reset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetConfig {
+    /// Length of the reset pulse, in milliseconds.
+    pub pulse_ms: u8,
+    /// Time to wait after the reset is released before sending commands, in milliseconds.
+    pub wait_ms: u8,
+    /// The reset is asserted by driving the line high, for boards that invert the reset line
+    /// through a transistor.
+    pub active_high: bool,
+}
+
+impl ResetConfig {
+    /// A 10 ms active low pulse and a 200 ms wait, which suit most modules.
+    pub const DEFAULT: ResetConfig = ResetConfig {
+        pulse_ms: 10,
+        wait_ms: 200,
+        active_high: false,
+    };
+}
+
+/// How `Ssd1322::flush` and `Ssd1322::flush_all` recover from a failed transfer, e.g. a glitch
+/// on the bus, so the panel doesn't keep showing a partial frame.
+///
+/// Only `DisplayError::BusWriteError` is retried. Other errors, e.g. of the command lock, are
+/// returned at once, and a locked driver is never reinitialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of times a failed flush is repeated.
+    pub retries: u8,
+    /// Once the retries failed, reinitializes the display and sends the whole framebuffer with
+    /// `Ssd1322::reinitialize`, for glitches that leave the controller in an unknown state.
+    pub reinitialize: bool,
+}
+
+impl RetryPolicy {
+    /// Returns the error of a failed flush without retrying, the default.
+    pub const NONE: RetryPolicy = RetryPolicy {
+        retries: 0,
+        reinitialize: false,
+    };
+}
+
+/// Mode of a GPIO pin of the controller (command B5h). Some modules switch the panel supply or
+/// other circuitry with these pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpioState {
+    /// The pin is a high impedance input with the input buffer disabled.
+    InputDisabled = 0b00,
+    /// The pin is a high impedance input.
+    InputEnabled = 0b01,
+    /// The pin drives low.
+    OutputLow = 0b10,
+    /// The pin drives high.
+    OutputHigh = 0b11,
+}
+
+/// Source of the core logic supply VDD, which depends on how the module is wired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VddSource {
+    /// The internal VDD regulator, the default, powered from VCI.
+    Internal,
+    /// An external VDD supply.
+    External,
+}
+
+/// Source of the segment low voltage (VSL), which depends on how the module is wired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vsl {
+    /// An external VSL, the default, with the VSL pin wired to a resistor and capacitor.
+    External,
+    /// The internal VSL.
+    Internal,
+}
+
+/// Display quality of the low gray levels, set together with the VSL source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrayScaleQuality {
+    /// The normal quality (B5h).
+    Normal,
+    /// The enhanced low gray scale display quality (FDh), the default.
+    Enhanced,
+}
+
+/// Display brightness, set by the contrast current and the master current that scales it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Brightness {
+    pub(crate) contrast: u8,
+    pub(crate) master_current: u8,
+}
+
+impl Brightness {
+    /// The dimmest preset.
+    pub const DIMMEST: Brightness = Brightness::custom(0x1F, 0x04);
+    /// A dim preset, e.g. for use at night.
+    pub const DIM: Brightness = Brightness::custom(0x4F, 0x08);
+    /// A medium preset.
+    pub const NORMAL: Brightness = Brightness::custom(0x7F, 0x0C);
+    /// A bright preset, the brightness set by `init` with the default configuration.
+    pub const BRIGHT: Brightness = Brightness::custom(0xCF, 0x0F);
+    /// The brightest preset.
+    pub const BRIGHTEST: Brightness = Brightness::custom(0xFF, 0x0F);
+
+    /// Creates a brightness from a contrast current and a master current. The master current is
+    /// limited to its 4 bits.
+    pub const fn custom(contrast: u8, master_current: u8) -> Self {
+        Self {
+            contrast,
+            master_current: master_current & 0x0F,
+        }
+    }
+
+    /// Returns the contrast current.
+    pub const fn contrast(&self) -> u8 {
+        self.contrast
+    }
+
+    /// Returns the master current.
+    pub const fn master_current(&self) -> u8 {
+        self.master_current
+    }
+}
+
+/// Gray scale gamma curve presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gamma {
+    /// The built-in linear gray scale table set by `init`.
+    Linear,
+    /// A gamma of 1.8, over the full pulse width range.
+    Gamma18,
+    /// A gamma of 2.2, over the full pulse width range.
+    Gamma22,
+}
+
+impl Gamma {
+    /// Returns the gray scale table of the preset, or `None` for the built-in linear table.
+    pub const fn table(self) -> Option<[u8; 15]> {
+        match self {
+            Gamma::Linear => None,
+            Gamma::Gamma18 => Some([
+                1, 5, 10, 17, 25, 35, 46, 58, 72, 87, 103, 120, 139, 159, 180,
+            ]),
+            Gamma::Gamma22 => Some([1, 2, 5, 10, 16, 24, 34, 45, 59, 74, 91, 110, 131, 155, 180]),
+        }
+    }
+}
+
+/// How the buffered mode tracks the changed parts of the framebuffer between flushes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirtyTracking {
+    /// Up to 4 rectangular areas, the default.
+    #[default]
+    Areas,
+    /// A span of changed columns per row. Rows that did not change are skipped, which suits
+    /// diagonal or scattered changes, at the cost of a window per run of rows with the same
+    /// span.
+    RowSpans,
+}
+
+/// How pixels drawn into the framebuffer are combined with the pixels already there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrawMode {
+    /// Drawn pixels replace the framebuffer contents, the default.
+    #[default]
+    Replace,
+    /// The gray levels are XORed, so drawing the same pixels again restores the framebuffer,
+    /// e.g. to erase a cursor or a rubber-band selection.
+    Xor,
+    /// The brighter of both gray levels is kept.
+    Max,
+    /// The gray levels are added, saturating at white.
+    Add,
+    /// The drawn gray level is blended over the framebuffer with an opacity from 0
+    /// (transparent) to 255 (opaque), e.g. for translucent overlays or to dim the background
+    /// behind a dialog by drawing black over it.
+    Blend(u8),
+}
+
+impl DrawMode {
+    /// Returns the gray level of a pixel at `old` after drawing `new` onto it.
+    pub(crate) fn apply(self, old: u8, new: u8) -> u8 {
+        match self {
+            DrawMode::Replace => new,
+            DrawMode::Xor => old ^ new,
+            DrawMode::Max => old.max(new),
+            DrawMode::Add => (old + new).min(0x0F),
+            DrawMode::Blend(alpha) => {
+                let alpha = u16::from(alpha);
+                ((u16::from(old) * (255 - alpha) + u16::from(new) * alpha + 127) / 255) as u8
+            }
+        }
+    }
+}
+
+/// Clockwise rotation of the displayed content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayRotation {
+    /// No rotation.
+    Rotate0,
+    /// Rotated by 90 degrees, the width and height are swapped.
+    Rotate90,
+    /// Rotated by 180 degrees, e.g. for panels mounted upside down.
+    Rotate180,
+    /// Rotated by 270 degrees, the width and height are swapped.
+    Rotate270,
+}
+
+impl DisplayRotation {
+    /// Returns true if the rotation is applied by flipping the remap register.
+    pub(crate) fn is_flipped(self) -> bool {
+        matches!(
+            self,
+            DisplayRotation::Rotate180 | DisplayRotation::Rotate270
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that the remap bits are composed into the command bytes.
+    fn remap_config() {
+        assert_eq!(RemapConfig::new().bytes(), [0x00, 0x01]);
+        assert_eq!(DisplayConfig::for_panel(256, 64).remap, [0x14, 0x11]);
+        assert_eq!(DisplayConfig::for_panel(256, 128).remap, [0x14, 0x01]);
+
+        let remap = RemapConfig::new()
+            .vertical_address_increment(true)
+            .column_remap(true)
+            .com_split(true)
+            .dual_com(true);
+        assert_eq!(remap.bytes(), [0x23, 0x11]);
+        assert_eq!(
+            remap.column_remap(false).dual_com(false).bytes(),
+            [0x21, 0x01]
+        );
+    }
+
+    #[test]
+    /// Tests that the typed command arguments are encoded and validated.
+    fn command_arguments() {
+        assert_eq!(DisplayClock::new(2, 9).unwrap(), DisplayClock::DEFAULT);
+        assert_eq!(DisplayClock::new(1024, 0).unwrap().byte(), 0x0A);
+        assert!(DisplayClock::new(3, 0).is_err());
+        assert!(DisplayClock::new(2048, 0).is_err());
+        assert!(DisplayClock::new(1, 16).is_err());
+        assert!(DisplayClock::from_byte(0xF0).is_ok());
+        assert!(DisplayClock::from_byte(0x9B).is_err());
+        let clock = DisplayClock::new(2, 9).unwrap();
+        assert_eq!((clock.divider(), clock.frequency()), (2, 9));
+        // 5 + 14 clocks of the phases and 180 of the current drive.
+        assert_eq!(clock.frame_rate(3_000_000, 199, 64), 117);
+        assert_eq!(clock.frame_rate(3_000_000, 0, 64), 0);
+        let clock = DisplayClock::new(1024, 9).unwrap();
+        assert_eq!(clock.frame_rate(u32::MAX, u32::MAX / 2, 64), 0);
+        assert_eq!(clock.frame_rate(u32::MAX, 65_535, 64), 1);
+
+        assert_eq!(PhaseLength::new(5, 14).unwrap(), PhaseLength::DEFAULT);
+        assert_eq!(PhaseLength::new(31, 3).unwrap().byte(), 0x3F);
+        assert!(PhaseLength::new(4, 8).is_err());
+        assert!(PhaseLength::new(6, 8).is_err());
+        assert!(PhaseLength::new(33, 8).is_err());
+        assert!(PhaseLength::new(5, 2).is_err());
+        let phases = PhaseLength::new(9, 7).unwrap();
+        assert_eq!((phases.phase1(), phases.phase2()), (9, 7));
+    }
+}
@@ -0,0 +1,344 @@
+//! changed area tracking module
+use crate::config::DirtyTracking;
+
+/// Maximum number of separately tracked changed areas.
+pub(crate) const MAX_DIRTY_AREAS: usize = 4;
+
+/// A new area is started instead of growing an existing one by more than this many bytes.
+const MAX_GROWTH: u32 = 64;
+
+/// A changed area of the framebuffer as inclusive byte columns and rows.
+pub(crate) type DirtyArea = ([u8; 2], [u8; 2]);
+
+/// Rows that can hold a changed span, every row of the GDDRAM.
+const MAX_ROWS: usize = 128;
+
+/// Marks a row without a changed span.
+const NO_SPAN: [u8; 2] = [u8::MAX, 0];
+
+/// The changed areas of the framebuffer.
+///
+/// With `DirtyTracking::Areas` changes close to an area grow it, changes far away from every
+/// area start a new one, so widgets at opposite corners of the display are flushed separately.
+/// When all areas are in use, the area that grows the least is extended and overlapping areas
+/// are merged.
+///
+/// With `DirtyTracking::RowSpans` every row has its own span of changed bytes, and consecutive
+/// rows with the same span are reported as one area.
+#[derive(Debug, Clone)]
+pub(crate) struct DirtyAreas {
+    storage: Storage,
+    /// Set by drawing in `UntrackedMode`, which doesn't add the drawn pixels.
+    drawn: bool,
+}
+
+/// The changes kept for each `DirtyTracking`, so the areas don't come with the spans.
+///
+/// The spans aren't boxed, as the driver doesn't need an allocator, so the storage is still the
+/// size of the spans.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+enum Storage {
+    Areas([Option<DirtyArea>; MAX_DIRTY_AREAS]),
+    RowSpans([[u8; 2]; MAX_ROWS]),
+}
+
+impl Default for DirtyAreas {
+    fn default() -> Self {
+        Self::new(DirtyTracking::Areas)
+    }
+}
+
+impl DirtyAreas {
+    /// Creates an empty set of changed areas.
+    pub(crate) const fn new(tracking: DirtyTracking) -> Self {
+        let storage = match tracking {
+            DirtyTracking::Areas => Storage::Areas([None; MAX_DIRTY_AREAS]),
+            DirtyTracking::RowSpans => Storage::RowSpans([NO_SPAN; MAX_ROWS]),
+        };
+
+        Self {
+            storage,
+            drawn: false,
+        }
+    }
+
+    /// Returns how the changes are tracked.
+    pub(crate) fn tracking(&self) -> DirtyTracking {
+        match self.storage {
+            Storage::Areas(_) => DirtyTracking::Areas,
+            Storage::RowSpans(_) => DirtyTracking::RowSpans,
+        }
+    }
+
+    /// Adds the byte at column `col` and row `row` to the changed areas.
+    pub(crate) fn add(&mut self, col: u8, row: u8) {
+        match &mut self.storage {
+            Storage::Areas(areas) => add_to_areas(areas, ([col, col], [row, row])),
+            Storage::RowSpans(spans) => add_to_span(spans, col, row),
+        }
+    }
+
+    /// Adds every byte of `area` to the changed areas.
+    pub(crate) fn add_area(&mut self, area: DirtyArea) {
+        match &mut self.storage {
+            Storage::Areas(areas) => add_to_areas(areas, area),
+            Storage::RowSpans(spans) => {
+                let (cols, rows) = area;
+                for row in rows[0]..=rows[1] {
+                    add_to_span(spans, cols[0], row);
+                    add_to_span(spans, cols[1], row);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if nothing changed.
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.drawn && self.iter().next().is_none()
+    }
+
+    /// Notes that something was drawn without adding it to the changed areas.
+    #[inline]
+    pub(crate) fn touch(&mut self) {
+        self.drawn = true;
+    }
+
+    /// Forgets all changes.
+    pub(crate) fn clear(&mut self) {
+        *self = Self::new(self.tracking());
+    }
+
+    /// Removes and returns the first of the changed areas.
+    pub(crate) fn pop(&mut self) -> Option<DirtyArea> {
+        let first = self.iter().next()?;
+        match &mut self.storage {
+            Storage::Areas(areas) => {
+                areas.iter_mut().find_map(Option::take);
+            }
+            Storage::RowSpans(spans) => {
+                let (_, rows) = first;
+                for row in rows[0]..=rows[1] {
+                    spans[usize::from(row)] = NO_SPAN;
+                }
+            }
+        }
+
+        Some(first)
+    }
+
+    /// Returns the changed areas.
+    pub(crate) fn iter(&self) -> DirtyIter<'_> {
+        DirtyIter {
+            dirty: self,
+            index: 0,
+        }
+    }
+
+    /// Returns the bounding box of all changed areas.
+    pub(crate) fn union(&self) -> Option<DirtyArea> {
+        self.iter().reduce(union)
+    }
+}
+
+/// Iterates over the changed areas of `DirtyAreas`.
+pub(crate) struct DirtyIter<'a> {
+    dirty: &'a DirtyAreas,
+    /// Next area or row to look at.
+    index: usize,
+}
+
+impl Iterator for DirtyIter<'_> {
+    type Item = DirtyArea;
+
+    fn next(&mut self) -> Option<DirtyArea> {
+        match &self.dirty.storage {
+            Storage::Areas(areas) => {
+                let (i, area) =
+                    (self.index..MAX_DIRTY_AREAS).find_map(|i| areas[i].map(|area| (i, area)))?;
+                self.index = i + 1;
+
+                Some(area)
+            }
+            Storage::RowSpans(spans) => {
+                let first = (self.index..MAX_ROWS).find(|&row| spans[row][0] <= spans[row][1])?;
+                let last = (first..MAX_ROWS)
+                    .take_while(|&row| spans[row] == spans[first])
+                    .last()
+                    .unwrap_or(first);
+                self.index = last + 1;
+
+                Some((spans[first], [first as u8, last as u8]))
+            }
+        }
+    }
+}
+
+/// Widens the span of `row` to the byte at column `col`.
+fn add_to_span(spans: &mut [[u8; 2]; MAX_ROWS], col: u8, row: u8) {
+    let span = &mut spans[usize::from(row)];
+    *span = [span[0].min(col), span[1].max(col)];
+}
+
+fn add_to_areas(areas: &mut [Option<DirtyArea>; MAX_DIRTY_AREAS], new: DirtyArea) {
+    if areas.iter().flatten().any(|&area| contains(area, new)) {
+        return;
+    }
+
+    let closest = areas
+        .iter()
+        .enumerate()
+        .filter_map(|(i, area)| area.map(|area| (i, growth(area, new))))
+        .min_by_key(|&(_, growth)| growth);
+    let free = areas.iter().position(Option::is_none);
+
+    match (closest, free) {
+        (Some((_, growth)), Some(free)) if growth > MAX_GROWTH => {
+            areas[free] = Some(new);
+            merge(areas, free);
+        }
+        (Some((i, _)), _) => {
+            areas[i] = areas[i].map(|area| union(area, new));
+            merge(areas, i);
+        }
+        // Nothing changed yet
+        (None, _) => areas[0] = Some(new),
+    }
+}
+
+/// Merges the areas that overlap the area at `index` into it.
+fn merge(areas: &mut [Option<DirtyArea>; MAX_DIRTY_AREAS], mut index: usize) {
+    let mut merged = true;
+    while merged {
+        merged = false;
+        for i in 0..MAX_DIRTY_AREAS {
+            if let (true, Some(a), Some(b)) = (i != index, areas[index], areas[i]) {
+                if overlaps(a, b) {
+                    let (keep, drop) = (index.min(i), index.max(i));
+                    areas[keep] = Some(union(a, b));
+                    areas[drop] = None;
+                    index = keep;
+                    merged = true;
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if `area` covers every byte of `other`.
+fn contains((cols, rows): DirtyArea, (other_cols, other_rows): DirtyArea) -> bool {
+    cols[0] <= other_cols[0]
+        && other_cols[1] <= cols[1]
+        && rows[0] <= other_rows[0]
+        && other_rows[1] <= rows[1]
+}
+
+fn overlaps((a_cols, a_rows): DirtyArea, (b_cols, b_rows): DirtyArea) -> bool {
+    a_cols[0] <= b_cols[1]
+        && b_cols[0] <= a_cols[1]
+        && a_rows[0] <= b_rows[1]
+        && b_rows[0] <= a_rows[1]
+}
+
+fn union((a_cols, a_rows): DirtyArea, (b_cols, b_rows): DirtyArea) -> DirtyArea {
+    (
+        [a_cols[0].min(b_cols[0]), a_cols[1].max(b_cols[1])],
+        [a_rows[0].min(b_rows[0]), a_rows[1].max(b_rows[1])],
+    )
+}
+
+fn size((cols, rows): DirtyArea) -> u32 {
+    u32::from(cols[1] - cols[0] + 1) * u32::from(rows[1] - rows[0] + 1)
+}
+
+/// Number of bytes `area` grows by when `other` is added to it.
+fn growth(area: DirtyArea, other: DirtyArea) -> u32 {
+    size(union(area, other)) - size(area)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that distant changes are tracked separately.
+    fn separate_areas() {
+        let mut dirty = DirtyAreas::default();
+        assert!(dirty.is_empty());
+
+        dirty.add(0, 0);
+        dirty.add(2, 3);
+        dirty.add(127, 63);
+        assert_eq!(dirty.iter().count(), 2);
+        assert_eq!(dirty.union(), Some(([0, 127], [0, 63])));
+
+        dirty.add(120, 0);
+        dirty.add(0, 63);
+        assert_eq!(dirty.iter().count(), 4);
+
+        assert_eq!(dirty.pop(), Some(([0, 2], [0, 3])));
+        assert_eq!(dirty.iter().count(), 3);
+        dirty.clear();
+        assert!(dirty.is_empty());
+        assert_eq!(dirty.union(), None);
+    }
+
+    #[test]
+    /// Tests that row spans are reported per run of rows with the same span.
+    fn row_spans() {
+        let mut dirty = DirtyAreas::new(DirtyTracking::RowSpans);
+        dirty.add(3, 0);
+        dirty.add(1, 0);
+        dirty.add(1, 1);
+        dirty.add(3, 1);
+        dirty.add(100, 2);
+        dirty.add(5, 63);
+
+        let mut areas = dirty.iter();
+        assert_eq!(areas.next(), Some(([1, 3], [0, 1])));
+        assert_eq!(areas.next(), Some(([100, 100], [2, 2])));
+        assert_eq!(areas.next(), Some(([5, 5], [63, 63])));
+        assert_eq!(areas.next(), None);
+
+        assert_eq!(dirty.pop(), Some(([1, 3], [0, 1])));
+        assert_eq!(dirty.iter().count(), 2);
+        dirty.clear();
+        assert!(dirty.is_empty());
+        assert_eq!(dirty.tracking(), DirtyTracking::RowSpans);
+    }
+
+    #[test]
+    /// Tests that an area that grows into another one absorbs it.
+    fn merge_overlapping() {
+        let mut dirty = DirtyAreas {
+            storage: Storage::Areas([
+                Some(([10, 20], [0, 40])),
+                Some(([30, 30], [20, 20])),
+                None,
+                Some(([127, 127], [63, 63])),
+            ]),
+            ..DirtyAreas::default()
+        };
+
+        dirty.add(0, 20);
+        assert_eq!(dirty.iter().count(), 2);
+        assert_eq!(dirty.pop(), Some(([0, 30], [0, 40])));
+    }
+
+    #[test]
+    /// Tests that a wide area is tracked as one area instead of its edge columns.
+    fn add_wide_area() {
+        let mut dirty = DirtyAreas::default();
+        dirty.add_area(([0, 127], [0, 63]));
+        assert_eq!(dirty.iter().count(), 1);
+        assert_eq!(dirty.pop(), Some(([0, 127], [0, 63])));
+
+        dirty.add(120, 0);
+        dirty.add_area(([0, 100], [10, 13]));
+        dirty.add_area(([10, 20], [11, 12]));
+        let mut areas = dirty.iter();
+        assert_eq!(areas.next(), Some(([120, 120], [0, 0])));
+        assert_eq!(areas.next(), Some(([0, 100], [10, 13])));
+        assert_eq!(areas.next(), None);
+    }
+}
@@ -1,394 +1,3830 @@
 //! main display module
-use core::convert::TryInto;
+use crate::command::{self, Command};
+use crate::config::{
+    Brightness, DataWidth, DirtyTracking, DisplayClock, DisplayConfig, DisplayRotation, DrawMode,
+    DriveConfig, Gamma, GpioState, InitConfig, VddSource,
+};
+#[cfg(feature = "graphics")]
+use crate::dirty::DirtyArea;
+use crate::dirty::DirtyAreas;
+use crate::interface::{self, ReadData};
+use crate::mode::{BufferedGraphicsMode, UntrackedMode};
+use display_interface::{
+    DataFormat::{U8Iter, U16BE, U8},
+    DisplayError, WriteOnlyDataCommand,
+};
+#[cfg(feature = "graphics")]
+use embedded_graphics::{
+    draw_target::DrawTarget, geometry::OriginDimensions, pixelcolor::Gray4, prelude::*,
+    primitives::Rectangle, Pixel,
+};
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec};
+
+/// Number of column addresses in the SSD1322 GDDRAM. Each column address covers 4 pixels.
+const GDDRAM_COLUMNS: usize = 120;
+
+/// Number of rows in the SSD1322 GDDRAM.
+const GDDRAM_ROWS: usize = 128;
+
+/// Image columns dithered by `dither_from_gray8`, the widest display in any rotation.
+const MAX_DITHER_WIDTH: usize = GDDRAM_COLUMNS * 4;
+
+/// Mask of the left (even) pixel of a framebuffer byte.
+pub const LEFT_PIXEL_MASK: u8 = 0xF0;
+
+/// Mask of the right (odd) pixel of a framebuffer byte.
+pub const RIGHT_PIXEL_MASK: u8 = 0x0F;
+
+/// Shift of the left (even) pixel of a framebuffer byte.
+pub const LEFT_PIXEL_SHIFT: u32 = 4;
+
+/// Fewest rows the SSD1322 can drive.
+const MIN_MUX_RATIO: u8 = 16;
+
+/// Highest pulse width setting of a gray scale level
+const MAX_GRAY_SCALE: u8 = 180;
+
+/// Words collected from the framebuffer for each transfer of `DataWidth::U16`.
+const WORD_CHUNK_SIZE: usize = 128;
+
+/// Represents the SSD1322 Display.
+///
+/// Use this struct to initialize the driver. `WIDTH` and `HEIGHT` are the panel dimensions in
+/// pixels and `B` is the framebuffer storage, which must hold `WIDTH * HEIGHT / 2` bytes. The
+/// defaults describe a 256x64 panel, e.g. a 256x32 panel is `Ssd1322<DI, 256, 32, [u8; 4096]>`.
+/// The framebuffer is either owned by the driver or borrowed, see `new_with_buffer`. It may be
+/// taller than the panel, see [`DisplayConfig::display_rows`].
+///
+/// `MODE` selects how drawing reaches the display. In the default [`BufferedGraphicsMode`]
+/// drawing goes to the framebuffer and is sent by the flush methods. In
+/// [`DirectMode`](crate::mode::DirectMode) there is no framebuffer (`B` is `()`) and drawing is
+/// written straight to the GDDRAM, [`TerminalMode`](crate::terminal::TerminalMode) prints text
+/// and [`BasicMode`](crate::mode::BasicMode) only sends commands. The `into_*_mode` methods
+/// convert between the modes.
+pub struct Ssd1322<
+    DI,
+    const WIDTH: usize = 256,
+    const HEIGHT: usize = 64,
+    B = [u8; 8192],
+    MODE = BufferedGraphicsMode,
+> {
+    pub(crate) display: DI,
+    pub(crate) buffer: B,
+    pub(crate) config: DisplayConfig,
+    pub(crate) dirty: DirtyAreas,
+    pub(crate) num_changed: u16,
+    stats: FlushStats,
+    clock: Option<fn() -> u32>,
+    draw_mode: DrawMode,
+    pub(crate) locked: bool,
+    /// GDDRAM row shown at the top of the display.
+    pub(crate) start_line: u8,
+    pub(crate) mode: MODE,
+}
+
+/// A resumable flush started by `Ssd1322::start_flush` or `Ssd1322::start_flush_all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushOp {
+    row: usize,
+    last_row: usize,
+    col_byte: usize,
+    num_col_bytes: usize,
+    offset: usize,
+}
+
+impl FlushOp {
+    /// Returns true once every byte of the flush was sent.
+    pub fn is_done(&self) -> bool {
+        self.row > self.last_row
+    }
+}
+
+/// Progress of a resumable flush.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushStatus {
+    /// Part of the framebuffer remains to be sent.
+    Pending,
+    /// The flush is complete.
+    Done,
+}
+
+/// Counters of the data sent by the flush methods, see `Ssd1322::stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushStats {
+    /// Number of address windows written, one per full flush and one per changed area or
+    /// region.
+    pub windows: u32,
+    /// Number of framebuffer bytes sent, including the bytes left to a DMA transfer by
+    /// `prepare_flush`.
+    pub bytes: u32,
+    /// The changed area sent by the last flush of the tracked changes.
+    #[cfg(feature = "graphics")]
+    pub last_dirty_area: Option<Rectangle>,
+    /// Number of `flush` calls.
+    pub flushes: u32,
+    /// Clock ticks spent in `flush`, measured with the clock passed to `Ssd1322::set_clock`.
+    pub flush_ticks: u32,
+    /// Number of `flush_all` calls.
+    pub full_flushes: u32,
+    /// Clock ticks spent in `flush_all`.
+    pub full_flush_ticks: u32,
+}
+
+impl FlushStats {
+    fn add_window(&mut self, bytes: usize) {
+        self.windows = self.windows.wrapping_add(1);
+        self.add_bytes(bytes);
+    }
+
+    fn add_bytes(&mut self, bytes: usize) {
+        self.bytes = self.bytes.wrapping_add(bytes as u32);
+    }
+}
+
+/// The status register of the controller, see `Ssd1322::read_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// The controller is busy with the previous command (D7).
+    pub busy: bool,
+    /// The display is off or asleep (D6).
+    pub display_off: bool,
+    /// The register as read, for bits that are not decoded.
+    pub raw: u8,
+}
+
+impl From<u8> for Status {
+    fn from(raw: u8) -> Self {
+        Self {
+            busy: raw & 0x80 != 0,
+            display_off: raw & 0x40 != 0,
+            raw,
+        }
+    }
+}
+
+/// Provides an optimized way to capture changes to the framebuffer.
+pub trait BoundingBox {
+    /// Adds the byte holding pixel `x`, `y` to the changed areas.
+    fn update_box(&mut self, x: u16, y: u16);
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> Ssd1322<DI, WIDTH, HEIGHT, B, MODE> {
+    const DIMENSIONS_CHECK: () = assert!(
+        WIDTH.is_multiple_of(4)
+            && WIDTH > 0
+            && WIDTH <= GDDRAM_COLUMNS * 4
+            && HEIGHT > 0
+            && HEIGHT <= GDDRAM_ROWS,
+        "WIDTH must be a non-zero multiple of 4 up to 480 and HEIGHT must be between 1 and 128"
+    );
+
+    /// The number of GDDRAM column addresses covered by the panel.
+    const COLS: u8 = (WIDTH / 4) as u8;
+
+    /// The last GDDRAM row address of the panel.
+    const ROW_END: u8 = (HEIGHT - 1) as u8;
+
+    /// Size of the framebuffer in bytes.
+    pub const BUFFER_SIZE: usize = WIDTH * HEIGHT / 2;
+
+    /// Number of framebuffer bytes per row.
+    pub const ROW_BYTES: usize = WIDTH / 2;
+
+    pub(crate) fn from_parts(display: DI, buffer: B, config: DisplayConfig, mode: MODE) -> Self {
+        let () = Self::DIMENSIONS_CHECK;
+
+        Self {
+            display,
+            buffer,
+            config,
+            dirty: DirtyAreas::default(),
+            num_changed: 0,
+            stats: FlushStats::default(),
+            clock: None,
+            draw_mode: DrawMode::Replace,
+            locked: false,
+            start_line: 0,
+            mode,
+        }
+    }
+
+    /// Returns the display interface, e.g. to reuse the bus and pins after the display is
+    /// shut down.
+    pub fn release(self) -> DI {
+        self.display
+    }
+
+    /// Returns the display interface and the framebuffer, e.g. the external buffer given to
+    /// `new_with_buffer`.
+    pub fn release_with_buffer(self) -> (DI, B) {
+        (self.display, self.buffer)
+    }
+
+    /// Returns the driver with the display interface replaced by `display`, and the old
+    /// interface. The framebuffer and the state are kept.
+    pub(crate) fn into_interface<DI2>(
+        self,
+        display: DI2,
+    ) -> (Ssd1322<DI2, WIDTH, HEIGHT, B, MODE>, DI) {
+        let disp = Ssd1322 {
+            display,
+            buffer: self.buffer,
+            config: self.config,
+            dirty: self.dirty,
+            num_changed: self.num_changed,
+            stats: self.stats,
+            clock: self.clock,
+            draw_mode: self.draw_mode,
+            locked: self.locked,
+            start_line: self.start_line,
+            mode: self.mode,
+        };
+
+        (disp, self.display)
+    }
+
+    /// Returns true if the rotation and mirroring toggle the column address remap.
+    fn columns_flipped(&self) -> bool {
+        self.config.rotation.is_flipped() != self.config.mirror_horizontal
+    }
+
+    /// Returns true if the rotation and mirroring toggle the COM scan direction.
+    fn rows_flipped(&self) -> bool {
+        self.config.rotation.is_flipped() != self.config.mirror_vertical
+    }
+
+    /// The first GDDRAM column address of the panel.
+    ///
+    /// When the column address remap is toggled the GDDRAM columns are mirrored, so the offset
+    /// is counted from the other end of the GDDRAM.
+    pub(crate) fn col_start(&self) -> u8 {
+        if self.columns_flipped() {
+            (GDDRAM_COLUMNS - usize::from(Self::COLS)).saturating_sub(self.config.col_offset.into())
+                as u8
+        } else {
+            self.config.col_offset
+        }
+    }
+
+    /// The last GDDRAM column address of the panel.
+    fn col_end(&self) -> u8 {
+        self.col_start() + Self::COLS - 1
+    }
+
+    /// The remap bytes for the configured rotation and mirroring. A horizontal mirror toggles
+    /// the column address remap, a vertical mirror toggles the COM scan direction and a 180
+    /// degree rotation toggles both.
+    fn remap(&self) -> [u8; 2] {
+        let [mut a, b] = self.config.remap;
+        if self.columns_flipped() {
+            a ^= 0x02;
+        }
+        if self.rows_flipped() {
+            a ^= 0x10;
+        }
+        [a, b]
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Maps a point in the rotated coordinate system to the framebuffer coordinates, discarding
+    /// points outside of the display.
+    pub(crate) fn to_physical(&self, point: Point) -> Option<(usize, usize)> {
+        // Negative coordinates wrap to large values and are discarded by the bounds checks.
+        self.physical_xy(point.x as usize, point.y as usize)
+    }
+
+    /// Maps the display coordinates `x`, `y` to the framebuffer column and row, or `None` if
+    /// they are outside of the display.
+    pub(crate) fn physical_xy(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        match self.config.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                (x < WIDTH && y < HEIGHT).then_some((x, y))
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                (x < HEIGHT && y < WIDTH).then_some((WIDTH - 1 - y, x))
+            }
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Returns the display area of the framebuffer bytes and rows `area`.
+    fn logical_area(&self, (col_addr, row_addr): DirtyArea) -> Rectangle {
+        let x = [
+            usize::from(col_addr[0]) * 2,
+            usize::from(col_addr[1]) * 2 + 1,
+        ];
+        let y = [usize::from(row_addr[0]), usize::from(row_addr[1])];
+
+        let (top_left, bottom_right) = match self.config.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => ((x[0], y[0]), (x[1], y[1])),
+            // Inverse of `to_physical`
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                ((y[0], WIDTH - 1 - x[1]), (y[1], WIDTH - 1 - x[0]))
+            }
+        };
+
+        Rectangle::with_corners(
+            Point::new(top_left.0 as i32, top_left.1 as i32),
+            Point::new(bottom_right.0 as i32, bottom_right.1 as i32),
+        )
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Returns the framebuffer bytes and rows covered by `area`, clipped to the display.
+    fn physical_area(&self, area: Rectangle) -> Option<DirtyArea> {
+        let (x, y) = self.physical_pixels(area)?;
+
+        Some((
+            [(x[0] / 2) as u8, (x[1] / 2) as u8],
+            [y[0] as u8, y[1] as u8],
+        ))
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Returns the inclusive framebuffer columns and rows of the pixels covered by `area`,
+    /// clipped to the display.
+    fn physical_pixels(&self, area: Rectangle) -> Option<([usize; 2], [usize; 2])> {
+        let area = area.intersection(&self.bounding_box());
+        let bottom_right = area.bottom_right()?;
+
+        // Both corners are inside of the display after clipping; a rotation may swap the axes
+        // and directions, so the physical area spans both.
+        let (x0, y0) = self.to_physical(area.top_left)?;
+        let (x1, y1) = self.to_physical(bottom_right)?;
+
+        Some(([x0.min(x1), x0.max(x1)], [y0.min(y1), y0.max(y1)]))
+    }
+}
+
+/// Checks at compile time that a framebuffer of `N` bytes fits a `WIDTH` x `HEIGHT` panel.
+pub(crate) struct BufferSize<const WIDTH: usize, const HEIGHT: usize, const N: usize>;
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> BufferSize<WIDTH, HEIGHT, N> {
+    pub(crate) const CHECK: () = assert!(
+        N == WIDTH * HEIGHT / 2,
+        "the buffer must hold WIDTH * HEIGHT / 2 bytes"
+    );
+}
+
+/// Checks at compile time that two frames of `HEIGHT` rows fit in the GDDRAM.
+struct PageFlipSize<const HEIGHT: usize>;
+
+impl<const HEIGHT: usize> PageFlipSize<HEIGHT> {
+    const CHECK: () = assert!(
+        HEIGHT * 2 <= GDDRAM_ROWS,
+        "page flipping needs a panel of at most 64 rows"
+    );
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>
+    Ssd1322<DI, WIDTH, HEIGHT, [u8; N]>
+{
+    /// Creates the SSD1322 Display.
+    ///
+    /// The panel is assumed to be centred in the GDDRAM. The device needs to be reset before use.
+    pub fn new(display: DI) -> Self {
+        Self::new_with_config(display, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display for a panel wired as described by `config`.
+    ///
+    /// The configuration is validated by `init`. The device needs to be reset before use.
+    pub fn new_with_config(display: DI, config: DisplayConfig) -> Self {
+        let () = BufferSize::<WIDTH, HEIGHT, N>::CHECK;
+
+        Self::from_parts(display, [0; N], config, BufferedGraphicsMode)
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>
+    Ssd1322<DI, WIDTH, HEIGHT, [u8; N], UntrackedMode>
+{
+    /// Creates the SSD1322 Display without change tracking, see `UntrackedMode`.
+    ///
+    /// The panel is assumed to be centred in the GDDRAM. The device needs to be reset before use.
+    pub fn new_untracked(display: DI) -> Self {
+        Self::new_untracked_with_config(display, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display without change tracking for a panel wired as described by
+    /// `config`.
+    ///
+    /// The configuration is validated by `init`. The device needs to be reset before use.
+    pub fn new_untracked_with_config(display: DI, config: DisplayConfig) -> Self {
+        let () = BufferSize::<WIDTH, HEIGHT, N>::CHECK;
+
+        Self::from_parts(display, [0; N], config, BufferedGraphicsMode)
+    }
+}
+
+impl<'a, DI, const WIDTH: usize, const HEIGHT: usize, const N: usize>
+    Ssd1322<DI, WIDTH, HEIGHT, &'a mut [u8; N]>
+{
+    /// Creates the SSD1322 Display using an external framebuffer, e.g. a `static` buffer in
+    /// `.bss` so it does not live on the stack.
+    ///
+    /// The buffer is cleared and the panel is assumed to be centred in the GDDRAM. The device
+    /// needs to be reset before use.
+    pub fn new_with_buffer(display: DI, buffer: &'a mut [u8; N]) -> Self {
+        Self::new_with_buffer_and_config(display, buffer, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display using an external framebuffer for a panel wired as described
+    /// by `config`.
+    ///
+    /// The buffer is cleared and the configuration is validated by `init`. The device needs to
+    /// be reset before use.
+    pub fn new_with_buffer_and_config(
+        display: DI,
+        buffer: &'a mut [u8; N],
+        config: DisplayConfig,
+    ) -> Self {
+        let () = BufferSize::<WIDTH, HEIGHT, N>::CHECK;
+
+        buffer.fill(0);
+        Self::from_parts(display, buffer, config, BufferedGraphicsMode)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize> Ssd1322<DI, WIDTH, HEIGHT, Box<[u8]>> {
+    /// Creates the SSD1322 Display with a framebuffer allocated on the heap.
+    ///
+    /// The panel is assumed to be centred in the GDDRAM. The device needs to be reset before use.
+    pub fn new_boxed(display: DI) -> Self {
+        Self::new_boxed_with_config(display, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display with a framebuffer allocated on the heap for a panel wired as
+    /// described by `config`.
+    ///
+    /// The configuration is validated by `init`. The device needs to be reset before use.
+    pub fn new_boxed_with_config(display: DI, config: DisplayConfig) -> Self {
+        let buffer = vec![0; WIDTH * HEIGHT / 2].into_boxed_slice();
+        Self::from_parts(display, buffer, config, BufferedGraphicsMode)
+    }
+}
+
+impl<DI> Ssd1322<DI, 256, 32, [u8; 4096]> {
+    /// Creates the SSD1322 Display for a 256x32 panel.
+    ///
+    /// The mux ratio and row address range are set up for 32 rows and the framebuffer only holds
+    /// those rows. The device needs to be reset before use.
+    pub fn new_256x32(display: DI) -> Self {
+        Self::new(display)
+    }
+}
+
+impl<DI> Ssd1322<DI, 480, 128, [u8; 30720]> {
+    /// Creates the SSD1322 Display for a 480x128 panel, the maximum GDDRAM resolution.
+    ///
+    /// The panel uses every GDDRAM column and row, and dual COM line mode is disabled. The
+    /// device needs to be reset before use.
+    pub fn new_480x128(display: DI) -> Self {
+        Self::new(display)
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> Ssd1322<DI, WIDTH, HEIGHT, B, MODE>
+where
+    DI: WriteOnlyDataCommand,
+{
+    /// Resets the display with the pulse and wait of `DisplayConfig::reset`.
+    pub fn reset<RST, DELAY>(
+        &mut self,
+        rst: &mut RST,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError>
+    where
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+    {
+        let reset = self.config.reset;
+        let mut drive = |high: bool| {
+            if high { rst.set_high() } else { rst.set_low() }
+                .map_err(|_| DisplayError::BusWriteError)
+        };
+
+        drive(reset.active_high)?;
+        delay.delay_ms(reset.pulse_ms);
+
+        drive(!reset.active_high)?;
+        delay.delay_ms(reset.wait_ms);
+
+        Ok(())
+    }
+
+    /// Initializes the display.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if the configured offsets place the panel
+    /// outside of the GDDRAM, the panel has fewer than 16 rows or more rows than the framebuffer,
+    /// or the clock or a driving register is out of range.
+    pub fn init(&mut self) -> Result<(), DisplayError> {
+        self.init_registers(true)
+    }
+
+    /// Initializes the display like `init`, but leaves it off, e.g. until the panel supply (VCC)
+    /// is enabled. `Command::DisplayOn` then turns it on.
+    pub fn init_display_off(&mut self) -> Result<(), DisplayError> {
+        self.init_registers(false)
+    }
+
+    /// Sends the init sequence, ending with the display turned on if `on` is true.
+    fn init_registers(&mut self, on: bool) -> Result<(), DisplayError> {
+        let init = self.config.init;
+        if usize::from(self.config.col_offset) + WIDTH / 4 > GDDRAM_COLUMNS
+            || usize::from(self.config.row_offset) >= GDDRAM_ROWS
+            || self.config.display_rows < MIN_MUX_RATIO
+            || usize::from(self.config.display_rows) > HEIGHT
+            || !init.is_valid()
+        {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+        let clock = DisplayClock::from_byte(self.config.clock)?;
+
+        let [remap_a, remap_b] = self.remap();
+        let commands = [
+            Command::Unlock,
+            Command::DisplayOff,
+            Command::SetColumnAddress(self.col_start(), self.col_end()),
+            Command::SetRowAddress(0x00, Self::ROW_END),
+            Command::SetDisplayClock(clock),
+            Command::SetMuxRatio(self.config.display_rows - 1),
+            Command::SetDisplayOffset(self.config.row_offset),
+            Command::SetStartLine(0x00),
+            Command::SetRemapFormat(remap_a, remap_b),
+            Command::SetGPIO(self.config.gpio[0], self.config.gpio[1]),
+            // VDD is selected before the driving registers, as in the datasheet's sequence.
+            Command::SetFunctionSelection(init.vdd),
+            Command::SetDisplayEnhancementA(init.vsl, init.gray_scale_quality),
+            Command::SetContrastCurrent(self.config.contrast),
+            Command::SetMasterCurrent(self.config.master_current),
+            Command::SetLinearGrayScaleTable,
+            Command::SetPhaseLength(init.phase_length),
+            Command::SetDisplayEnhancementB(init.enhancement_b[0], init.enhancement_b[1]),
+            Command::SetPrechargeVoltage(init.precharge_voltage),
+            Command::SetPrechargePeriod(init.precharge_period),
+            Command::SetVCOMH(init.vcomh),
+            Command::NormalDisplayMode,
+            Command::DisplayOn,
+        ];
+        let len = if on {
+            commands.len()
+        } else {
+            commands.len() - 1
+        };
+        self.send_commands(&commands[..len])?;
+        self.start_line = 0;
+
+        Ok(())
+    }
+
+    /// Initializes the display with the driving registers `init`, e.g. the init sequence a panel
+    /// vendor recommends. The registers are kept for later calls to `init` and `wake`.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` like `init`, the registers are only kept if
+    /// they are in range.
+    pub fn init_with(&mut self, init: &InitConfig) -> Result<(), DisplayError> {
+        if !init.is_valid() {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.config.init = *init;
+        self.init()
+    }
+
+    /// Resends `recording`, the transfers recorded by an `interface::RecordInterface`, e.g. the
+    /// init sequence a vendor's driver sends on another board. The framebuffer and the
+    /// configuration are unchanged, so a recorded remap or offset must match the configuration.
+    /// A recorded lock or unlock (FDh) changes the command lock of the driver, see `lock`.
+    ///
+    /// Returns `DisplayError::InvalidFormatError` without sending anything if `recording` is
+    /// truncated or malformed, or if it would send anything but an unlock while the command
+    /// interface is locked.
+    pub fn replay(&mut self, recording: &[u8]) -> Result<(), DisplayError> {
+        let mut locked = self.locked;
+        let mut lock_command = false;
+        for record in interface::records(recording) {
+            let (tag, bytes) = record?;
+            match (tag, bytes) {
+                (interface::RECORD_COMMANDS, [0xFD]) => {}
+                (interface::RECORD_DATA, [arg, ..]) if lock_command => locked = arg & 0x04 != 0,
+                (interface::RECORD_COMMANDS | interface::RECORD_DATA, _) if locked => {
+                    return Err(DisplayError::InvalidFormatError)
+                }
+                (interface::RECORD_COMMANDS | interface::RECORD_DATA, _) => {}
+                _ => return Err(DisplayError::InvalidFormatError),
+            }
+            // Bit 2 of the argument of an FDh command sets the lock
+            lock_command = tag == interface::RECORD_COMMANDS && bytes.last() == Some(&0xFD);
+        }
+
+        for record in interface::records(recording) {
+            let (tag, bytes) = record?;
+            interface::send(&mut self.display, tag, U8(bytes))?;
+        }
+        self.locked = locked;
+
+        Ok(())
+    }
+
+    /// Puts the display to sleep, which blanks the panel and turns off the oscillator and the
+    /// driving currents. The GDDRAM content and the configuration are retained.
+    pub fn sleep(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::DisplayOff)
+    }
+
+    /// Wakes the display from sleep without a reset or a full `init`.
+    ///
+    /// Restores the display enhancement registers and the contrast before turning the panel on, so
+    /// it comes back with the brightness it had before `sleep`.
+    ///
+    /// Returns `DisplayError::InvalidFormatError` while the command interface is locked.
+    pub fn wake(&mut self) -> Result<(), DisplayError> {
+        self.wake_with_contrast(self.config.contrast)
+    }
+
+    /// Dims the display to a contrast current of 0 in `steps` steps of `step_ms` milliseconds
+    /// and then puts it to sleep, for a smooth transition to sleep. The contrast is kept for
+    /// `wake` and `fade_in`.
+    pub fn fade_out<DELAY>(
+        &mut self,
+        steps: u8,
+        step_ms: u8,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError>
+    where
+        DELAY: DelayMs<u8>,
+    {
+        for step in (0..steps).rev() {
+            self.send_command(Command::SetContrastCurrent(self.fade_contrast(step, steps)))?;
+            delay.delay_ms(step_ms);
+        }
+
+        self.sleep()
+    }
+
+    /// Wakes the display like `wake`, but starting at a contrast current of 0 and ramping it up
+    /// to the configured contrast in `steps` steps of `step_ms` milliseconds.
+    pub fn fade_in<DELAY>(
+        &mut self,
+        steps: u8,
+        step_ms: u8,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError>
+    where
+        DELAY: DelayMs<u8>,
+    {
+        if steps == 0 {
+            return self.wake();
+        }
+
+        self.wake_with_contrast(0)?;
+        for step in 1..=steps {
+            delay.delay_ms(step_ms);
+            self.send_command(Command::SetContrastCurrent(self.fade_contrast(step, steps)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the contrast current of step `step` of a fade over `steps` steps.
+    fn fade_contrast(&self, step: u8, steps: u8) -> u8 {
+        (u16::from(self.config.contrast) * u16::from(step) / u16::from(steps)) as u8
+    }
+
+    fn wake_with_contrast(&mut self, contrast: u8) -> Result<(), DisplayError> {
+        let init = self.config.init;
+        self.send_command(Command::SetDisplayEnhancementA(
+            init.vsl,
+            init.gray_scale_quality,
+        ))?;
+        self.send_command(Command::SetContrastCurrent(contrast))?;
+        self.send_command(Command::SetMasterCurrent(self.config.master_current))?;
+        let [enhancement_a, enhancement_b] = init.enhancement_b;
+        self.send_command(Command::SetDisplayEnhancementB(
+            enhancement_a,
+            enhancement_b,
+        ))?;
+        self.send_command(Command::DisplayOn)
+    }
+
+    /// Only drives the panel rows `start_row` to `end_row` (inclusive), to save power when
+    /// the rest of the screen is blank. The rows are counted in the scan order of the panel.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if the rows are out of range or out of order.
+    pub fn enable_partial_display(
+        &mut self,
+        start_row: u8,
+        end_row: u8,
+    ) -> Result<(), DisplayError> {
+        if start_row > end_row || end_row >= self.config.display_rows {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_command(Command::EnterPartialDisplay(start_row, end_row))
+    }
+
+    /// Drives all rows of the panel again.
+    pub fn disable_partial_display(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::ExitPartialDisplay)
+    }
+
+    /// Inverts the gray levels of the whole display, e.g. to flash the screen for an alert,
+    /// without changing the GDDRAM content.
+    pub fn invert(&mut self, inverted: bool) -> Result<(), DisplayError> {
+        if inverted {
+            self.send_command(Command::InverseDisplayMode)
+        } else {
+            self.send_command(Command::NormalDisplayMode)
+        }
+    }
+
+    /// Turns all pixels on at the highest gray level regardless of the GDDRAM content, e.g. for
+    /// factory tests of the panel. `normal_mode` shows the GDDRAM content again.
+    pub fn test_all_on(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::AllPixelsOn)
+    }
+
+    /// Turns all pixels off regardless of the GDDRAM content. `normal_mode` shows the GDDRAM
+    /// content again.
+    pub fn test_all_off(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::AllPixelsOff)
+    }
+
+    /// Shows the GDDRAM content, leaving the all pixels on/off and the inverse display modes.
+    pub fn normal_mode(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::NormalDisplayMode)
+    }
+
+    /// Scrolls the display in hardware so GDDRAM row `line` is shown at the top (command A1h).
+    /// The rows wrap around at the end of the 128 GDDRAM rows.
+    ///
+    /// Only the displayed window moves, the GDDRAM content is not changed. In buffered mode the
+    /// framebuffer is still written to the rows starting at 0, so the rows scrolled into view
+    /// have to be written another way, e.g. in direct mode.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `line` is outside of the GDDRAM.
+    pub fn scroll_to_line(&mut self, line: u8) -> Result<(), DisplayError> {
+        if usize::from(line) >= GDDRAM_ROWS {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_command(Command::SetStartLine(line))?;
+        self.start_line = line;
+
+        Ok(())
+    }
+
+    /// Scrolls the display in hardware by `lines` rows, up for positive values.
+    pub fn scroll_by(&mut self, lines: i8) -> Result<(), DisplayError> {
+        let line = (i16::from(self.start_line) + i16::from(lines)).rem_euclid(GDDRAM_ROWS as i16);
+        self.scroll_to_line(line as u8)
+    }
+
+    /// Shows the framebuffer rows starting at `y` on a panel that has fewer rows than the
+    /// framebuffer, see `DisplayConfig::display_rows`. This moves the start line, so it is
+    /// instant and nothing has to be flushed.
+    ///
+    /// Panning is vertical only. The controller has a start line but no start column, as every
+    /// segment output drives a fixed GDDRAM column, and the framebuffer is only as wide as the
+    /// panel. Moving the image sideways means redrawing it and flushing the changes.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if the panel would show rows past the end of the
+    /// framebuffer.
+    pub fn pan_to(&mut self, y: u8) -> Result<(), DisplayError> {
+        if usize::from(y) + usize::from(self.config.display_rows) > HEIGHT {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.scroll_to_line(y)
+    }
+
+    /// Sets the number of driven rows (the mux ratio) from 16 to 128, e.g. for panels shorter
+    /// than the framebuffer, see `DisplayConfig::display_rows`. `pan_to` then pans over the
+    /// framebuffer in steps of the panel rows.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `rows` is out of range, above the
+    /// framebuffer height, or above 64 rows with the dual COM line mode enabled.
+    pub fn set_mux_ratio(&mut self, rows: u8) -> Result<(), DisplayError> {
+        if rows < MIN_MUX_RATIO
+            || usize::from(rows) > HEIGHT
+            || (rows > 64 && self.config.remap[1] & 0x10 != 0)
+        {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_command(Command::SetMuxRatio(rows - 1))?;
+        self.config.display_rows = rows;
+
+        Ok(())
+    }
+
+    /// Sets the phase lengths, the pre-charge and the COM deselect voltage at once, see
+    /// `DriveConfig`. The registers are kept for later calls to `init`.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if a register is out of range, in which case
+    /// nothing is sent.
+    pub fn set_drive_config(&mut self, drive: DriveConfig) -> Result<(), DisplayError> {
+        if !drive.is_valid() {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_commands(&[
+            Command::SetPhaseLength(drive.phase_length),
+            Command::SetPrechargeVoltage(drive.precharge_voltage),
+            Command::SetPrechargePeriod(drive.precharge_period),
+            Command::SetVCOMH(drive.vcomh),
+        ])?;
+        let init = &mut self.config.init;
+        init.phase_length = drive.phase_length;
+        init.precharge_voltage = drive.precharge_voltage;
+        init.precharge_period = drive.precharge_period;
+        init.vcomh = drive.vcomh;
+
+        Ok(())
+    }
+
+    /// Sets the modes of the GPIO0 and GPIO1 pins, e.g. to switch on the panel supply of modules
+    /// that route its enable through a GPIO pin. The modes are kept for later calls to `init`.
+    pub fn set_gpio(&mut self, gpio0: GpioState, gpio1: GpioState) -> Result<(), DisplayError> {
+        self.send_command(Command::SetGPIO(gpio0, gpio1))?;
+        self.config.gpio = [gpio0, gpio1];
+
+        Ok(())
+    }
+
+    /// Selects the internal VDD regulator or an external VDD, e.g. for modules that supply VDD
+    /// themselves. The source is kept for later calls to `init`, which selects it before
+    /// programming the driving registers.
+    pub fn set_vdd_source(&mut self, vdd: VddSource) -> Result<(), DisplayError> {
+        self.send_command(Command::SetFunctionSelection(vdd))?;
+        self.config.init.vdd = vdd;
+
+        Ok(())
+    }
+
+    /// Returns the GDDRAM row shown at the top of the display.
+    pub fn start_line(&self) -> u8 {
+        self.start_line
+    }
+
+    /// Sets the display rotation.
+    ///
+    /// 180 and 270 degrees are applied by reprogramming the remap register, which flips the
+    /// content already shown. 90 and 270 degrees swap the width and height reported by `size()`
+    /// and transform the coordinates of every drawn pixel, so the content has to be redrawn.
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) -> Result<(), DisplayError> {
+        let current = core::mem::replace(&mut self.config.rotation, rotation);
+        let [remap_a, remap_b] = self.remap();
+        self.config.rotation = current;
+
+        self.send_command(Command::SetRemapFormat(remap_a, remap_b))?;
+        self.config.rotation = rotation;
+
+        Ok(())
+    }
+
+    /// Mirrors the displayed content horizontally and/or vertically, e.g. for displays viewed
+    /// through a mirror.
+    ///
+    /// Mirroring is applied by reprogramming the remap register and flips the content already
+    /// shown.
+    pub fn set_mirror(&mut self, horizontal: bool, vertical: bool) -> Result<(), DisplayError> {
+        let current = (self.config.mirror_horizontal, self.config.mirror_vertical);
+        (self.config.mirror_horizontal, self.config.mirror_vertical) = (horizontal, vertical);
+        let [remap_a, remap_b] = self.remap();
+        (self.config.mirror_horizontal, self.config.mirror_vertical) = current;
+
+        self.send_command(Command::SetRemapFormat(remap_a, remap_b))?;
+        (self.config.mirror_horizontal, self.config.mirror_vertical) = (horizontal, vertical);
+
+        Ok(())
+    }
+
+    /// Sets the display brightness, e.g. one of the `Brightness` presets.
+    pub fn set_brightness(&mut self, brightness: Brightness) -> Result<(), DisplayError> {
+        self.set_contrast(brightness.contrast)?;
+        self.set_master_current(brightness.master_current)
+    }
+
+    /// Returns the display brightness.
+    pub fn brightness(&self) -> Brightness {
+        Brightness::custom(self.config.contrast, self.config.master_current)
+    }
+
+    /// Sets the contrast current (command C1h).
+    pub fn set_contrast(&mut self, contrast: u8) -> Result<(), DisplayError> {
+        self.send_command(Command::SetContrastCurrent(contrast))?;
+        self.config.contrast = contrast;
+
+        Ok(())
+    }
+
+    /// Sets the master current (command C7h), which scales the contrast current in 16 steps.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `master_current` is above 0x0F.
+    pub fn set_master_current(&mut self, master_current: u8) -> Result<(), DisplayError> {
+        if master_current > 0x0F {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_command(Command::SetMasterCurrent(master_current))?;
+        self.config.master_current = master_current;
+
+        Ok(())
+    }
+
+    /// Uploads a custom gray scale table (command B8h), e.g. to calibrate the gamma of the panel.
+    ///
+    /// `table` holds the pulse widths of the gray levels 1 to 15 (level 0 is always off). The
+    /// values have to be strictly increasing and at most 180, otherwise
+    /// `DisplayError::OutOfBoundsError` is returned.
+    pub fn set_gray_scale_table(&mut self, table: &[u8; 15]) -> Result<(), DisplayError> {
+        if table[14] > MAX_GRAY_SCALE || table.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_command(Command::SetGrayScaleTable(*table))?;
+        self.send_command(Command::EnableGrayScaleTable)
+    }
+
+    /// Restores the default linear gray scale table (command B9h) that is set by `init`.
+    pub fn set_default_grayscale(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::SetLinearGrayScaleTable)
+    }
+
+    /// Selects one of the gamma curve presets.
+    pub fn set_gamma(&mut self, gamma: Gamma) -> Result<(), DisplayError> {
+        match gamma.table() {
+            Some(table) => self.set_gray_scale_table(&table),
+            None => self.set_default_grayscale(),
+        }
+    }
+
+    /// Returns the display rotation.
+    pub fn rotation(&self) -> DisplayRotation {
+        self.config.rotation
+    }
+
+    /// Returns the panel configuration.
+    pub fn config(&self) -> &DisplayConfig {
+        &self.config
+    }
+
+    /// Locks the command interface (command FDh), so the controller ignores every command and
+    /// memory access until `unlock` is called. This protects production units against accidental
+    /// reconfiguration.
+    ///
+    /// While locked the driver refuses to send anything and returns
+    /// `DisplayError::InvalidFormatError`, instead of losing the data silently.
+    pub fn lock(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::Lock)?;
+        self.locked = true;
+
+        Ok(())
+    }
+
+    /// Unlocks the command interface. `init` unlocks it as well.
+    pub fn unlock(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::Unlock)
+    }
+
+    /// Returns `true` if the command interface is locked.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Allows to send custom commands to the display.
+    ///
+    /// Returns `DisplayError::InvalidFormatError` if the command interface is locked and the
+    /// command is not `Unlock`.
+    pub fn send_command(&mut self, command: Command) -> Result<(), DisplayError> {
+        let unlock = matches!(command, Command::Unlock);
+        if self.locked && !unlock {
+            return Err(DisplayError::InvalidFormatError);
+        }
+
+        command.send(&mut self.display)?;
+        // The lock only changes once the controller got the command
+        if unlock {
+            self.locked = false;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a sequence of commands, e.g. a vendor's init sequence. The command bytes of
+    /// consecutive commands without data are sent in one transfer, which saves time on slow
+    /// buses.
+    ///
+    /// Returns `DisplayError::InvalidFormatError` without sending anything if a command other
+    /// than `Unlock` would be sent while the command interface is locked.
+    pub fn send_commands(&mut self, commands: &[Command]) -> Result<(), DisplayError> {
+        let mut locked = self.locked;
+        for command in commands {
+            match command {
+                Command::Unlock => locked = false,
+                _ if locked => return Err(DisplayError::InvalidFormatError),
+                _ => {}
+            }
+        }
+        command::send_all(commands.iter().copied(), &mut self.display)?;
+        self.locked = locked;
+
+        Ok(())
+    }
+
+    /// Writes the entire panel with the gray levels (0 to 15) `pixel` returns for each
+    /// framebuffer column `x` and row `y`, generated while they are sent in one
+    /// `DataFormat::U8Iter` transfer. This needs no framebuffer, e.g. in direct or basic mode or
+    /// for patterns and adapters that synthesize the pixels. A 90 or 270 degree rotation is not
+    /// applied.
+    pub fn flush_iter<F>(&mut self, mut pixel: F) -> Result<(), DisplayError>
+    where
+        F: FnMut(usize, usize) -> u8,
+    {
+        self.send_command(Command::SetColumnAddress(self.col_start(), self.col_end()))?;
+        self.send_command(Command::SetRowAddress(0x00, Self::ROW_END))?;
+        self.send_command(Command::WriteRAM)?;
+
+        let order = self.config.nibble_order;
+        let mut bytes = (0..WIDTH * HEIGHT / 2).map(|i| {
+            let (x, y) = (i % (WIDTH / 2) * 2, i / (WIDTH / 2));
+            let left = pixel(x, y) & 0x0F;
+            order.arrange(left << 4 | pixel(x + 1, y) & 0x0F)
+        });
+        self.display.send_data(U8Iter(&mut bytes))
+    }
+
+    /// Writes `data`, bytes in the framebuffer format, to the `width` x `height` pixels at
+    /// framebuffer column `x` and row `y`, bypassing the framebuffer, e.g. for an image viewer
+    /// decoding from flash on the fly. The rows of the area follow each other without padding,
+    /// and bytes past the area are not sent. A 90 or 270 degree rotation is not applied.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if the area is empty, outside of the
+    /// framebuffer, or `x` or `width` isn't a multiple of 4, the pixels of a column address.
+    pub fn flush_raw<I>(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        data: I,
+    ) -> Result<(), DisplayError>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        if width == 0
+            || height == 0
+            || x + width > WIDTH
+            || y + height > HEIGHT
+            || !x.is_multiple_of(4)
+            || !width.is_multiple_of(4)
+        {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_command(Command::SetColumnAddress(
+            self.col_start() + (x / 4) as u8,
+            self.col_start() + ((x + width) / 4 - 1) as u8,
+        ))?;
+        self.send_command(Command::SetRowAddress(y as u8, (y + height - 1) as u8))?;
+        self.send_command(Command::WriteRAM)?;
+
+        let order = self.config.nibble_order;
+        let mut bytes = data
+            .into_iter()
+            .take(width * height / 2)
+            .map(|byte| order.arrange(byte));
+        self.display.send_data(U8Iter(&mut bytes))
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> Ssd1322<DI, WIDTH, HEIGHT, B, MODE>
+where
+    DI: WriteOnlyDataCommand + ReadData,
+{
+    /// Reads `buf.len()` bytes of the GDDRAM from framebuffer column `x`, rounded down to a
+    /// multiple of 4, of row `y`, e.g. for hardware self-tests that verify what the controller
+    /// stored. The bytes are in the framebuffer format and continue at column `x` of the
+    /// following rows, a 90 or 270 degree rotation is not applied.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `x`, `y` is outside of the framebuffer.
+    pub fn read_ram(&mut self, x: usize, y: usize, buf: &mut [u8]) -> Result<(), DisplayError> {
+        if x >= WIDTH || y >= HEIGHT {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.send_command(Command::SetColumnAddress(
+            self.col_start() + (x / 4) as u8,
+            self.col_end(),
+        ))?;
+        self.send_command(Command::SetRowAddress(y as u8, Self::ROW_END))?;
+        self.send_command(Command::ReadRAM)?;
+        // The first read after the command is a dummy read.
+        self.display.read_data(&mut [0])?;
+
+        self.display.read_data(buf)
+    }
+
+    /// Reads the status register of the controller, e.g. for production diagnostics. The command
+    /// lock isn't reported by the controller, see `is_locked`.
+    pub fn read_status(&mut self) -> Result<Status, DisplayError> {
+        self.display.read_status().map(Status::from)
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const TRACKED: bool>
+    Ssd1322<DI, WIDTH, HEIGHT, B, BufferedGraphicsMode<TRACKED>>
+where
+    DI: WriteOnlyDataCommand,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Flushes the entire display, and makes the output visible on the screen.
+    pub fn flush_all(&mut self) -> Result<(), DisplayError> {
+        let start = self.now();
+        let result = self.with_retry(Self::write_all);
+        self.stats.full_flushes = self.stats.full_flushes.wrapping_add(1);
+        self.stats.full_flush_ticks = self
+            .stats
+            .full_flush_ticks
+            .wrapping_add(self.now().wrapping_sub(start));
+
+        result
+    }
+
+    /// Runs a factory test of the panel: all pixels on and off, a gradient of the 16 gray
+    /// levels, the gradient inverted and a sweep of the contrast current, each shown for
+    /// `pause_ms` milliseconds. The gradient is written to the GDDRAM only, and the framebuffer
+    /// and the contrast are restored at the end. Pauses longer than 255 milliseconds are made of
+    /// several delays, so `delay` only needs to implement `DelayMs<u8>`, like for `reset`.
+    ///
+    /// Returns the first bus error, which ends the test.
+    pub fn self_test<DELAY>(&mut self, pause_ms: u16, delay: &mut DELAY) -> Result<(), DisplayError>
+    where
+        DELAY: DelayMs<u8>,
+    {
+        self.test_all_on()?;
+        delay_long(delay, pause_ms);
+        self.test_all_off()?;
+        delay_long(delay, pause_ms);
+        self.normal_mode()?;
+
+        self.flush_iter(|x, _| (x * 16 / WIDTH) as u8)?;
+        delay_long(delay, pause_ms);
+        self.invert(true)?;
+        delay_long(delay, pause_ms);
+        self.invert(false)?;
+
+        for contrast in (0..=0xFF).step_by(0x33) {
+            self.send_command(Command::SetContrastCurrent(contrast))?;
+            delay_long(delay, pause_ms);
+        }
+        self.send_command(Command::SetContrastCurrent(self.config.contrast))?;
+
+        self.write_all()
+    }
+
+    /// Reinitializes the display with the stored configuration and sends the whole framebuffer,
+    /// e.g. to recover from a bus error that left the controller in an unknown state. The
+    /// tracked changes are sent with it, so they are cleared.
+    ///
+    /// The scrolling is reset, and a gray scale table set with `set_gray_scale_table` or
+    /// `set_gamma` isn't stored, so it needs to be set again.
+    ///
+    /// Returns `DisplayError::InvalidFormatError` while the command interface is locked, as `init`
+    /// would unlock it.
+    pub fn reinitialize(&mut self) -> Result<(), DisplayError> {
+        if self.locked {
+            return Err(DisplayError::InvalidFormatError);
+        }
+
+        self.init()?;
+        self.write_all()?;
+        self.dirty.clear();
+        self.num_changed = 0;
+
+        Ok(())
+    }
+
+    /// Calls `write` and repeats it or reinitializes the display if it fails with a bus error,
+    /// as set by `DisplayConfig::retry`.
+    fn with_retry(
+        &mut self,
+        write: fn(&mut Self) -> Result<(), DisplayError>,
+    ) -> Result<(), DisplayError> {
+        let retry = self.config.retry;
+        let mut result = write(self);
+        for _ in 0..retry.retries {
+            // Only bus errors are transient, and a locked driver must not be reconfigured
+            if !matches!(result, Err(DisplayError::BusWriteError)) || self.locked {
+                break;
+            }
+            result = write(self);
+        }
+
+        match result {
+            Err(DisplayError::BusWriteError) if retry.reinitialize && !self.locked => {
+                self.reinitialize()
+            }
+            result => result,
+        }
+    }
+
+    fn write_all(&mut self) -> Result<(), DisplayError> {
+        self.send_command(Command::SetColumnAddress(self.col_start(), self.col_end()))?;
+        self.send_command(Command::SetRowAddress(0x00, Self::ROW_END))?;
+        self.send_command(Command::WriteRAM)?;
+        self.send_buffer()?;
+        self.stats.add_window(WIDTH * HEIGHT / 2);
+
+        Ok(())
+    }
+
+    /// Returns the framebuffer.
+    ///
+    /// The framebuffer holds `Self::BUFFER_SIZE` bytes in rows of `Self::ROW_BYTES` bytes, top
+    /// to bottom. Each byte holds two horizontally adjacent pixels, the left one in the bits of
+    /// `LEFT_PIXEL_MASK` and the right one in the bits of `RIGHT_PIXEL_MASK`, swapped if
+    /// `DisplayConfig::nibble_order` is `LeftLower`. A 90 or 270 degree rotation is not applied.
+    pub fn framebuffer(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+
+    /// Copies the framebuffer to `snapshot`, e.g. to save the screen before drawing a menu
+    /// overlay. The content can be brought back with `restore`.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `snapshot` is not the size of the
+    /// framebuffer.
+    pub fn snapshot(&self, snapshot: &mut [u8]) -> Result<(), DisplayError> {
+        if snapshot.len() != WIDTH * HEIGHT / 2 {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        snapshot.copy_from_slice(self.buffer.as_ref());
+
+        Ok(())
+    }
+
+    /// Restores the framebuffer from a `snapshot` and marks the rows that differ as changed,
+    /// e.g. to remove a dialog drawn over the previous content.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `snapshot` is not the size of the
+    /// framebuffer.
+    pub fn restore(&mut self, snapshot: &[u8]) -> Result<(), DisplayError> {
+        if snapshot.len() != WIDTH * HEIGHT / 2 {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        for (row, [first, last]) in row_diffs(snapshot, self.buffer.as_ref(), WIDTH / 2) {
+            self.dirty
+                .add_area(([first as u8, last as u8], [row as u8, row as u8]));
+        }
+        self.buffer.as_mut().copy_from_slice(snapshot);
+
+        Ok(())
+    }
+
+    /// Returns framebuffer row `y`, or `None` if it is outside of the framebuffer.
+    ///
+    /// Each byte holds two horizontally adjacent pixels, the left pixel in the upper nibble unless
+    /// `DisplayConfig::nibble_order` is `LeftLower`. The rows are the unrotated rows of the panel,
+    /// a 90 or 270 degree rotation is not applied.
+    pub fn row(&self, y: usize) -> Option<&[u8]> {
+        self.buffer.as_ref().chunks_exact(WIDTH / 2).nth(y)
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Returns the color of the pixel at `point` in the framebuffer, or `None` if the point is
+    /// outside of the display.
+    pub fn get_pixel(&self, point: Point) -> Option<Gray4> {
+        let (x, y) = self.to_physical(point)?;
+
+        Some(Gray4::new(self.read_pixel(x, y)))
+    }
+
+    #[cfg(any(feature = "graphics", feature = "std"))]
+    /// Returns the gray level of the framebuffer column `x` and row `y`.
+    fn read_pixel(&self, x: usize, y: usize) -> u8 {
+        let byte = self
+            .config
+            .nibble_order
+            .arrange(self.buffer.as_ref()[x / 2 + y * WIDTH / 2]);
+
+        if x.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        }
+    }
+
+    #[cfg(feature = "std")]
+    /// Writes the display content as a binary PGM image, e.g. a screenshot for the
+    /// documentation or a golden image for a test, with the rotation applied. The gray levels
+    /// are scaled to 0 to 255.
+    pub fn to_pgm<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let (width, height) = match self.config.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => (HEIGHT, WIDTH),
+        };
+        write!(writer, "P5\n{} {}\n255\n", width, height)?;
+
+        let mut line = [0; GDDRAM_COLUMNS * 4];
+        for y in 0..height {
+            for (x, luma) in line[..width].iter_mut().enumerate() {
+                *luma = match self.physical_xy(x, y) {
+                    Some((x, y)) => self.read_pixel(x, y) * 17,
+                    None => 0,
+                };
+            }
+            writer.write_all(&line[..width])?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Returns the bounding box of the parts of the framebuffer that changed since the last
+    /// flush, widened to whole bytes of 2 pixels, or `None` if nothing changed.
+    ///
+    /// Together with `clear_dirty` this allows callers that manage flushing themselves, e.g.
+    /// with `flush_region`, to use the tracked changes. In `UntrackedMode` it is the entire
+    /// display once something was drawn.
+    pub fn dirty_area(&self) -> Option<Rectangle> {
+        if !TRACKED && !self.dirty.is_empty() {
+            return Some(self.bounding_box());
+        }
+
+        self.dirty.union().map(|area| self.logical_area(area))
+    }
+
+    /// Returns the counters of the data sent by the flush methods, e.g. to tune the redraw
+    /// strategy of a UI on real hardware.
+    pub fn stats(&self) -> FlushStats {
+        self.stats
+    }
+
+    /// Resets the counters returned by `stats`.
+    pub fn reset_stats(&mut self) {
+        self.stats = FlushStats::default();
+    }
+
+    /// Sets the clock that measures the time spent in `flush` and `flush_all` for `stats`, e.g.
+    /// a function reading the cycle counter or a microsecond timer. The ticks may wrap around.
+    /// Without a clock the time isn't measured.
+    pub fn set_clock(&mut self, clock: Option<fn() -> u32>) {
+        self.clock = clock;
+    }
+
+    /// Returns the ticks of the clock set with `set_clock`, or 0 without a clock.
+    fn now(&self) -> u32 {
+        self.clock.map_or(0, |clock| clock())
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Fills the `area` of the framebuffer with `color` and marks it as changed, e.g. to blank a
+    /// widget before redrawing it. The pixels are replaced regardless of the draw mode.
+    pub fn clear_region(&mut self, area: Rectangle, color: Gray4) {
+        let mode = core::mem::replace(&mut self.draw_mode, DrawMode::Replace);
+        let Ok(()) = self.fill_solid(&area, color);
+        self.draw_mode = mode;
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Marks the `area` of the framebuffer as changed, so the next `flush` sends it, e.g. after
+    /// writing to the framebuffer without drawing. The area is clipped to the display.
+    pub fn mark_dirty(&mut self, area: Rectangle) {
+        if let Some(area) = self.physical_area(area) {
+            self.dirty.add_area(area);
+        }
+    }
+
+    /// Forgets the changes since the last flush, e.g. after sending them with `flush_region`.
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+        self.num_changed = 0;
+    }
+
+    /// Selects how the changed parts of the framebuffer are tracked between flushes. Pending
+    /// changes are kept.
+    pub fn set_dirty_tracking(&mut self, tracking: DirtyTracking) {
+        let pending = self.dirty.union();
+        self.dirty = DirtyAreas::new(tracking);
+        if let Some(area) = pending {
+            self.dirty.add_area(area);
+        }
+    }
+
+    /// Returns how the changed parts of the framebuffer are tracked.
+    pub fn dirty_tracking(&self) -> DirtyTracking {
+        self.dirty.tracking()
+    }
+
+    /// Selects how drawn pixels are combined with the framebuffer, e.g. `DrawMode::Xor` to draw
+    /// a cursor that is erased by drawing it again. The mode applies to drawing, `set_pixel`,
+    /// the blit methods and `dither_from_gray8`, but not to clearing or writes to the raw
+    /// framebuffer.
+    pub fn set_draw_mode(&mut self, mode: DrawMode) {
+        self.draw_mode = mode;
+    }
+
+    /// Returns how drawn pixels are combined with the framebuffer.
+    pub fn draw_mode(&self) -> DrawMode {
+        self.draw_mode
+    }
+
+    /// Flushes the entire framebuffer to the hidden half of the GDDRAM and then shows it, so an
+    /// animation never shows a partly written frame.
+    ///
+    /// The GDDRAM holds two frames for panels of up to 64 rows, which are shown alternately by
+    /// moving the start line. The other flush methods write to the rows starting at 0 and should
+    /// not be mixed with this one.
+    pub fn flush_page_flip(&mut self) -> Result<(), DisplayError> {
+        let () = PageFlipSize::<HEIGHT>::CHECK;
+
+        let hidden = if self.start_line == 0 {
+            HEIGHT as u8
+        } else {
+            0
+        };
+        self.send_command(Command::SetColumnAddress(self.col_start(), self.col_end()))?;
+        self.send_command(Command::SetRowAddress(hidden, hidden + Self::ROW_END))?;
+        self.send_command(Command::WriteRAM)?;
+        self.send_buffer()?;
+        self.stats.add_window(WIDTH * HEIGHT / 2);
+        self.scroll_to_line(hidden)?;
+
+        self.dirty.clear();
+        self.num_changed = 0;
+
+        Ok(())
+    }
+
+    /// Flushes only the changed portion of the display.
+    ///
+    /// Up to 4 separate changed areas are tracked, so changes far apart from each other, e.g.
+    /// two widgets at opposite corners, are sent as separate windows.
+    pub fn flush(&mut self) -> Result<(), DisplayError> {
+        let start = self.now();
+        let result = self.with_retry(Self::write_changes);
+        self.stats.flushes = self.stats.flushes.wrapping_add(1);
+        self.stats.flush_ticks = self
+            .stats
+            .flush_ticks
+            .wrapping_add(self.now().wrapping_sub(start));
+
+        result
+    }
+
+    fn write_changes(&mut self) -> Result<(), DisplayError> {
+        if !TRACKED {
+            // Nothing but the fact that something was drawn is known.
+            if !self.dirty.is_empty() {
+                self.write_all()?;
+            }
+        } else {
+            #[cfg(feature = "graphics")]
+            let union = self.dirty.union();
+            // The areas are taken one by one, a failed one is put back for the retry.
+            while let Some(area) = self.dirty.pop() {
+                let result = self
+                    .start_window(area.0, area.1)
+                    .and_then(|mut op| self.poll_flush(&mut op, usize::MAX));
+                if let Err(error) = result {
+                    self.dirty.add_area(area);
+                    return Err(error);
+                }
+            }
+            #[cfg(feature = "graphics")]
+            if let Some(area) = union {
+                self.stats.last_dirty_area = Some(self.logical_area(area));
+            }
+        }
+
+        // Reset the changed areas
+        self.dirty.clear();
+        self.num_changed = 0;
+
+        Ok(())
+    }
+
+    /// Flushes the bytes that differ from `shadow`, a copy of the frame that was flushed last,
+    /// and then updates the copy. This suits applications that redraw whole widgets every frame,
+    /// which the tracking of changes while drawing reports as changed even if they look the
+    /// same.
+    ///
+    /// Each run of rows is sent as one window spanning the differing bytes. `shadow` has to
+    /// match the GDDRAM when it is first used, e.g. zeroed after clearing the display and
+    /// calling `flush_all`. The tracked changes are reset.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `shadow` is not the size of the framebuffer.
+    pub fn flush_diff(&mut self, shadow: &mut [u8]) -> Result<(), DisplayError> {
+        if shadow.len() != WIDTH * HEIGHT / 2 {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        let mut diff = DirtyAreas::new(DirtyTracking::RowSpans);
+        for (row, [first, last]) in row_diffs(self.buffer.as_ref(), shadow, WIDTH / 2) {
+            diff.add(first as u8, row as u8);
+            diff.add(last as u8, row as u8);
+        }
+
+        for (col_addr, row_addr) in diff.iter() {
+            let mut op = self.start_window(col_addr, row_addr)?;
+            self.poll_flush(&mut op, usize::MAX)?;
+        }
+        shadow.copy_from_slice(self.buffer.as_ref());
+
+        self.dirty.clear();
+        self.num_changed = 0;
+
+        Ok(())
+    }
+
+    /// Programs the address window of the entire display and issues WriteRAM, leaving the data
+    /// phase to the caller, e.g. a DMA transfer of `dma_buffer()`.
+    ///
+    /// The caller is responsible for driving the interface into data mode (D/C high) and for
+    /// sending exactly `dma_buffer().len()` bytes before any other command. The changed area is
+    /// reset, since the whole framebuffer is expected to be sent.
+    pub fn prepare_flush(&mut self) -> Result<(), DisplayError> {
+        self.start_flush_all()?;
+        self.stats.add_bytes(WIDTH * HEIGHT / 2);
+
+        self.dirty.clear();
+        self.num_changed = 0;
+
+        Ok(())
+    }
+
+    /// Returns the framebuffer in the byte order expected by the data phase following
+    /// `prepare_flush`.
+    ///
+    /// Each byte holds two horizontally adjacent pixels, the left pixel in the upper nibble unless
+    /// `DisplayConfig::nibble_order` is `LeftLower`, and rows are stored top to bottom without
+    /// padding.
+    pub fn dma_buffer(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+
+    /// Starts a resumable flush of one of the changed areas of the display.
+    ///
+    /// This programs the address window and returns the operation to pass to `poll_flush`, or
+    /// `None` if nothing changed. Once the operation is done, call `start_flush` again to flush
+    /// the next changed area until it returns `None`. The area is forgotten when its flush
+    /// starts, so pixels drawn while the flush is in progress are sent later. No other commands
+    /// may be sent to the display until the operation is done.
+    pub fn start_flush(&mut self) -> Result<Option<FlushOp>, DisplayError> {
+        if !TRACKED {
+            if self.dirty.is_empty() {
+                return Ok(None);
+            }
+            let op = self.start_flush_all()?;
+            self.dirty.clear();
+            self.num_changed = 0;
+
+            return Ok(Some(op));
+        }
+
+        let next = self.dirty.iter().next();
+        match next {
+            Some((col_addr, row_addr)) => {
+                let op = self.start_window(col_addr, row_addr)?;
+                #[cfg(feature = "graphics")]
+                {
+                    self.stats.last_dirty_area = Some(self.logical_area((col_addr, row_addr)));
+                }
+
+                self.dirty.pop();
+                if self.dirty.is_empty() {
+                    self.num_changed = 0;
+                }
+
+                Ok(Some(op))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Starts a resumable flush of the entire display. See `start_flush`.
+    pub fn start_flush_all(&mut self) -> Result<FlushOp, DisplayError> {
+        self.start_window([0, (WIDTH / 2 - 1) as u8], [0, Self::ROW_END])
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Flushes the `area` of the framebuffer, independent of the changed area that is tracked
+    /// while drawing, e.g. when the application knows exactly what changed.
+    ///
+    /// The area is clipped to the display and widened to whole column addresses of 4 pixels.
+    /// The changed area is left as it is.
+    pub fn flush_region(&mut self, area: Rectangle) -> Result<(), DisplayError> {
+        if let Some(mut op) = self.start_flush_region(area)? {
+            self.poll_flush(&mut op, usize::MAX)?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "graphics")]
+    /// Starts a resumable flush of the `area` of the framebuffer, or returns `None` if the area
+    /// is outside of the display. See `flush_region` and `start_flush`.
+    pub fn start_flush_region(&mut self, area: Rectangle) -> Result<Option<FlushOp>, DisplayError> {
+        match self.physical_area(area) {
+            Some((col_addr, row_addr)) => self.start_window(col_addr, row_addr).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Sends up to `max_bytes` of the framebuffer for a flush started by `start_flush`,
+    /// `start_flush_all` or `start_flush_region`.
+    ///
+    /// Returns `FlushStatus::Pending` until the last byte was sent. If sending fails the
+    /// operation is left unchanged, so the same chunk is sent again by the next call.
+    pub fn poll_flush(
+        &mut self,
+        op: &mut FlushOp,
+        max_bytes: usize,
+    ) -> Result<FlushStatus, DisplayError> {
+        let mut budget = max_bytes.max(1);
+
+        while !op.is_done() && budget > 0 {
+            let start = op.row * WIDTH / 2 + op.col_byte + op.offset;
+            // The rows of a full width window are contiguous in the framebuffer, so they are
+            // sent in one transfer.
+            let remaining = if op.num_col_bytes == WIDTH / 2 {
+                (op.last_row - op.row + 1) * op.num_col_bytes - op.offset
+            } else {
+                op.num_col_bytes - op.offset
+            };
+            let len = self.transfer_len(remaining.min(budget));
+            self.send_bytes(start, len)?;
+            self.stats.add_bytes(len);
+
+            budget = budget.saturating_sub(len);
+            op.offset += len;
+            op.row += op.offset / op.num_col_bytes;
+            op.offset %= op.num_col_bytes;
+        }
+
+        if op.is_done() {
+            Ok(FlushStatus::Done)
+        } else {
+            Ok(FlushStatus::Pending)
+        }
+    }
+
+    /// Sends the entire framebuffer in transfers of up to `DisplayConfig::max_transfer` bytes.
+    fn send_buffer(&mut self) -> Result<(), DisplayError> {
+        let len = self.transfer_len(usize::MAX);
+        for start in (0..WIDTH * HEIGHT / 2).step_by(len) {
+            self.send_bytes(start, len.min(WIDTH * HEIGHT / 2 - start))?;
+        }
+
+        Ok(())
+    }
+
+    /// Limits a transfer of `len` framebuffer bytes to `DisplayConfig::max_transfer`, and to
+    /// whole words if `DisplayConfig::data_width` is `DataWidth::U16`.
+    fn transfer_len(&self, len: usize) -> usize {
+        let len = len.min(self.config.max_transfer.max(1));
+        match self.config.data_width {
+            DataWidth::U8 => len,
+            DataWidth::U16 => (len - len % 2).max(2),
+        }
+    }
+
+    /// Sends the `len` framebuffer bytes at `start` in words of `DisplayConfig::data_width`.
+    fn send_bytes(&mut self, start: usize, len: usize) -> Result<(), DisplayError> {
+        let bytes = &self.buffer.as_ref()[start..start + len];
+        match self.config.data_width {
+            DataWidth::U8 => self.display.send_data(U8(bytes)),
+            DataWidth::U16 => {
+                let mut words = [0; WORD_CHUNK_SIZE];
+                for chunk in bytes.chunks(WORD_CHUNK_SIZE * 2) {
+                    let words = &mut words[..chunk.len() / 2];
+                    for (word, pair) in words.iter_mut().zip(chunk.chunks_exact(2)) {
+                        *word = u16::from_be_bytes([pair[0], pair[1]]);
+                    }
+                    self.display.send_data(U16BE(words))?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Programs the address window for the framebuffer bytes `col_addr` of the rows `row_addr`
+    /// and returns the operation that sends them.
+    fn start_window(
+        &mut self,
+        mut col_addr: [u8; 2],
+        row_addr: [u8; 2],
+    ) -> Result<FlushOp, DisplayError> {
+        col_addr[0] -= col_addr[0] % 2;
+        col_addr[1] -= col_addr[1] % 2;
+
+        // Convert bytes to column address
+        self.send_command(Command::SetColumnAddress(
+            col_addr[0] / 2 + self.col_start(),
+            col_addr[1] / 2 + self.col_start(),
+        ))?;
+        self.send_command(Command::SetRowAddress(row_addr[0], row_addr[1]))?;
+        self.send_command(Command::WriteRAM)?;
+        self.stats.add_window(0);
+
+        Ok(FlushOp {
+            row: row_addr[0].into(),
+            last_row: row_addr[1].into(),
+            col_byte: col_addr[0].into(),
+            num_col_bytes: usize::from(col_addr[1] - col_addr[0]) + 2,
+            offset: 0,
+        })
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const TRACKED: bool>
+    Ssd1322<DI, WIDTH, HEIGHT, B, BufferedGraphicsMode<TRACKED>>
+where
+    B: AsMut<[u8]>,
+{
+    /// Calls `f` with a driver that sends to `display` and shares the framebuffer and the state
+    /// of this one.
+    pub(crate) fn with_interface<DI2, R, F>(&mut self, display: DI2, f: F) -> R
+    where
+        F: FnOnce(&mut Ssd1322<DI2, WIDTH, HEIGHT, &mut [u8], BufferedGraphicsMode<TRACKED>>) -> R,
+    {
+        let Self {
+            buffer,
+            config,
+            dirty,
+            num_changed,
+            stats,
+            clock,
+            draw_mode,
+            locked,
+            start_line,
+            ..
+        } = self;
+        let mut disp = Ssd1322 {
+            display,
+            buffer: buffer.as_mut(),
+            config: *config,
+            dirty: core::mem::take(dirty),
+            num_changed: *num_changed,
+            stats: *stats,
+            clock: *clock,
+            draw_mode: *draw_mode,
+            locked: *locked,
+            start_line: *start_line,
+            mode: BufferedGraphicsMode,
+        };
+        let result = f(&mut disp);
+
+        *config = disp.config;
+        *dirty = disp.dirty;
+        *num_changed = disp.num_changed;
+        *stats = disp.stats;
+        *clock = disp.clock;
+        *draw_mode = disp.draw_mode;
+        *locked = disp.locked;
+        *start_line = disp.start_line;
+
+        result
+    }
+
+    /// Sets the pixel at `x`, `y` to the gray level `gray` (0 to 15), without going through
+    /// embedded-graphics. Pixels outside of the display are ignored.
+    pub fn set_pixel(&mut self, x: u16, y: u16, gray: u8) {
+        if let Some((x, y)) = self.physical_xy(x.into(), y.into()) {
+            self.write_pixel(x, y, gray & 0x0F);
+        }
+    }
+
+    /// Returns the framebuffer for writing, e.g. for a custom compositor or to decode assets in
+    /// place. See `framebuffer` for the layout. Changes are not tracked, mark them with
+    /// `mark_dirty`.
+    pub fn framebuffer_mut(&mut self) -> &mut [u8] {
+        self.buffer.as_mut()
+    }
+
+    /// Returns framebuffer row `y` for writing, e.g. by a scanline renderer, or `None` if it is
+    /// outside of the framebuffer. See `row` for the layout. Changes are not tracked, mark
+    /// them with `mark_dirty`.
+    pub fn row_mut(&mut self, y: usize) -> Option<&mut [u8]> {
+        self.buffer.as_mut().chunks_exact_mut(WIDTH / 2).nth(y)
+    }
+
+    /// Copies a `width` x `height` image of packed 4 bit pixels to `x`, `y`, e.g. an asset
+    /// stored in flash, and marks it as changed. Pixels outside of the display are ignored.
+    ///
+    /// Each row of `data` starts at a new byte and holds two pixels per byte, the left pixel in
+    /// the upper nibble. Without rotation and at an even `x` the rows are copied byte by byte,
+    /// otherwise pixel by pixel.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `data` is too short for the image.
+    pub fn blit_packed(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        data: &[u8],
+    ) -> Result<(), DisplayError> {
+        let stride = usize::from(width).div_ceil(2);
+        if data.len() < stride * usize::from(height) {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        let unrotated = matches!(
+            self.config.rotation,
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180
+        );
+        if !unrotated || !x.is_multiple_of(2) || self.draw_mode != DrawMode::Replace {
+            for row in 0..height {
+                for col in 0..width {
+                    let byte = data[usize::from(row) * stride + usize::from(col) / 2];
+                    let gray = if col.is_multiple_of(2) {
+                        byte >> 4
+                    } else {
+                        byte & 0x0F
+                    };
+                    self.set_pixel(x.saturating_add(col), y.saturating_add(row), gray);
+                }
+            }
+
+            return Ok(());
+        }
+
+        let (x, y) = (usize::from(x), usize::from(y));
+        if x >= WIDTH || y >= HEIGHT || width == 0 || height == 0 {
+            return Ok(());
+        }
+        let cols = usize::from(width).min(WIDTH - x);
+        let rows = usize::from(height).min(HEIGHT - y);
+
+        let order = self.config.nibble_order;
+        let mut changed = 0u16;
+        for (row, src) in data.chunks(stride).take(rows).enumerate() {
+            let start = (y + row) * WIDTH / 2 + x / 2;
+            let dst = &mut self.buffer.as_mut()[start..][..cols.div_ceil(2)];
+            for (i, (old_val, &new_val)) in dst.iter_mut().zip(src).enumerate() {
+                // A trailing odd pixel only covers the upper nibble
+                let new_val = if i * 2 + 1 == cols {
+                    update_upper_nibble(order.arrange(*old_val), new_val >> 4)
+                } else {
+                    new_val
+                };
+                let new_val = order.arrange(new_val);
+
+                changed += changed_pixels(*old_val, new_val);
+                *old_val = new_val;
+            }
+        }
+
+        if changed > 0 {
+            self.num_changed = self.num_changed.saturating_add(changed);
+            self.dirty.add_area((
+                [(x / 2) as u8, ((x + cols - 1) / 2) as u8],
+                [y as u8, (y + rows - 1) as u8],
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Copies a `width` x `height` image of packed 4 bit pixels to `x`, `y` like `blit_packed`,
+    /// but skips the pixels with the gray level `key` (0 to 15), so icons with irregular shapes
+    /// can be drawn over a background without a separate mask.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `data` is too short for the image.
+    pub fn blit_with_key(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        data: &[u8],
+        key: u8,
+    ) -> Result<(), DisplayError> {
+        let stride = usize::from(width).div_ceil(2);
+        if data.len() < stride * usize::from(height) {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        for row in 0..height {
+            for col in 0..width {
+                let byte = data[usize::from(row) * stride + usize::from(col) / 2];
+                let gray = if col.is_multiple_of(2) {
+                    byte >> 4
+                } else {
+                    byte & 0x0F
+                };
+                if gray != key & 0x0F {
+                    self.set_pixel(x.saturating_add(col), y.saturating_add(row), gray);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts a `width` x `height` image of 8 bit gray pixels, e.g. a decoded photo, to the
+    /// framebuffer at the top left corner with Floyd-Steinberg dithering, and marks it as
+    /// changed. Pixels outside of the display are ignored.
+    ///
+    /// The rounding error of each pixel is diffused to its neighbours, which shows gradients
+    /// with far less banding than rounding alone.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if `image` does not hold `width * height`
+    /// pixels.
+    pub fn dither_from_gray8(
+        &mut self,
+        image: &[u8],
+        width: u16,
+        height: u16,
+    ) -> Result<(), DisplayError> {
+        let (width, height) = (usize::from(width), usize::from(height));
+        if image.len() != width * height {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        // Errors diffused to the current and the next row, offset by one column so the left
+        // and right neighbours of every pixel have an entry.
+        let mut errors = [[0i16; MAX_DITHER_WIDTH + 2]; 2];
+        let cols = width.min(MAX_DITHER_WIDTH);
+        for (y, row) in image.chunks(width.max(1)).take(height).enumerate() {
+            let (current, next) = errors.split_at_mut(1);
+            let (current, next) = (&mut current[0], &mut next[0]);
+            for (x, &luma) in row[..cols].iter().enumerate() {
+                let value = (i16::from(luma) + current[x + 1] / 16).clamp(0, 255);
+                let gray = ((value * 15 + 127) / 255) as u8;
+                let error = value - i16::from(gray) * 17;
+
+                current[x + 2] += error * 7;
+                next[x] += error * 3;
+                next[x + 1] += error * 5;
+                next[x + 2] += error;
+
+                if let Some((x, y)) = self.physical_xy(x, y) {
+                    self.write_pixel(x, y, gray);
+                }
+            }
+
+            *current = *next;
+            *next = [0; MAX_DITHER_WIDTH + 2];
+        }
+
+        Ok(())
+    }
+
+    /// Writes the gray level `luma` to the framebuffer column `x` and row `y` and tracks the
+    /// change. In `UntrackedMode` it only notes that something was drawn.
+    fn write_pixel(&mut self, x: usize, y: usize, luma: u8) {
+        // Calculate the index in the framebuffer.
+        let index = (x / 2) + (y * (WIDTH / 2));
+        let order = self.config.nibble_order;
+        let old_val = self.buffer.as_mut()[index];
+        let pixels = order.arrange(old_val);
+        let new_val = order.arrange(if x.is_multiple_of(2) {
+            update_upper_nibble(pixels, self.draw_mode.apply(pixels >> 4, luma))
+        } else {
+            update_lower_nibble(pixels, self.draw_mode.apply(pixels & 0x0F, luma))
+        });
+
+        if !TRACKED {
+            self.buffer.as_mut()[index] = new_val;
+            self.dirty.touch();
+        } else if new_val != old_val {
+            // Update only if changed
+            self.num_changed = self.num_changed.saturating_add(1);
+            self.update_box(x as u16, y as u16);
+            self.buffer.as_mut()[index] = new_val;
+        }
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const TRACKED: bool> BoundingBox
+    for Ssd1322<DI, WIDTH, HEIGHT, B, BufferedGraphicsMode<TRACKED>>
+{
+    fn update_box(&mut self, x: u16, y: u16) {
+        // The column unit is bytes and both fit in a u8 for the largest (480x128) GDDRAM.
+        self.dirty.add((x / 2) as u8, y as u8);
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const TRACKED: bool> DrawTarget
+    for Ssd1322<DI, WIDTH, HEIGHT, B, BufferedGraphicsMode<TRACKED>>
+where
+    B: AsMut<[u8]>,
+{
+    type Color = Gray4;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels.into_iter() {
+            // Check if the pixel coordinates are out of bounds (negative or greater than the
+            // size). `DrawTarget` implementation are required to discard any out of bounds
+            // pixels without returning an error or causing a panic.
+            if let Some((x, y)) = self.to_physical(coord) {
+                self.write_pixel(x, y, color.luma());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let (x, y) = match self.physical_pixels(*area) {
+            Some(pixels) => pixels,
+            None => return Ok(()),
+        };
+
+        // Whole bytes are written at once, a partly covered byte at either end only gets the
+        // nibble of its covered pixel.
+        let luma = color.luma();
+        let mode = self.draw_mode;
+        let order = self.config.nibble_order;
+        let (first, last) = (x[0] / 2, x[1] / 2);
+        let mut changed = 0u16;
+        for row in y[0]..=y[1] {
+            let line = &mut self.buffer.as_mut()[row * WIDTH / 2..][..WIDTH / 2];
+            for (i, old_val) in line[first..=last].iter_mut().enumerate() {
+                let pixels = order.arrange(*old_val);
+                let upper = mode.apply(pixels >> 4, luma);
+                let lower = mode.apply(pixels & 0x0F, luma);
+                let new_val = order.arrange(match (first + i) * 2 {
+                    col if col < x[0] => update_lower_nibble(pixels, lower),
+                    col if col + 1 > x[1] => update_upper_nibble(pixels, upper),
+                    _ => (upper << 4) | lower,
+                });
+
+                changed += changed_pixels(*old_val, new_val);
+                *old_val = new_val;
+            }
+        }
+
+        if changed > 0 {
+            self.num_changed = self.num_changed.saturating_add(changed);
+            self.dirty
+                .add_area(([first as u8, last as u8], [y[0] as u8, y[1] as u8]));
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, fill: Self::Color) -> Result<(), Self::Error> {
+        // Marks the changed part of the screen, so `flush` shows the cleared screen. The pixels
+        // are replaced regardless of the draw mode.
+        let mode = core::mem::replace(&mut self.draw_mode, DrawMode::Replace);
+        let result = self.fill_solid(&self.bounding_box(), fill);
+        self.draw_mode = mode;
+
+        result
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> OriginDimensions
+    for Ssd1322<DI, WIDTH, HEIGHT, B, MODE>
+{
+    fn size(&self) -> Size {
+        match self.config.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                Size::new(WIDTH as u32, HEIGHT as u32)
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(HEIGHT as u32, WIDTH as u32)
+            }
+        }
+    }
+}
+
+/// Forwards to the display, so it can be passed by mutable reference to drawing code that takes
+/// a `DrawTarget` by value.
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> DrawTarget
+    for &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>
+where
+    Ssd1322<DI, WIDTH, HEIGHT, B, MODE>: DrawTarget,
+{
+    type Color = <Ssd1322<DI, WIDTH, HEIGHT, B, MODE> as DrawTarget>::Color;
+    type Error = <Ssd1322<DI, WIDTH, HEIGHT, B, MODE> as DrawTarget>::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        (**self).draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        (**self).fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        (**self).fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        (**self).clear(color)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> OriginDimensions
+    for &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>
+{
+    fn size(&self) -> Size {
+        (**self).size()
+    }
+}
+
+/// Returns the rows of `new` and `old` that differ and the first and last differing byte of each.
+fn row_diffs<'a>(
+    new: &'a [u8],
+    old: &'a [u8],
+    row_bytes: usize,
+) -> impl Iterator<Item = (usize, [usize; 2])> + 'a {
+    let rows = new.chunks(row_bytes).zip(old.chunks(row_bytes));
+    rows.enumerate().filter_map(|(row, (new, old))| {
+        let first = new.iter().zip(old).position(|(new, old)| new != old)?;
+        let last = new.iter().zip(old).rposition(|(new, old)| new != old)?;
+
+        Some((row, [first, last]))
+    })
+}
+
+/// Waits `ms` milliseconds with a delay that takes at most `u8::MAX` at a time.
+fn delay_long<DELAY: DelayMs<u8>>(delay: &mut DELAY, mut ms: u16) {
+    while ms > 0 {
+        let step = ms.min(u16::from(u8::MAX)) as u8;
+        delay.delay_ms(step);
+        ms -= u16::from(step);
+    }
+}
+
+/// Returns the number of pixels that differ between the framebuffer bytes `old` and `new`.
+#[inline]
+fn changed_pixels(old: u8, new: u8) -> u16 {
+    let diff = old ^ new;
+    u16::from(diff & 0xF0 != 0) + u16::from(diff & 0x0F != 0)
+}
+
+#[inline]
+pub(crate) fn update_upper_nibble(input: u8, color: u8) -> u8 {
+    ((color << 4) & 0xF0) | (input & 0x0F)
+}
+
+#[inline]
+pub(crate) fn update_lower_nibble(input: u8, color: u8) -> u8 {
+    color & 0x0F | (input & 0xF0)
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+    use crate::builder::Ssd1322Builder;
+    use display_interface::DataFormat;
+    use embedded_graphics::{
+        mono_font::{ascii::FONT_6X10, MonoTextStyleBuilder},
+        pixelcolor::Gray4,
+        text::{Baseline, Text},
+    };
+    type Result = core::result::Result<(), DisplayError>;
+
+    pub struct TestInterface1 {}
+
+    impl WriteOnlyDataCommand for TestInterface1 {
+        fn send_commands(&mut self, _cmds: DataFormat<'_>) -> Result {
+            Ok(())
+        }
+
+        fn send_data(&mut self, buf: DataFormat<'_>) -> Result {
+            match buf {
+                U8(_slice) => Ok(()),
+                _ => Err(DisplayError::DataFormatNotImplemented),
+            }
+        }
+    }
+
+    #[test]
+    /// Tests the character '|'. The framebuffer looks like starting from beginning of row 0
+    /// where each '.' represents a pixel.
+    /// ......
+    /// ..x...
+    /// ..x...
+    /// ..x...
+    /// ..x...
+    /// ..x...
+    /// ..x...
+    /// ..x...
+    ///
+    fn single_char_one_col() {
+        let s = TestInterface1 {};
+        let mut disp: Ssd1322<_> = Ssd1322::new(s);
+
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(Gray4::new(0b0000_1111))
+            .build();
+
+        Text::with_baseline("|", Point::new(0, 0), text_style, Baseline::Top)
+            .draw(&mut disp)
+            .unwrap();
+
+        assert_eq!(disp.dirty.union().unwrap().0[0], 1);
+        assert_eq!(disp.dirty.union().unwrap().0[1], 1);
+        assert_eq!(disp.dirty.union().unwrap().1[0], 1);
+        assert_eq!(disp.dirty.union().unwrap().1[1], 7);
+        assert_eq!(disp.num_changed, 7);
+
+        for i in 1..8 {
+            let start = i * 128;
+            assert_eq!(&disp.buffer[start..start + 3], [0, 0xf0, 0]);
+        }
+
+        let _ = disp.flush();
+    }
+
+    #[test]
+    /// Tests the character 'A'. The framebuffer looks like starting from beginning of row 0
+    /// where each '.' represents a pixel.
+    /// ......
+    /// ..x...
+    /// .x.x..
+    /// x...x.
+    /// x...x.
+    /// xxxxx.
+    /// x...x.
+    /// x...x.
+    ///
+    fn single_char_multi_col() {
+        let s = TestInterface1 {};
+        let mut disp: Ssd1322<_> = Ssd1322::new(s);
+
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(Gray4::new(0b0000_1111))
+            .build();
+
+        Text::with_baseline("A", Point::new(0, 0), text_style, Baseline::Top)
+            .draw(&mut disp)
+            .unwrap();
+
+        assert_eq!(disp.dirty.union().unwrap().0[0], 0);
+        assert_eq!(disp.dirty.union().unwrap().0[1], 2);
+        assert_eq!(disp.dirty.union().unwrap().1[0], 1);
+        assert_eq!(disp.dirty.union().unwrap().1[1], 7);
+        assert_eq!(disp.num_changed, 16);
+
+        let _ = disp.flush();
+    }
+
+    #[test]
+    /// Tests the character 'A' at an offset.
+    /// .......
+    /// .......
+    /// .......
+    /// .......
+    /// .......
+    /// .......
+    /// ...x...
+    /// ..x.x..
+    /// .x...x.
+    /// .x...x.
+    /// .xxxxx.
+    /// .x...x.
+    /// .x...x.
+    ///
+    fn single_char_offset() {
+        let s = TestInterface1 {};
+        let mut disp: Ssd1322<_> = Ssd1322::new(s);
+
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(Gray4::new(0b0000_1111))
+            .build();
+
+        Text::with_baseline("A", Point::new(1, 5), text_style, Baseline::Top)
+            .draw(&mut disp)
+            .unwrap();
+
+        assert_eq!(disp.dirty.union().unwrap().0[0], 0);
+        assert_eq!(disp.dirty.union().unwrap().0[1], 2);
+        assert_eq!(disp.dirty.union().unwrap().1[0], 6);
+        assert_eq!(disp.dirty.union().unwrap().1[1], 12);
+        assert_eq!(disp.num_changed, 16);
+
+        let _ = disp.flush();
+    }
+
+    #[test]
+    /// Tests the character 'A' clipped at the right.
+    /// .......
+    /// ....... x
+    /// .......x x
+    /// ......x   x
+    /// ......x   x
+    /// ......xxxxx
+    /// ......x   x
+    /// ......x   x
+    ///
+    fn single_char_clipped() {
+        let s = TestInterface1 {};
+        let mut disp: Ssd1322<_> = Ssd1322::new(s);
+
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(Gray4::new(0b0000_1111))
+            .build();
+
+        Text::with_baseline("A", Point::new(255, 0), text_style, Baseline::Top)
+            .draw(&mut disp)
+            .unwrap();
+
+        assert_eq!(disp.dirty.union().unwrap().0[0], 127);
+        assert_eq!(disp.dirty.union().unwrap().0[1], 127);
+        assert_eq!(disp.dirty.union().unwrap().1[0], 3);
+        assert_eq!(disp.dirty.union().unwrap().1[1], 7);
+        assert_eq!(disp.num_changed, 5);
+
+        let _ = disp.flush();
+    }
+
+    #[test]
+    /// Tests that the 256x32 preset only allocates and flushes 32 rows.
+    fn preset_256x32() {
+        let s = TestInterface1 {};
+        let mut disp = Ssd1322::new_256x32(s);
+
+        assert_eq!(disp.size(), Size::new(256, 32));
+        assert_eq!(disp.buffer.len(), 4096);
+        assert_eq!(Ssd1322::<TestInterface1, 256, 32, [u8; 4096]>::ROW_END, 31);
+
+        let _ = disp.init();
+        let _ = disp.flush_all();
+    }
+
+    #[test]
+    /// Tests that the 480x128 preset spans the whole GDDRAM.
+    fn preset_480x128() {
+        type Disp = Ssd1322<TestInterface1, 480, 128, [u8; 30720]>;
+
+        assert_eq!(Disp::ROW_END, 0x7F);
+
+        let s = TestInterface1 {};
+        let mut disp = Ssd1322::new_480x128(s);
+
+        assert_eq!(disp.config().remap, [0x14, 0x01]);
+        assert_eq!(disp.col_start(), 0x00);
+        assert_eq!(disp.col_end(), 0x77);
+
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(Gray4::new(0b0000_1111))
+            .build();
+
+        Text::with_baseline("A", Point::new(477, 120), text_style, Baseline::Top)
+            .draw(&mut disp)
+            .unwrap();
+
+        assert_eq!(disp.dirty.union().unwrap().0[0], 238);
+        assert_eq!(disp.dirty.union().unwrap().0[1], 239);
+        assert_eq!(disp.dirty.union().unwrap().1[1], 127);
+
+        let _ = disp.flush();
+    }
+
+    #[test]
+    /// Tests the default column window of a 256 pixel wide panel and the validation of custom
+    /// offsets.
+    fn display_config_offsets() {
+        let disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        assert_eq!(disp.col_start(), 0x1C);
+        assert_eq!(disp.col_end(), 0x5B);
+
+        let config = DisplayConfig {
+            col_offset: 0x20,
+            row_offset: 0x10,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> = Ssd1322::new_with_config(TestInterface1 {}, config);
+        assert_eq!(disp.col_start(), 0x20);
+        assert_eq!(disp.col_end(), 0x5F);
+        assert!(disp.init().is_ok());
+
+        let config = DisplayConfig {
+            col_offset: 0x39,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> = Ssd1322::new_with_config(TestInterface1 {}, config);
+        assert!(matches!(disp.init(), Err(DisplayError::OutOfBoundsError)));
+
+        let config = DisplayConfig {
+            clock: 0x9B,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> = Ssd1322::new_with_config(TestInterface1 {}, config);
+        assert!(matches!(disp.init(), Err(DisplayError::OutOfBoundsError)));
+    }
+
+    #[test]
+    /// Tests the coordinate transform of the 90 degree rotation and the remap and column window
+    /// of the 180 degree rotation.
+    fn rotation() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.set_rotation(DisplayRotation::Rotate90).unwrap();
+        assert_eq!(disp.size(), Size::new(64, 256));
+
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(63, 255), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(64, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(disp.buffer[127], 0x0f);
+        assert_eq!(disp.buffer[63 * 128], 0xf0);
+        assert_eq!(disp.num_changed, 2);
+
+        let config = DisplayConfig {
+            col_offset: 0x10,
+            rotation: DisplayRotation::Rotate180,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let disp: Ssd1322<_> = Ssd1322::new_with_config(TestInterface1 {}, config);
+        assert_eq!(disp.size(), Size::new(256, 64));
+        assert_eq!(disp.remap(), [0x06, 0x11]);
+        assert_eq!(disp.col_start(), 0x28);
+        assert_eq!(disp.col_end(), 0x67);
+    }
+
+    #[test]
+    /// Tests that a rotation the bus fails to send leaves the rotation unchanged.
+    fn rotation_bus_error() {
+        use crate::mock::RecordingInterface;
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        disp.display.set_fail(true);
+        assert!(matches!(
+            disp.set_rotation(DisplayRotation::Rotate180),
+            Err(DisplayError::BusWriteError)
+        ));
+        assert_eq!(disp.rotation(), DisplayRotation::Rotate0);
+        assert_eq!(disp.remap(), [0x14, 0x11]);
+
+        disp.display.set_fail(false);
+        disp.set_rotation(DisplayRotation::Rotate180).unwrap();
+        assert_eq!(disp.rotation(), DisplayRotation::Rotate180);
+        assert!(disp.display.has_command(&[0xA0, 0x06, 0x11]));
+    }
+
+    #[test]
+    /// Tests that mirroring toggles the remap bits and combines with the 180 degree rotation.
+    fn mirror() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.set_mirror(true, false).unwrap();
+        assert_eq!(disp.remap(), [0x16, 0x11]);
+        disp.set_mirror(false, true).unwrap();
+        assert_eq!(disp.remap(), [0x04, 0x11]);
+
+        disp.set_rotation(DisplayRotation::Rotate180).unwrap();
+        assert_eq!(disp.remap(), [0x16, 0x11]);
+        disp.set_mirror(true, true).unwrap();
+        assert_eq!(disp.remap(), [0x14, 0x11]);
+    }
+
+    #[test]
+    /// Tests that mirroring the bus fails to send leaves the mirroring unchanged.
+    fn mirror_bus_error() {
+        use crate::mock::RecordingInterface;
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        disp.display.set_fail(true);
+        assert!(matches!(
+            disp.set_mirror(true, true),
+            Err(DisplayError::BusWriteError)
+        ));
+        assert!(!disp.config().mirror_horizontal && !disp.config().mirror_vertical);
+        assert_eq!(disp.remap(), [0x14, 0x11]);
+
+        disp.display.set_fail(false);
+        disp.set_mirror(true, false).unwrap();
+        assert!(disp.config().mirror_horizontal && !disp.config().mirror_vertical);
+        assert!(disp.display.has_command(&[0xA0, 0x16, 0x11]));
+    }
+
+    #[test]
+    /// Tests that a resumable flush sends the changed area in chunks of at most the requested
+    /// size.
+    fn resumable_flush() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        assert_eq!(disp.start_flush().unwrap(), None);
+
+        Text::with_baseline(
+            "A",
+            Point::new(0, 0),
+            MonoTextStyleBuilder::new()
+                .font(&FONT_6X10)
+                .text_color(Gray4::WHITE)
+                .build(),
+            Baseline::Top,
+        )
+        .draw(&mut disp)
+        .unwrap();
+
+        // 7 rows of 4 bytes
+        let mut op = disp.start_flush().unwrap().unwrap();
+        assert!(disp.dirty.is_empty());
+        for _ in 0..9 {
+            assert_eq!(disp.poll_flush(&mut op, 3).unwrap(), FlushStatus::Pending);
+        }
+        assert_eq!(disp.poll_flush(&mut op, 3).unwrap(), FlushStatus::Done);
+        assert!(op.is_done());
+
+        let mut op = disp.start_flush_all().unwrap();
+        assert_eq!(
+            disp.poll_flush(&mut op, 4095).unwrap(),
+            FlushStatus::Pending
+        );
+        assert_eq!(disp.poll_flush(&mut op, 4097).unwrap(), FlushStatus::Done);
+    }
+
+    #[test]
+    /// Tests that the DMA buffer is the packed framebuffer and that preparing a flush resets the
+    /// changed area.
+    fn dma_flush() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        Pixel(Point::new(1, 1), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+
+        disp.prepare_flush().unwrap();
+        assert!(disp.dirty.is_empty());
+        assert_eq!(disp.dma_buffer().len(), 8192);
+        assert_eq!(disp.dma_buffer()[128], 0x0f);
+    }
+
+    #[test]
+    /// Tests that an external framebuffer is cleared and drawn into.
+    fn external_buffer() {
+        let mut buffer = [0xff; 8192];
+        let mut disp: Ssd1322<_, 256, 64, _> =
+            Ssd1322::new_with_buffer(TestInterface1 {}, &mut buffer);
+
+        Pixel(Point::new(0, 1), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(disp.size(), Size::new(256, 64));
+        assert_eq!(disp.num_changed, 1);
+        let _ = disp.flush();
+
+        assert_eq!(buffer[0], 0x00);
+        assert_eq!(buffer[128], 0xf0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    /// Tests that a heap allocated framebuffer is sized for the panel.
+    fn boxed_buffer() {
+        let mut disp: Ssd1322<_, 480, 128, _> = Ssd1322::new_boxed(TestInterface1 {});
+        assert_eq!(disp.buffer.len(), 30720);
+
+        Pixel(Point::new(479, 127), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(disp.buffer[30719], 0x0f);
+        let _ = disp.flush();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    /// Tests that the PGM image holds the rotated display content.
+    fn to_pgm() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.set_rotation(DisplayRotation::Rotate90).unwrap();
+        disp.set_pixel(1, 0, 0x0F);
+        disp.set_pixel(0, 255, 0x01);
+
+        let mut image = [0; 20000];
+        let mut writer = &mut image[..];
+        disp.to_pgm(&mut writer).unwrap();
+        let len = 20000 - writer.len();
+        let header = b"P5\n64 256\n255\n";
+        assert_eq!(image[..header.len()], header[..]);
+        assert_eq!(len, header.len() + 64 * 256);
+        assert_eq!(image[header.len()..][..2], [0x00, 0xFF]);
+        assert_eq!(image[header.len() + 255 * 64], 0x11);
+    }
+
+    #[test]
+    /// Tests that the driving registers passed to `init_with` are validated and kept.
+    fn init_with() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let init = InitConfig {
+            precharge_voltage: 0x20,
+            ..InitConfig::DEFAULT
+        };
+        assert!(matches!(
+            disp.init_with(&init),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert_eq!(disp.config().init, InitConfig::DEFAULT);
+
+        let init = InitConfig {
+            precharge_voltage: 0x17,
+            vcomh: 0x04,
+            ..InitConfig::DEFAULT
+        };
+        disp.init_with(&init).unwrap();
+        assert_eq!(disp.config().init, init);
+        disp.init().unwrap();
+    }
+
+    #[test]
+    /// Tests that the drive registers are only kept if they are all in range.
+    fn drive_config() {
+        use crate::config::PhaseLength;
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let drive = DriveConfig {
+            precharge_period: 0,
+            ..DriveConfig::DEFAULT
+        };
+        assert!(matches!(
+            disp.set_drive_config(drive),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert_eq!(disp.config().init.drive(), DriveConfig::DEFAULT);
+
+        let drive = DriveConfig {
+            phase_length: PhaseLength::new(9, 7).unwrap(),
+            vcomh: 0x04,
+            ..DriveConfig::DEFAULT
+        };
+        disp.set_drive_config(drive).unwrap();
+        assert_eq!(disp.config().init.drive(), drive);
+        assert_eq!(disp.config().init.vsl, InitConfig::DEFAULT.vsl);
+    }
+
+    #[test]
+    /// Tests that releasing the driver returns the interface and the external buffer.
+    fn release() {
+        let mut buffer = [0xFF; 8192];
+        let mut disp: Ssd1322<_, 256, 64, _> =
+            Ssd1322::new_with_buffer(TestInterface1 {}, &mut buffer);
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+
+        let (_interface, buffer) = disp.release_with_buffer();
+        assert_eq!(buffer[..2], [0xF0, 0x00]);
+        let _interface: TestInterface1 = Ssd1322::<_>::new(TestInterface1 {}).release();
+    }
+
+    #[test]
+    /// Tests that the pixels are packed in the configured nibble order.
+    fn nibble_order() {
+        use crate::config::NibbleOrder;
+
+        let mut disp: Ssd1322<_> = Ssd1322Builder::new()
+            .nibble_order(NibbleOrder::LeftLower)
+            .build(TestInterface1 {});
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        disp.fill_solid(
+            &Rectangle::new(Point::new(3, 0), Size::new(2, 1)),
+            Gray4::new(0x08),
+        )
+        .unwrap();
+        disp.blit_packed(6, 0, 3, 1, &[0x12, 0x30]).unwrap();
+
+        assert_eq!(disp.buffer[..5], [0x0F, 0x80, 0x08, 0x21, 0x03]);
+        assert_eq!(disp.get_pixel(Point::new(0, 0)), Some(Gray4::WHITE));
+        assert_eq!(disp.get_pixel(Point::new(1, 0)), Some(Gray4::BLACK));
+        assert_eq!(disp.get_pixel(Point::new(8, 0)), Some(Gray4::new(0x03)));
+    }
+
+    #[test]
+    /// Tests that the GPIO modes and VDD source are kept for `init`.
+    fn gpio() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.set_gpio(GpioState::OutputHigh, GpioState::InputEnabled)
+            .unwrap();
+        assert_eq!(
+            disp.config().gpio,
+            [GpioState::OutputHigh, GpioState::InputEnabled]
+        );
+
+        disp.lock().unwrap();
+        assert!(disp
+            .set_gpio(GpioState::OutputLow, GpioState::OutputLow)
+            .is_err());
+        assert_eq!(disp.config().gpio[0], GpioState::OutputHigh);
+        disp.unlock().unwrap();
+
+        disp.set_vdd_source(VddSource::External).unwrap();
+        assert_eq!(disp.config().init.vdd, VddSource::External);
+    }
+
+    #[test]
+    /// Tests that commands are refused while the command interface is locked.
+    fn command_lock() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.lock().unwrap();
+        assert!(disp.is_locked());
+        assert!(matches!(
+            disp.set_contrast(0x10),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert!(disp.lock().is_err());
+        assert_eq!(disp.config().contrast, 0xCF);
+
+        disp.unlock().unwrap();
+        assert!(!disp.is_locked());
+        disp.set_contrast(0x10).unwrap();
+
+        disp.lock().unwrap();
+        assert!(matches!(
+            disp.send_commands(&[Command::Nop, Command::Unlock]),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert!(disp.is_locked());
+        disp.send_commands(&[Command::Unlock, Command::Nop])
+            .unwrap();
+        assert!(!disp.is_locked());
+    }
+
+    #[test]
+    /// Tests that waking doesn't unlock the command interface and a failed unlock keeps it
+    /// locked.
+    fn wake_while_locked() {
+        use crate::mock::RecordingInterface;
+
+        struct TestDelay;
+
+        impl DelayMs<u8> for TestDelay {
+            fn delay_ms(&mut self, _ms: u8) {}
+        }
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        disp.lock().unwrap();
+        assert!(matches!(disp.wake(), Err(DisplayError::InvalidFormatError)));
+        assert!(matches!(
+            disp.fade_in(2, 1, &mut TestDelay),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert!(disp.is_locked());
+
+        disp.display.set_fail(true);
+        assert!(disp.unlock().is_err());
+        assert!(disp.send_commands(&[Command::Unlock]).is_err());
+        assert!(disp.is_locked());
+
+        let iface = disp.release();
+        assert!(iface.sent().map(|(_, byte)| byte).eq([0xFD, 0x16]));
+    }
+
+    #[test]
+    /// Tests that changes at opposite corners are flushed as separate windows.
+    fn separate_changed_areas() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(255, 63), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+
+        let op = disp.start_flush().unwrap().unwrap();
+        assert_eq!((op.row, op.col_byte, op.num_col_bytes), (0, 0, 2));
+        assert!(!disp.dirty.is_empty());
+        let op = disp.start_flush().unwrap().unwrap();
+        assert_eq!((op.row, op.col_byte, op.num_col_bytes), (63, 126, 2));
+        assert_eq!(disp.start_flush().unwrap(), None);
+        assert_eq!(disp.num_changed, 0);
+    }
+
+    #[test]
+    /// Tests that the changed area is reported in display coordinates and reset by flushing.
+    fn dirty_area() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        assert_eq!(disp.dirty_area(), None);
+
+        Pixel(Point::new(3, 1), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(6, 2), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(2, 1), Size::new(6, 2)))
+        );
+        disp.flush().unwrap();
+        assert_eq!(disp.dirty_area(), None);
+
+        disp.set_rotation(DisplayRotation::Rotate90).unwrap();
+        Pixel(Point::new(3, 1), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(3, 0), Size::new(1, 2)))
+        );
+        disp.clear_dirty();
+        assert_eq!(disp.dirty_area(), None);
+        assert_eq!(disp.num_changed, 0);
+
+        disp.mark_dirty(Rectangle::new(Point::new(10, 250), Size::new(20, 20)));
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(10, 250), Size::new(20, 6)))
+        );
+    }
+
+    #[test]
+    /// Tests that drawn pixels are combined with the framebuffer as set by the draw mode.
+    fn draw_modes() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let area = Rectangle::new(Point::new(1, 0), Size::new(2, 1));
+        disp.fill_solid(&area, Gray4::new(0x06)).unwrap();
+
+        disp.set_draw_mode(DrawMode::Xor);
+        assert_eq!(disp.draw_mode(), DrawMode::Xor);
+        Pixel(Point::new(1, 0), Gray4::new(0x0F))
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(disp.buffer[..2], [0x09, 0x60]);
+        Pixel(Point::new(1, 0), Gray4::new(0x0F))
+            .draw(&mut disp)
+            .unwrap();
+        disp.fill_solid(&area, Gray4::new(0x06)).unwrap();
+        assert_eq!(disp.buffer[..2], [0x00, 0x00]);
+
+        disp.fill_solid(&area, Gray4::new(0x06)).unwrap();
+        disp.set_draw_mode(DrawMode::Max);
+        disp.set_pixel(1, 0, 0x03);
+        disp.set_pixel(2, 0, 0x09);
+        assert_eq!(disp.buffer[..2], [0x06, 0x90]);
+
+        disp.set_draw_mode(DrawMode::Add);
+        disp.fill_solid(&area, Gray4::new(0x08)).unwrap();
+        assert_eq!(disp.buffer[..2], [0x0E, 0xF0]);
+
+        // Clearing replaces the pixels
+        disp.clear(Gray4::new(0x01)).unwrap();
+        assert!(disp.buffer.iter().all(|&b| b == 0x11));
+    }
+
+    #[test]
+    /// Tests that the count of changed pixels saturates instead of overflowing.
+    fn changed_count_saturates() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.set_draw_mode(DrawMode::Xor);
+        for _ in 0..70_000 {
+            disp.set_pixel(0, 0, 0x0F);
+        }
+        assert_eq!(disp.num_changed, u16::MAX);
+    }
+
+    #[test]
+    /// Tests blending drawn pixels over the framebuffer.
+    fn alpha_blending() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let area = Rectangle::new(Point::new(0, 0), Size::new(4, 1));
+        disp.clear(Gray4::new(0x0C)).unwrap();
+
+        disp.set_draw_mode(DrawMode::Blend(128));
+        disp.fill_solid(&area, Gray4::BLACK).unwrap();
+        assert_eq!(disp.buffer[..3], [0x66, 0x66, 0xCC]);
+
+        disp.set_draw_mode(DrawMode::Blend(0));
+        disp.fill_solid(&area, Gray4::WHITE).unwrap();
+        assert_eq!(disp.buffer[0], 0x66);
+        disp.set_draw_mode(DrawMode::Blend(255));
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(disp.buffer[0], 0xF6);
+    }
+
+    #[test]
+    /// Tests that fill_solid writes whole bytes and the nibbles of partly covered bytes.
+    fn fill_solid() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.fill_solid(
+            &Rectangle::new(Point::new(1, 1), Size::new(4, 2)),
+            Gray4::new(0x0A),
+        )
+        .unwrap();
+
+        assert_eq!(disp.buffer[..3], [0x00; 3]);
+        assert_eq!(disp.buffer[128..131], [0x0A, 0xAA, 0xA0]);
+        assert_eq!(disp.buffer[256..259], [0x0A, 0xAA, 0xA0]);
+        assert_eq!(disp.buffer[384..387], [0x00; 3]);
+        assert_eq!(disp.num_changed, 8);
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(0, 1), Size::new(6, 2)))
+        );
+
+        // Filling with the same color changes nothing
+        disp.flush().unwrap();
+        disp.fill_solid(
+            &Rectangle::new(Point::new(1, 1), Size::new(4, 2)),
+            Gray4::new(0x0A),
+        )
+        .unwrap();
+        assert_eq!(disp.dirty_area(), None);
+
+        disp.set_rotation(DisplayRotation::Rotate90).unwrap();
+        disp.fill_solid(
+            &Rectangle::new(Point::new(0, 0), Size::new(2, 1)),
+            Gray4::WHITE,
+        )
+        .unwrap();
+        assert_eq!(disp.buffer[127], 0x0F);
+        assert_eq!(disp.buffer[255], 0x0F);
+    }
+
+    #[test]
+    /// Tests that the areas changed by each of the drawing operations are flushed as the
+    /// expected windows, wide areas as one window instead of their edge columns.
+    fn drawing_flush_windows() {
+        use crate::mock::RecordingInterface;
+
+        type Disp = Ssd1322<RecordingInterface<8300>>;
+        // The drawing, then the columns, rows, byte and length of every flushed window.
+        type Case = (fn(&mut Disp), &'static [([u8; 2], [u8; 2], u8, usize)]);
+        let cases: [Case; 5] = [
+            (
+                |disp| {
+                    let area = Rectangle::new(Point::new(0, 10), Size::new(200, 4));
+                    disp.fill_solid(&area, Gray4::WHITE).unwrap();
+                },
+                &[([0x1C, 0x4D], [10, 13], 0xFF, 400)],
+            ),
+            (
+                |disp| {
+                    disp.framebuffer_mut()[..100].fill(0xFF);
+                    disp.framebuffer_mut()[128..228].fill(0xFF);
+                    disp.mark_dirty(Rectangle::new(Point::new(0, 0), Size::new(200, 2)));
+                },
+                &[([0x1C, 0x4D], [0, 1], 0xFF, 200)],
+            ),
+            (
+                |disp| disp.clear(Gray4::WHITE).unwrap(),
+                &[([0x1C, 0x5B], [0, 63], 0xFF, 8192)],
+            ),
+            (
+                |disp| disp.blit_packed(0, 30, 200, 2, &[0x5A; 200]).unwrap(),
+                &[([0x1C, 0x4D], [30, 31], 0x5A, 200)],
+            ),
+            // The rows changed by a restore are flushed in full.
+            (
+                |disp| {
+                    let mut saved = [0; 8192];
+                    saved[10 * 128..13 * 128].fill(0x11);
+                    disp.restore(&saved).unwrap();
+                },
+                &[
+                    ([0x1C, 0x5B], [10, 10], 0x11, 128),
+                    ([0x1C, 0x5B], [11, 11], 0x11, 128),
+                    ([0x1C, 0x5B], [12, 12], 0x11, 128),
+                ],
+            ),
+        ];
+
+        for (i, &(draw, expected)) in cases.iter().enumerate() {
+            let mut disp: Disp = Ssd1322::new(RecordingInterface::new());
+            draw(&mut disp);
+            disp.flush().unwrap();
+
+            let iface = disp.release();
+            assert!(!iface.is_overflowed(), "case {}", i);
+            let windows = iface.windows().map(|window| {
+                assert!(
+                    window.data.iter().all(|&b| b == window.data[0]),
+                    "case {}",
+                    i
+                );
+                (
+                    window.columns,
+                    window.rows,
+                    window.data[0],
+                    window.data.len(),
+                )
+            });
+            assert!(windows.eq(expected.iter().copied()), "case {}", i);
+        }
+    }
+
+    #[test]
+    /// Tests that clearing marks the changed part of the screen.
+    fn clear_marks_dirty() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.clear(Gray4::BLACK).unwrap();
+        assert_eq!(disp.dirty_area(), None);
+
+        disp.clear(Gray4::WHITE).unwrap();
+        assert!(disp.buffer.iter().all(|&b| b == 0xFF));
+        assert_eq!(disp.dirty_area(), Some(disp.bounding_box()));
+        disp.flush().unwrap();
+
+        disp.clear_region(
+            Rectangle::new(Point::new(8, 8), Size::new(8, 8)),
+            Gray4::BLACK,
+        );
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(8, 8), Size::new(8, 8)))
+        );
+        assert_eq!(disp.buffer[8 * 128 + 4..8 * 128 + 8], [0x00; 4]);
+    }
+
+    #[test]
+    /// Tests that a mutable reference can be passed to drawing code taking a `DrawTarget`.
+    fn draw_by_reference() {
+        fn draw_frame<D: DrawTarget<Color = Gray4>>(
+            mut target: D,
+        ) -> core::result::Result<(), D::Error> {
+            target.fill_solid(&target.bounding_box(), Gray4::new(0x3))?;
+            Pixel(Point::new(0, 0), Gray4::WHITE).draw(&mut target)
+        }
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let Ok(()) = draw_frame(&mut disp);
+        assert_eq!(disp.buffer[..2], [0xF3, 0x33]);
+        assert_eq!(disp.dirty_area(), Some(disp.bounding_box()));
+    }
+
+    #[test]
+    /// Tests that only the bytes that differ from the shadow buffer are flushed.
+    fn flush_diff() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let mut shadow = [0; 8192];
+        assert!(disp.flush_diff(&mut shadow[..100]).is_err());
+
+        Pixel(Point::new(10, 3), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(100, 3), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        disp.flush_diff(&mut shadow).unwrap();
+        assert_eq!(shadow[3 * 128 + 5], 0xF0);
+        assert_eq!(shadow[3 * 128 + 50], 0xF0);
+        assert_eq!(disp.dirty_area(), None);
+
+        // Redrawing the same content is not sent again
+        Pixel(Point::new(10, 3), Gray4::BLACK)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(10, 3), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert!(disp.dirty_area().is_some());
+        disp.flush_diff(&mut shadow).unwrap();
+        assert_eq!(shadow, disp.buffer);
+    }
+
+    #[test]
+    /// Tests reading back drawn pixels.
+    fn get_pixel() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        Pixel(Point::new(4, 2), Gray4::new(0x07))
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(5, 2), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+
+        assert_eq!(disp.get_pixel(Point::new(4, 2)), Some(Gray4::new(0x07)));
+        assert_eq!(disp.get_pixel(Point::new(5, 2)), Some(Gray4::WHITE));
+        assert_eq!(disp.get_pixel(Point::new(6, 2)), Some(Gray4::BLACK));
+        assert_eq!(disp.get_pixel(Point::new(-1, 2)), None);
+        assert_eq!(disp.get_pixel(Point::new(0, 64)), None);
+
+        disp.set_rotation(DisplayRotation::Rotate90).unwrap();
+        Pixel(Point::new(63, 255), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        assert_eq!(disp.get_pixel(Point::new(63, 255)), Some(Gray4::WHITE));
+    }
+
+    #[test]
+    /// Tests setting pixels without embedded-graphics.
+    fn set_pixel() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.set_pixel(3, 1, 0x0C);
+        disp.set_pixel(256, 0, 0x0F);
+        disp.set_pixel(0, 0, 0xF1);
+
+        assert_eq!(disp.buffer[0], 0x10);
+        assert_eq!(disp.buffer[129], 0x0C);
+        assert_eq!(disp.num_changed, 2);
+        assert_eq!(disp.get_pixel(Point::new(3, 1)), Some(Gray4::new(0x0C)));
+    }
+
+    #[test]
+    /// Tests copying packed images byte by byte and pixel by pixel.
+    fn blit_packed() {
+        let image = [0x12, 0x30, 0x45, 0x60];
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        assert!(disp.blit_packed(0, 0, 3, 3, &image).is_err());
+
+        disp.buffer[1] = 0x0F;
+        disp.blit_packed(0, 0, 3, 2, &image).unwrap();
+        assert_eq!(disp.buffer[..3], [0x12, 0x3F, 0x00]);
+        assert_eq!(disp.buffer[128..131], [0x45, 0x60, 0x00]);
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(0, 0), Size::new(4, 2)))
+        );
+
+        // Odd position and clipped at the right edge
+        disp.blit_packed(255, 10, 3, 2, &image).unwrap();
+        assert_eq!(disp.get_pixel(Point::new(255, 10)), Some(Gray4::new(1)));
+        assert_eq!(disp.get_pixel(Point::new(255, 11)), Some(Gray4::new(4)));
+        disp.blit_packed(254, 20, 3, 2, &image).unwrap();
+        assert_eq!(disp.buffer[20 * 128 + 127], 0x12);
+    }
+
+    #[test]
+    /// Tests that dithering keeps the average gray level of an image.
+    fn dither_from_gray8() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        assert!(disp.dither_from_gray8(&[0; 15], 4, 4).is_err());
+
+        // 94 is about halfway between the gray levels 5 and 6.
+        let image = [94; 16 * 16];
+        disp.dither_from_gray8(&image, 16, 16).unwrap();
+        let levels = Rectangle::new(Point::new(0, 0), Size::new(16, 16))
+            .points()
+            .map(|point| disp.get_pixel(point).unwrap().luma());
+        let (min, max, sum) = levels.fold((15, 0, 0), |(min, max, sum), l| {
+            (min.min(l), max.max(l), sum + u32::from(l))
+        });
+        assert_eq!((min, max), (5, 6));
+        assert_eq!((sum * 17 + 128) / 256, 94);
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(0, 0), Size::new(16, 16)))
+        );
+
+        let image = [255; 300 * 2];
+        disp.dither_from_gray8(&image, 300, 2).unwrap();
+        assert!(disp.buffer[..256].iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    /// Tests that pixels with the key color are skipped.
+    fn blit_with_key() {
+        let image = [0x1F, 0xF2];
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        assert!(disp.blit_with_key(0, 0, 4, 2, &image, 0x0F).is_err());
+
+        disp.buffer[..2].copy_from_slice(&[0x88, 0x88]);
+        disp.blit_with_key(0, 0, 4, 1, &image, 0x0F).unwrap();
+        assert_eq!(disp.buffer[..2], [0x18, 0x82]);
+        assert_eq!(disp.num_changed, 2);
+    }
+
+    #[test]
+    /// Tests row access to the framebuffer.
+    fn row_access() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.row_mut(63).unwrap()[127] = 0x0F;
+        assert!(disp.row_mut(64).is_none());
+
+        assert_eq!(disp.row(63).unwrap().len(), 128);
+        assert_eq!(disp.get_pixel(Point::new(255, 63)), Some(Gray4::WHITE));
+        assert_eq!(disp.dirty_area(), None);
+        assert!(disp.row(64).is_none());
+    }
 
-use crate::command::Command;
-use display_interface::{DataFormat::U8, DisplayError, WriteOnlyDataCommand};
-use embedded_graphics::{
-    draw_target::DrawTarget, geometry::OriginDimensions, pixelcolor::Gray4, prelude::*, Pixel,
-};
-use embedded_hal::blocking::delay::DelayMs;
-use embedded_hal::digital::v2::OutputPin;
+    #[test]
+    /// Tests raw access to the framebuffer.
+    fn framebuffer_access() {
+        let mut disp: Ssd1322<_, 256, 32, [u8; 4096]> = Ssd1322::new_256x32(TestInterface1 {});
+        assert_eq!(
+            Ssd1322::<TestInterface1, 256, 32, [u8; 4096]>::BUFFER_SIZE,
+            4096
+        );
+        assert_eq!(
+            Ssd1322::<TestInterface1, 256, 32, [u8; 4096]>::ROW_BYTES,
+            128
+        );
 
-const DISPLAY_WIDTH: usize = 256;
-const DISPLAY_HEIGHT: usize = 64;
-const BUFFER_SIZE: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT / 2;
+        disp.framebuffer_mut()[129] = (0x0A << LEFT_PIXEL_SHIFT) & LEFT_PIXEL_MASK;
+        assert_eq!(disp.framebuffer().len(), 4096);
+        assert_eq!(disp.get_pixel(Point::new(2, 1)), Some(Gray4::new(0x0A)));
+        assert_eq!(disp.get_pixel(Point::new(3, 1)), Some(Gray4::BLACK));
+    }
 
-/// Represents the SSD1322 Display.
-///
-/// Use this struct to initialize the driver.
-pub struct Ssd1322<DI> {
-    display: DI,
-    buffer: [u8; BUFFER_SIZE],
-    bounding_box: Option<([u8; 2], [u8; 2])>,
-    num_changed: u16,
-}
+    #[test]
+    /// Tests saving and restoring the framebuffer.
+    fn snapshot_restore() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let mut saved = [0; 8192];
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        disp.snapshot(&mut saved).unwrap();
+        assert!(disp.snapshot(&mut saved[1..]).is_err());
+        disp.flush().unwrap();
 
-/// Provides an optimized way to capture changes to the framebuffer.
-pub trait BoundingBox {
-    /// Updates the bounding_box field to the modified area. The bounding_box unit is in bytes.
-    fn update_box(&mut self, x: u8, y: u8);
-}
+        disp.fill_solid(
+            &Rectangle::new(Point::new(10, 10), Size::new(20, 4)),
+            Gray4::WHITE,
+        )
+        .unwrap();
+        disp.flush().unwrap();
 
-impl<DI: WriteOnlyDataCommand> Ssd1322<DI> {
-    /// Creates the SSD1322 Display.
-    ///
-    /// The device needs to be reset before use.
-    pub fn new(display: DI) -> Self {
-        Self {
-            display,
-            buffer: [0; BUFFER_SIZE],
-            bounding_box: None,
-            num_changed: 0,
-        }
+        disp.restore(&saved).unwrap();
+        assert_eq!(disp.buffer, saved);
+        assert_eq!(
+            disp.dirty_area(),
+            Some(Rectangle::new(Point::new(10, 10), Size::new(20, 4)))
+        );
+        assert!(disp.restore(&saved[1..]).is_err());
     }
 
-    /// Resets the display.
-    pub fn reset<RST, DELAY>(
-        &mut self,
-        rst: &mut RST,
-        delay: &mut DELAY,
-    ) -> Result<(), DisplayError>
-    where
-        RST: OutputPin,
-        DELAY: DelayMs<u8>,
-    {
-        rst.set_low().map_err(|_| DisplayError::BusWriteError)?;
-        delay.delay_ms(10);
+    #[test]
+    /// Tests the counters of the flushed data.
+    fn flush_stats() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(255, 63), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        disp.flush().unwrap();
 
-        rst.set_high().map_err(|_| DisplayError::BusWriteError)?;
-        delay.delay_ms(200);
+        let stats = disp.stats();
+        assert_eq!((stats.windows, stats.bytes), (2, 4));
+        assert_eq!(stats.last_dirty_area, Some(disp.bounding_box()));
 
-        Ok(())
+        disp.flush_all().unwrap();
+        assert_eq!(disp.stats().windows, 3);
+        assert_eq!(disp.stats().bytes, 4 + 8192);
+
+        disp.reset_stats();
+        assert_eq!(disp.stats(), FlushStats::default());
     }
 
-    /// Initializes the display.
-    pub fn init(&mut self) -> Result<(), DisplayError> {
-        self.send_command(Command::Unlock)?;
-        self.send_command(Command::DisplayOff)?;
-        self.send_command(Command::SetColumnAddress(0x1C, 0x5B))?;
-        self.send_command(Command::SetRowAddress(0x00, 0x3F))?;
-        self.send_command(Command::SetDisplayClock(0x91))?;
-        self.send_command(Command::SetMuxRatio(0x3F))?;
-        self.send_command(Command::SetDisplayOffset(0x00))?;
-        self.send_command(Command::SetStartLine(0x00))?;
-        self.send_command(Command::SetRemapFormat(0x14, 0x11))?;
-        self.send_command(Command::SetGPIO(0x00))?;
-        self.send_command(Command::SetFunctionSelection(0x01))?;
-        self.send_command(Command::SetDisplayEnhancementA(0xA0, 0xFD))?;
-        self.send_command(Command::SetContrastCurrent(0xCF))?;
-        self.send_command(Command::SetMasterCurrent(0x0F))?;
-        self.send_command(Command::SetLinearGrayScaleTable)?;
-        self.send_command(Command::SetPhaseLength(0xE2))?;
-        self.send_command(Command::SetDisplayEnhancementB(0xA2, 0x20))?;
-        self.send_command(Command::SetPrechargeVoltage(0x1F))?;
-        self.send_command(Command::SetPrechargePeriod(0x08))?;
-        self.send_command(Command::SetVCOMH(0x07))?;
-        self.send_command(Command::NormalDisplayMode)?;
-        //self.send_command(Command::AllPixelsOn)?;
-        self.send_command(Command::DisplayOn)?;
+    #[test]
+    /// Tests that the flushes split their data transfers at the maximum transfer size.
+    fn max_transfer() {
+        use crate::mock::RecordingInterface;
 
-        Ok(())
+        let config = DisplayConfig {
+            max_transfer: 3000,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> =
+            Ssd1322::new_with_config(RecordingInterface::<9000>::new(), config);
+        disp.flush_all().unwrap();
+        let iface = disp.release();
+        // The window commands, their arguments and 3 transfers of up to 3000 bytes
+        assert_eq!(iface.transfers(), 5 + 3);
+        assert_eq!(iface.sent().filter(|&(data, _)| data).count(), 4 + 8192);
+
+        let config = DisplayConfig {
+            max_transfer: 3,
+            ..config
+        };
+        let mut disp: Ssd1322<_> =
+            Ssd1322::new_with_config(RecordingInterface::<64>::new(), config);
+        disp.set_pixel(0, 0, 0x0F);
+        disp.set_pixel(9, 0, 0x0F);
+        disp.flush().unwrap();
+        let iface = disp.release();
+        // One window of 6 bytes, sent in 2 transfers
+        assert_eq!(iface.windows().next().unwrap().data.len(), 6);
+        assert_eq!(iface.transfers(), 5 + 2);
     }
 
-    /// Allows to send custom commands to the display.
-    pub fn send_command(&mut self, command: Command) -> Result<(), DisplayError> {
-        command.send(&mut self.display)
+    #[test]
+    /// Tests that a failed flush is retried and then recovered by reinitializing the display.
+    fn retry() {
+        use crate::config::RetryPolicy;
+        use crate::mock::RecordingInterface;
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<9000>::new());
+        disp.set_pixel(0, 0, 0x0F);
+        disp.display.fail_next(1);
+        assert!(matches!(disp.flush(), Err(DisplayError::BusWriteError)));
+        assert!(!disp.dirty.is_empty());
+
+        let config = DisplayConfig {
+            retry: RetryPolicy {
+                retries: 1,
+                reinitialize: false,
+            },
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> =
+            Ssd1322::new_with_config(RecordingInterface::<9000>::new(), config);
+        disp.set_pixel(0, 0, 0x0F);
+        disp.display.fail_next(1);
+        disp.flush().unwrap();
+        assert!(disp.dirty.is_empty());
+        disp.display.fail_next(2);
+        assert!(matches!(disp.flush_all(), Err(DisplayError::BusWriteError)));
+
+        disp.config.retry.reinitialize = true;
+        disp.set_pixel(1, 0, 0x0F);
+        disp.display.clear();
+        disp.display.fail_next(2);
+        disp.flush().unwrap();
+        assert!(disp.dirty.is_empty());
+        let iface = disp.release();
+        assert!(iface.has_command(&[0xFD, 0x12]));
+        let window = iface.windows().next().unwrap();
+        assert_eq!((window.data.len(), window.data[0]), (8192, 0xFF));
     }
 
-    /// Flushes the entire display, and makes the output visible on the screen.
-    pub fn flush_all(&mut self) -> Result<(), DisplayError> {
-        self.send_command(Command::SetColumnAddress(0x1C, 0x5B))?;
-        self.send_command(Command::SetRowAddress(0x00, 0x3F))?;
-        self.send_command(Command::WriteRAM)?;
-        self.display.send_data(U8(&self.buffer))
+    #[test]
+    /// Tests that a locked driver isn't retried or reinitialized and stays locked.
+    fn retry_while_locked() {
+        use crate::config::RetryPolicy;
+        use crate::mock::RecordingInterface;
+
+        let config = DisplayConfig {
+            retry: RetryPolicy {
+                retries: 2,
+                reinitialize: true,
+            },
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> =
+            Ssd1322::new_with_config(RecordingInterface::<64>::new(), config);
+        disp.lock().unwrap();
+        disp.set_pixel(0, 0, 0x0F);
+        assert!(matches!(
+            disp.flush(),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert!(matches!(
+            disp.flush_all(),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert!(matches!(
+            disp.reinitialize(),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert!(disp.is_locked());
+
+        let iface = disp.release();
+        assert!(iface.sent().map(|(_, byte)| byte).eq([0xFD, 0x16]));
     }
 
-    /// Flushes only the changed portion of the display.
-    pub fn flush(&mut self) -> Result<(), DisplayError> {
-        if let Some((mut col_addr, row_addr)) = self.bounding_box {
-            col_addr[0] -= col_addr[0] % 2;
-            col_addr[1] -= col_addr[1] % 2;
-            let num_col_bytes: usize = (col_addr[1] - col_addr[0] + 2).into();
-
-            // Convert bytes to column address
-            self.send_command(Command::SetColumnAddress(
-                col_addr[0] / 2 + 0x1C,
-                col_addr[1] / 2 + 0x1C,
-            ))?;
-            self.send_command(Command::SetRowAddress(row_addr[0], row_addr[1]))?;
-            self.send_command(Command::WriteRAM)?;
-
-            for i in row_addr[0]..=row_addr[1] {
-                let start_col_byte: usize = col_addr[0] as usize + (i as usize * DISPLAY_WIDTH / 2);
-                let end_col_byte: usize = start_col_byte + num_col_bytes;
-                self.display
-                    .send_data(U8(&self.buffer[start_col_byte..end_col_byte]))?;
-            }
+    #[test]
+    /// Tests that 16 bit words send the framebuffer bytes in the same order.
+    fn data_width() {
+        use crate::mock::RecordingInterface;
 
-            // Reset the bounding_box
-            self.bounding_box = None;
-            self.num_changed = 0;
-        }
+        let config = DisplayConfig {
+            data_width: DataWidth::U16,
+            max_transfer: 5,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> =
+            Ssd1322::new_with_config(RecordingInterface::<64>::new(), config);
+        disp.set_pixel(0, 0, 0x0F);
+        disp.set_pixel(9, 0, 0x0A);
+        disp.flush().unwrap();
+        let iface = disp.release();
+        let data: [u8; 6] = [0xF0, 0x00, 0x00, 0x00, 0x0A, 0x00];
+        assert_eq!(iface.windows().next().unwrap().data, data);
+        // 6 bytes in transfers of 2 words and 1 word
+        assert_eq!(iface.transfers(), 5 + 2);
 
-        Ok(())
+        let config = DisplayConfig {
+            max_transfer: usize::MAX,
+            ..config
+        };
+        let mut disp: Ssd1322<_> =
+            Ssd1322::new_with_config(RecordingInterface::<9000>::new(), config);
+        disp.flush_all().unwrap();
+        assert_eq!(disp.release().transfers(), 5 + 8192 / 256);
     }
-}
 
-impl<DI> BoundingBox for Ssd1322<DI> {
-    fn update_box(&mut self, x: u8, y: u8) {
-        match self.bounding_box {
-            Some((col_addr, row_addr)) => {
-                let mut new_col_addr: [u8; 2] = col_addr;
-                let mut new_row_addr: [u8; 2] = row_addr;
-
-                // Column address update
-                if x / 2 < col_addr[0] {
-                    new_col_addr = [x / 2, col_addr[1]];
-                } else if x / 2 > col_addr[1] {
-                    new_col_addr = [col_addr[0], x / 2];
-                }
+    #[test]
+    /// Tests that the rows of a full width window are sent in one transfer.
+    fn full_width_flush() {
+        use crate::mock::RecordingInterface;
 
-                // Row address update
-                if y < row_addr[0] {
-                    new_row_addr = [y, row_addr[1]];
-                } else if y > row_addr[1] {
-                    new_row_addr = [row_addr[0], y];
-                }
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<512>::new());
+        disp.flush_region(Rectangle::new(Point::new(0, 2), Size::new(256, 3)))
+            .unwrap();
+        let iface = disp.release();
+        assert_eq!(iface.windows().next().unwrap().rows, [2, 4]);
+        assert_eq!(iface.windows().next().unwrap().data.len(), 3 * 128);
+        assert_eq!(iface.transfers(), 5 + 1);
+    }
+
+    #[test]
+    /// Tests that the self test shows the patterns and restores the framebuffer.
+    fn self_test() {
+        use crate::mock::RecordingInterface;
 
-                self.bounding_box = Some((new_col_addr, new_row_addr));
+        /// Records the total time and the number of delays.
+        struct Delay(u32, u32);
+
+        impl DelayMs<u8> for Delay {
+            fn delay_ms(&mut self, ms: u8) {
+                (self.0, self.1) = (self.0 + u32::from(ms), self.1 + 1);
             }
-            None => self.bounding_box = Some(([x / 2, x / 2], [y, y])),
         }
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<20000>::new());
+        disp.set_pixel(3, 0, 0x05);
+        let mut delay = Delay(0, 0);
+        disp.self_test(10, &mut delay).unwrap();
+        assert_eq!((delay.0, delay.1), (100, 10));
+
+        // Pauses longer than a `DelayMs<u8>` are split into several delays.
+        let mut delay = Delay(0, 0);
+        delay_long(&mut delay, 600);
+        assert_eq!((delay.0, delay.1), (600, 3));
+
+        let iface = disp.release();
+        assert!(iface.has_command(&[0xA5]));
+        assert!(iface.has_command(&[0xA4]));
+        assert!(iface.has_command(&[0xA7]));
+        assert!(iface.has_command(&[0xC1, 0xFF]));
+        let mut windows = iface.windows();
+        let gradient = windows.next().unwrap().data;
+        assert_eq!(gradient[..2], [0x00, 0x00]);
+        assert_eq!(gradient[8], 0x11);
+        assert_eq!(gradient[127], 0xFF);
+        assert_eq!(windows.next().unwrap().data[1], 0x05);
+        assert!(windows.next().is_none());
+        assert!(iface.has_command(&[0xC1, 0xCF]));
     }
-}
 
-impl<DI> DrawTarget for Ssd1322<DI> {
-    type Color = Gray4;
-    type Error = core::convert::Infallible;
+    #[test]
+    /// Tests that the fades ramp the contrast current between 0 and the configured contrast.
+    fn fade() {
+        use crate::mock::RecordingInterface;
 
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
-    where
-        I: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        for Pixel(coord, color) in pixels.into_iter() {
-            // Check if the pixel coordinates are out of bounds (negative or greater than
-            // (255,63)). `DrawTarget` implementation are required to discard any out of bounds
-            // pixels without returning an error or causing a panic.
-            if let (x @ 0..=255, y @ 0..=63) = (coord.x as usize, coord.y as usize) {
-                // Calculate the index in the framebuffer.
-                let index = (x / 2) + (y * (DISPLAY_WIDTH / 2));
-                let new_val: u8 = if x % 2 == 0 {
-                    update_upper_nibble(self.buffer[index], color.luma())
-                } else {
-                    update_lower_nibble(self.buffer[index], color.luma())
-                };
+        struct Delay(u32);
 
-                // Update only if changed
-                if new_val != self.buffer[index] {
-                    self.num_changed += 1;
-                    self.update_box(x as u8, y as u8);
-                    self.buffer[index] = new_val;
-                }
+        impl DelayMs<u8> for Delay {
+            fn delay_ms(&mut self, ms: u8) {
+                self.0 += u32::from(ms);
             }
         }
 
-        Ok(())
-    }
-
-    fn clear(&mut self, fill: Self::Color) -> Result<(), Self::Error> {
-        let luma = fill.luma();
-        let byte = (luma << 4) | luma;
-        self.buffer.fill(byte);
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        disp.set_contrast(0x80).unwrap();
+        let mut delay = Delay(0);
+        disp.fade_out(4, 5, &mut delay).unwrap();
+        assert_eq!(delay.0, 20);
+        assert_eq!(disp.config().contrast, 0x80);
 
-        Ok(())
-    }
-}
+        let mut iface = disp.release();
+        let bytes: [u8; 11] = [
+            0xC1, 0x80, 0xC1, 0x60, 0xC1, 0x40, 0xC1, 0x20, 0xC1, 0x00, 0xAE,
+        ];
+        assert!(iface.sent().map(|(_, byte)| byte).eq(bytes));
 
-impl<DI> OriginDimensions for Ssd1322<DI> {
-    fn size(&self) -> Size {
-        Size::new(
-            DISPLAY_WIDTH.try_into().unwrap(),
-            DISPLAY_HEIGHT.try_into().unwrap(),
-        )
+        iface.clear();
+        let mut disp: Ssd1322<_> = Ssd1322::new(iface);
+        disp.set_contrast(0x80).unwrap();
+        disp.fade_in(2, 5, &mut delay).unwrap();
+        let iface = disp.release();
+        assert!(iface.has_command(&[0xC1, 0x00]));
+        assert!(iface.has_command(&[0xC1, 0x40]));
+        assert_eq!(iface.commands().filter(|&cmd| cmd == 0xAF).count(), 1);
+        let len = iface.sent().count();
+        assert!(iface.sent().skip(len - 2).eq([(false, 0xC1), (true, 0x80)]));
     }
-}
 
-#[inline]
-fn update_upper_nibble(input: u8, color: u8) -> u8 {
-    ((color << 4) & 0xF0) | (input & 0x0F)
-}
+    #[test]
+    /// Tests that the reset pulse follows the configured polarity and timing.
+    fn reset() {
+        use crate::config::ResetConfig;
+        use core::convert::Infallible;
 
-#[inline]
-fn update_lower_nibble(input: u8, color: u8) -> u8 {
-    color & 0x0F | (input & 0xF0)
-}
+        /// Records the levels the pin is set to.
+        struct Pin(bool, bool);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use display_interface::DataFormat;
-    use embedded_graphics::{
-        mono_font::{ascii::FONT_6X10, MonoTextStyleBuilder},
-        pixelcolor::Gray4,
-        text::{Baseline, Text},
-    };
-    type Result = core::result::Result<(), DisplayError>;
+        impl OutputPin for Pin {
+            type Error = Infallible;
 
-    pub struct TestInterface1 {}
+            fn set_low(&mut self) -> core::result::Result<(), Self::Error> {
+                *self = Pin(self.1, false);
+                Ok(())
+            }
 
-    impl WriteOnlyDataCommand for TestInterface1 {
-        fn send_commands(&mut self, _cmds: DataFormat<'_>) -> Result {
-            Ok(())
+            fn set_high(&mut self) -> core::result::Result<(), Self::Error> {
+                *self = Pin(self.1, true);
+                Ok(())
+            }
         }
 
-        fn send_data(&mut self, buf: DataFormat<'_>) -> Result {
-            match buf {
-                U8(_slice) => Ok(()),
-                _ => Err(DisplayError::DataFormatNotImplemented),
+        /// Records the last two delays.
+        struct Delay(u8, u8);
+
+        impl DelayMs<u8> for Delay {
+            fn delay_ms(&mut self, ms: u8) {
+                *self = Delay(self.1, ms);
             }
         }
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let (mut pin, mut delay) = (Pin(true, true), Delay(0, 0));
+        disp.reset(&mut pin, &mut delay).unwrap();
+        assert_eq!((pin.0, pin.1, delay.0, delay.1), (false, true, 10, 200));
+
+        let config = Ssd1322Builder::new()
+            .reset(ResetConfig {
+                pulse_ms: 1,
+                wait_ms: 50,
+                active_high: true,
+            })
+            .config(256, 64);
+        let mut disp: Ssd1322<_> = Ssd1322::new_with_config(TestInterface1 {}, config);
+        disp.reset(&mut pin, &mut delay).unwrap();
+        assert_eq!((pin.0, pin.1, delay.0, delay.1), (true, false, 1, 50));
+    }
+
+    #[test]
+    /// Tests that the time spent in the flush methods is measured with the clock.
+    fn flush_timing() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static TICKS: AtomicU32 = AtomicU32::new(u32::MAX - 5);
+        fn clock() -> u32 {
+            TICKS.fetch_add(10, Ordering::Relaxed)
+        }
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.flush().unwrap();
+        assert_eq!(disp.stats().flushes, 1);
+        assert_eq!(disp.stats().flush_ticks, 0);
+
+        disp.set_clock(Some(clock));
+        disp.flush().unwrap();
+        disp.flush_all().unwrap();
+        disp.flush_all().unwrap();
+        let stats = disp.stats();
+        assert_eq!((stats.flushes, stats.flush_ticks), (2, 10));
+        assert_eq!((stats.full_flushes, stats.full_flush_ticks), (2, 20));
     }
 
     #[test]
-    /// Tests the character '|'. The framebuffer looks like starting from beginning of row 0
-    /// where each '.' represents a pixel.
-    /// ......
-    /// ..x...
-    /// ..x...
-    /// ..x...
-    /// ..x...
-    /// ..x...
-    /// ..x...
-    /// ..x...
-    ///
-    fn single_char_one_col() {
-        let s = TestInterface1 {};
-        let mut disp = Ssd1322::new(s);
+    /// Tests that row span tracking skips the rows that did not change.
+    fn row_span_tracking() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        Pixel(Point::new(10, 1), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        disp.set_dirty_tracking(DirtyTracking::RowSpans);
+        assert_eq!(disp.dirty_tracking(), DirtyTracking::RowSpans);
+        Pixel(Point::new(200, 40), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        Pixel(Point::new(201, 41), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
 
-        let text_style = MonoTextStyleBuilder::new()
-            .font(&FONT_6X10)
-            .text_color(Gray4::new(0b0000_1111))
-            .build();
+        let op = disp.start_flush().unwrap().unwrap();
+        assert_eq!((op.row, op.last_row, op.col_byte), (1, 1, 4));
+        let op = disp.start_flush().unwrap().unwrap();
+        assert_eq!((op.row, op.last_row, op.col_byte), (40, 41, 100));
+        assert_eq!(disp.start_flush().unwrap(), None);
+    }
 
-        Text::with_baseline("|", Point::new(0, 0), text_style, Baseline::Top)
+    #[test]
+    /// Tests that drawing in untracked mode changes the framebuffer without tracking the pixels,
+    /// and that `flush` then sends the entire display once.
+    fn untracked_mode() {
+        use crate::mock::RecordingInterface;
+
+        let mut disp: Ssd1322<_, 256, 64, [u8; 8192], UntrackedMode> =
+            Ssd1322::new_untracked(RecordingInterface::<16384>::new());
+        assert_eq!(disp.dirty_area(), None);
+        Pixel(Point::new(10, 1), Gray4::WHITE)
             .draw(&mut disp)
             .unwrap();
 
-        assert_eq!(disp.bounding_box.unwrap().0[0], 1);
-        assert_eq!(disp.bounding_box.unwrap().0[1], 1);
-        assert_eq!(disp.bounding_box.unwrap().1[0], 1);
-        assert_eq!(disp.bounding_box.unwrap().1[1], 7);
-        assert_eq!(disp.num_changed, 7);
+        assert_eq!(disp.buffer[133], 0xF0);
+        assert_eq!(disp.num_changed, 0);
+        assert_eq!(disp.dirty.iter().next(), None);
+        assert_eq!(disp.dirty_area(), Some(disp.bounding_box()));
 
-        for i in 1..8 {
-            let start = i * 128;
-            assert_eq!(&disp.buffer[start..start + 3], [0, 0xf0, 0]);
-        }
+        disp.flush().unwrap();
+        assert_eq!(disp.display.windows().count(), 1);
+        let window = disp.display.windows().next().unwrap();
+        assert_eq!(window.columns, [0x1C, 0x5B]);
+        assert_eq!(window.rows, [0, 63]);
+        assert_eq!(window.data.len(), 8192);
+        assert!(!disp.display.is_overflowed());
+        assert_eq!(disp.dirty_area(), None);
 
-        let _ = disp.flush();
+        disp.display.clear();
+        disp.flush().unwrap();
+        assert_eq!(disp.display.sent().next(), None);
+        assert_eq!(disp.start_flush().unwrap(), None);
+
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
+        let op = disp.start_flush().unwrap().unwrap();
+        assert_eq!((op.row, op.last_row), (0, 63));
+        assert_eq!(disp.start_flush().unwrap(), None);
     }
 
     #[test]
-    /// Tests the character 'A'. The framebuffer looks like starting from beginning of row 0
-    /// where each '.' represents a pixel.
-    /// ......
-    /// ..x...
-    /// .x.x..
-    /// x...x.
-    /// x...x.
-    /// xxxxx.
-    /// x...x.
-    /// x...x.
-    ///
-    fn single_char_multi_col() {
-        let s = TestInterface1 {};
-        let mut disp = Ssd1322::new(s);
+    /// Tests that flushing a region sends the clipped and aligned window.
+    fn flush_region() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        Pixel(Point::new(0, 0), Gray4::WHITE)
+            .draw(&mut disp)
+            .unwrap();
 
-        let text_style = MonoTextStyleBuilder::new()
-            .font(&FONT_6X10)
-            .text_color(Gray4::new(0b0000_1111))
-            .build();
+        let op = disp
+            .start_flush_region(Rectangle::new(Point::new(5, 2), Size::new(4, 3)))
+            .unwrap()
+            .unwrap();
+        assert_eq!((op.row, op.last_row), (2, 4));
+        assert_eq!((op.col_byte, op.num_col_bytes), (2, 4));
+        assert!(!disp.dirty.is_empty());
 
-        Text::with_baseline("A", Point::new(0, 0), text_style, Baseline::Top)
-            .draw(&mut disp)
+        let op = disp
+            .start_flush_region(Rectangle::new(Point::new(250, 60), Size::new(20, 20)))
+            .unwrap()
             .unwrap();
+        assert_eq!((op.row, op.last_row), (60, 63));
+        assert_eq!((op.col_byte, op.num_col_bytes), (124, 4));
 
-        assert_eq!(disp.bounding_box.unwrap().0[0], 0);
-        assert_eq!(disp.bounding_box.unwrap().0[1], 2);
-        assert_eq!(disp.bounding_box.unwrap().1[0], 1);
-        assert_eq!(disp.bounding_box.unwrap().1[1], 7);
-        assert_eq!(disp.num_changed, 16);
+        assert!(disp
+            .start_flush_region(Rectangle::new(Point::new(256, 0), Size::new(4, 4)))
+            .unwrap()
+            .is_none());
+        disp.flush_region(Rectangle::new(Point::zero(), Size::new(8, 8)))
+            .unwrap();
+    }
 
-        let _ = disp.flush();
+    #[test]
+    /// Tests panning a framebuffer that is twice as tall as the panel.
+    fn virtual_framebuffer() {
+        let config = Ssd1322Builder::new().display_rows(64).config(256, 128);
+        assert_eq!(config.remap, [0x14, 0x11]);
+
+        let mut disp: Ssd1322<_, 256, 128, [u8; 16384]> =
+            Ssd1322::new_with_config(TestInterface1 {}, config);
+        disp.init().unwrap();
+        disp.pan_to(64).unwrap();
+        assert_eq!(disp.start_line(), 64);
+        assert!(disp.pan_to(65).is_err());
+
+        let config = DisplayConfig {
+            display_rows: 65,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> = Ssd1322::new_with_config(TestInterface1 {}, config);
+        assert!(disp.init().is_err());
     }
 
     #[test]
-    /// Tests the character 'A' at an offset.
-    /// .......
-    /// .......
-    /// .......
-    /// .......
-    /// .......
-    /// .......
-    /// ...x...
-    /// ..x.x..
-    /// .x...x.
-    /// .x...x.
-    /// .xxxxx.
-    /// .x...x.
-    /// .x...x.
-    ///
-    fn single_char_offset() {
-        let s = TestInterface1 {};
-        let mut disp = Ssd1322::new(s);
+    /// Tests the validation of the mux ratio.
+    fn mux_ratio() {
+        use crate::config::RemapConfig;
 
-        let text_style = MonoTextStyleBuilder::new()
-            .font(&FONT_6X10)
-            .text_color(Gray4::new(0b0000_1111))
-            .build();
+        let mut disp: Ssd1322<_, 256, 128, [u8; 16384]> = Ssd1322::new(TestInterface1 {});
+        disp.set_mux_ratio(64).unwrap();
+        assert_eq!(disp.config().display_rows, 64);
+        disp.pan_to(64).unwrap();
+        disp.set_mux_ratio(128).unwrap();
+        assert!(disp.pan_to(1).is_err());
+        assert!(disp.set_mux_ratio(15).is_err());
 
-        Text::with_baseline("A", Point::new(1, 5), text_style, Baseline::Top)
+        // The dual COM line mode only drives up to 64 rows.
+        let mut disp: Ssd1322<_, 256, 128, [u8; 16384]> = Ssd1322Builder::new()
+            .remap_config(RemapConfig::new().dual_com(true))
+            .build(TestInterface1 {});
+        assert!(disp.set_mux_ratio(65).is_err());
+        assert!(disp.set_mux_ratio(129).is_err());
+        assert_eq!(disp.config().display_rows, 128);
+    }
+
+    #[test]
+    /// Tests that page flipping alternates between the two halves of the GDDRAM.
+    fn page_flip() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        Pixel(Point::new(0, 0), Gray4::WHITE)
             .draw(&mut disp)
             .unwrap();
+        disp.flush_page_flip().unwrap();
+        assert_eq!(disp.start_line(), 64);
+        assert!(disp.dirty.is_empty());
+        disp.flush_page_flip().unwrap();
+        assert_eq!(disp.start_line(), 0);
+    }
 
-        assert_eq!(disp.bounding_box.unwrap().0[0], 0);
-        assert_eq!(disp.bounding_box.unwrap().0[1], 2);
-        assert_eq!(disp.bounding_box.unwrap().1[0], 6);
-        assert_eq!(disp.bounding_box.unwrap().1[1], 12);
-        assert_eq!(disp.num_changed, 16);
+    #[test]
+    /// Tests that hardware scrolling wraps around the GDDRAM rows.
+    fn hardware_scroll() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        disp.scroll_to_line(120).unwrap();
+        disp.scroll_by(10).unwrap();
+        assert_eq!(disp.start_line(), 2);
+        disp.scroll_by(-3).unwrap();
+        assert_eq!(disp.start_line(), 127);
+        assert!(disp.scroll_to_line(128).is_err());
+        assert_eq!(disp.start_line(), 127);
+    }
 
-        let _ = disp.flush();
+    #[test]
+    /// Tests the validation of the partial display rows.
+    fn partial_display() {
+        let mut disp: Ssd1322<_, 256, 32, _> = Ssd1322::new_256x32(TestInterface1 {});
+        disp.enable_partial_display(8, 31).unwrap();
+        assert!(disp.enable_partial_display(8, 32).is_err());
+        assert!(disp.enable_partial_display(9, 8).is_err());
+        disp.disable_partial_display().unwrap();
     }
 
     #[test]
-    /// Tests the character 'A' clipped at the right.
-    /// .......
-    /// ....... x
-    /// .......x x
-    /// ......x   x
-    /// ......x   x
-    /// ......xxxxx
-    /// ......x   x
-    /// ......x   x
-    ///
-    fn single_char_clipped() {
+    /// Tests the validation of custom gray scale tables.
+    fn gray_scale_table() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        let mut table = [0; 15];
+        for (i, level) in table.iter_mut().enumerate() {
+            *level = i as u8 * 12 + 12;
+        }
+        disp.set_gray_scale_table(&table).unwrap();
+
+        table[14] = 181;
+        assert!(disp.set_gray_scale_table(&table).is_err());
+        table[14] = table[13];
+        assert!(disp.set_gray_scale_table(&table).is_err());
+        disp.set_default_grayscale().unwrap();
+
+        for gamma in [Gamma::Linear, Gamma::Gamma18, Gamma::Gamma22] {
+            disp.set_gamma(gamma).unwrap();
+        }
+    }
+
+    #[test]
+    /// Tests that the brightness presets update the configuration used by `init`.
+    fn brightness() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(TestInterface1 {});
+        assert_eq!(disp.brightness(), Brightness::BRIGHT);
+
+        disp.set_brightness(Brightness::DIM).unwrap();
+        assert_eq!(disp.config().contrast, 0x4F);
+        assert_eq!(disp.config().master_current, 0x08);
+
+        assert!(disp.set_master_current(0x10).is_err());
+        assert_eq!(disp.config().master_current, 0x08);
+        disp.set_contrast(0x01).unwrap();
+        assert_eq!(disp.brightness(), Brightness::custom(0x01, 0x08));
+    }
+
+    #[test]
+    /// Tests that a 128x32 panel clips at its own edges and indexes rows by its own width.
+    fn small_panel_clipped() {
         let s = TestInterface1 {};
-        let mut disp = Ssd1322::new(s);
+        let mut disp: Ssd1322<_, 128, 32, [u8; 2048]> = Ssd1322::new(s);
 
         let text_style = MonoTextStyleBuilder::new()
             .font(&FONT_6X10)
             .text_color(Gray4::new(0b0000_1111))
             .build();
 
-        Text::with_baseline("A", Point::new(255, 0), text_style, Baseline::Top)
+        Text::with_baseline("A", Point::new(125, 25), text_style, Baseline::Top)
             .draw(&mut disp)
             .unwrap();
 
-        assert_eq!(disp.bounding_box.unwrap().0[0], 127);
-        assert_eq!(disp.bounding_box.unwrap().0[1], 127);
-        assert_eq!(disp.bounding_box.unwrap().1[0], 3);
-        assert_eq!(disp.bounding_box.unwrap().1[1], 7);
-        assert_eq!(disp.num_changed, 5);
+        assert_eq!(disp.size(), Size::new(128, 32));
+        assert_eq!(disp.dirty.union().unwrap().0[0], 62);
+        assert_eq!(disp.dirty.union().unwrap().0[1], 63);
+        assert_eq!(disp.dirty.union().unwrap().1[0], 26);
+        assert_eq!(disp.dirty.union().unwrap().1[1], 31);
+        assert_eq!(disp.buffer[26 * 64 + 63], 0x0f);
 
         let _ = disp.flush();
     }
@@ -0,0 +1,880 @@
+//! display interface module
+use core::convert::Infallible;
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Bytes collected from an iterator before they are written to the bus.
+const CHUNK_SIZE: usize = 32;
+
+/// An interface that can also read data from the controller, e.g. a parallel interface over a
+/// bidirectional bus. The SSD1322 doesn't send data over SPI.
+pub trait ReadData {
+    /// Reads `buf.len()` bytes of data, with the D/C line high.
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError>;
+
+    /// Reads the status register, with the D/C line low.
+    fn read_status(&mut self) -> Result<u8, DisplayError>;
+}
+
+/// Stands in for the chip select of a `SpiInterface` that leaves it to the caller.
+#[derive(Debug, Clone, Copy)]
+pub struct NoCs;
+
+impl OutputPin for NoCs {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// A 4-wire SPI interface, which drives the D/C pin low for commands and high for data.
+///
+/// This saves pulling in `display-interface-spi` for the common wiring, any other
+/// `WriteOnlyDataCommand` can be used with the driver as well. The chip select is left to the
+/// caller, e.g. tied low or held low while the display is used, unless the interface is created
+/// with `new_with_cs`.
+///
+/// ```ignore
+/// let interface = SpiInterface::new(spi, dc);
+/// let mut disp: Ssd1322<_> = Ssd1322::new(interface);
+/// ```
+///
+/// To share the bus with other devices, e.g. an SD card, pass a bus proxy such as the
+/// `shared-bus` ones and the chip select, which then frames each command and data transfer:
+///
+/// ```ignore
+/// let bus = shared_bus::BusManagerSimple::new(spi);
+/// let interface = SpiInterface::new_with_cs(bus.acquire_spi(), dc, cs);
+/// let sd_card = SdCard::new(bus.acquire_spi(), sd_cs);
+/// ```
+#[derive(Debug)]
+pub struct SpiInterface<SPI, DC, CS = NoCs> {
+    spi: SPI,
+    dc: DC,
+    cs: CS,
+}
+
+impl<SPI, DC> SpiInterface<SPI, DC>
+where
+    SPI: Write<u8>,
+    DC: OutputPin,
+{
+    /// Creates the interface from a SPI bus and the D/C pin.
+    pub fn new(spi: SPI, dc: DC) -> Self {
+        Self { spi, dc, cs: NoCs }
+    }
+
+    /// Returns the SPI bus and the D/C pin.
+    pub fn release(self) -> (SPI, DC) {
+        (self.spi, self.dc)
+    }
+}
+
+impl<SPI, DC, CS> SpiInterface<SPI, DC, CS>
+where
+    SPI: Write<u8>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    /// Creates the interface from a SPI bus, which may be shared with other devices, and the
+    /// D/C and chip select pins.
+    pub fn new_with_cs(spi: SPI, dc: DC, cs: CS) -> Self {
+        Self { spi, dc, cs }
+    }
+
+    /// Returns the SPI bus and the D/C and chip select pins.
+    pub fn release_with_cs(self) -> (SPI, DC, CS) {
+        (self.spi, self.dc, self.cs)
+    }
+
+    fn send(&mut self, data: bool, words: DataFormat<'_>) -> Result<(), DisplayError> {
+        let level = if data {
+            self.dc.set_high()
+        } else {
+            self.dc.set_low()
+        };
+        level.map_err(|_| DisplayError::DCError)?;
+
+        self.cs.set_low().map_err(|_| DisplayError::CSError)?;
+        let result = self.write_words(words);
+        self.cs.set_high().map_err(|_| DisplayError::CSError)?;
+
+        result
+    }
+
+    fn write_words(&mut self, words: DataFormat<'_>) -> Result<(), DisplayError> {
+        match words {
+            DataFormat::U8(slice) => write(&mut self.spi, slice),
+            DataFormat::U8Iter(iter) => self.write_iter(iter),
+            // Sent as their bytes, in the order of `DataWidth::U16`
+            DataFormat::U16BE(words) => self.write_iter(words.iter().flat_map(|w| w.to_be_bytes())),
+            // The SSD1322 only takes bytes
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        }
+    }
+
+    fn write_iter<I>(&mut self, bytes: I) -> Result<(), DisplayError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let mut chunk = [0; CHUNK_SIZE];
+        let mut len = 0;
+        for byte in bytes {
+            chunk[len] = byte;
+            len += 1;
+            if len == CHUNK_SIZE {
+                write(&mut self.spi, &chunk)?;
+                len = 0;
+            }
+        }
+
+        write(&mut self.spi, &chunk[..len])
+    }
+}
+
+impl<SPI, DC, CS> WriteOnlyDataCommand for SpiInterface<SPI, DC, CS>
+where
+    SPI: Write<u8>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(false, cmds)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(true, buf)
+    }
+}
+
+/// A 3-wire SPI interface for modules strapped for 3-wire SPI, which send the D/C flag as the
+/// first bit of each 9 bit word instead of on a D/C pin.
+///
+/// The words are packed into bytes, so any 8 bit SPI bus can be used. Each command or data
+/// transfer is framed by the chip select, which also discards the padding bits that complete
+/// the last byte.
+#[derive(Debug)]
+pub struct Spi3WireInterface<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+}
+
+impl<SPI, CS> Spi3WireInterface<SPI, CS>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+{
+    /// Creates the interface from a SPI bus and the chip select pin.
+    pub fn new(spi: SPI, cs: CS) -> Self {
+        Self { spi, cs }
+    }
+
+    /// Returns the SPI bus and the chip select pin.
+    pub fn release(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+
+    fn send(&mut self, data: bool, words: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.cs.set_low().map_err(|_| DisplayError::CSError)?;
+        let result = match words {
+            DataFormat::U8(slice) => self.send_words(data, slice.iter().copied()),
+            DataFormat::U8Iter(iter) => self.send_words(data, iter),
+            DataFormat::U16BE(words) => {
+                self.send_words(data, words.iter().flat_map(|w| w.to_be_bytes()))
+            }
+            // The SSD1322 only takes bytes
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        };
+        self.cs.set_high().map_err(|_| DisplayError::CSError)?;
+
+        result
+    }
+
+    fn send_words<I>(&mut self, data: bool, bytes: I) -> Result<(), DisplayError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let flag = if data { 0x100 } else { 0 };
+        let mut chunk = [0; CHUNK_SIZE];
+        let mut len = 0;
+        // Bits that don't fill a byte yet, in the low `pending` bits.
+        let (mut bits, mut pending) = (0u32, 0);
+        for byte in bytes {
+            bits = (bits << 9) | flag | u32::from(byte);
+            pending += 9;
+            while pending >= 8 {
+                pending -= 8;
+                chunk[len] = (bits >> pending) as u8;
+                len += 1;
+                if len == CHUNK_SIZE {
+                    write(&mut self.spi, &chunk)?;
+                    len = 0;
+                }
+            }
+            bits &= (1 << pending) - 1;
+        }
+
+        if pending > 0 {
+            chunk[len] = (bits << (8 - pending)) as u8;
+            len += 1;
+        }
+
+        write(&mut self.spi, &chunk[..len])
+    }
+}
+
+impl<SPI, CS> WriteOnlyDataCommand for Spi3WireInterface<SPI, CS>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(false, cmds)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(true, buf)
+    }
+}
+
+/// A transfer reported by a `TraceInterface`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer<'a> {
+    /// Command bytes, i.e. a command and its arguments sent with D/C low.
+    Commands(&'a [u8]),
+    /// The number of bytes of a data transfer, sent with D/C high.
+    Data(usize),
+}
+
+/// Wraps an interface and reports every transfer to `trace`, e.g. to log the command stream of
+/// a failing board with `log` or `defmt` and compare it to the one of a board that works.
+///
+/// Each transfer is reported after it is passed on, together with its result. Commands sent as
+/// 16 bit words, which the driver doesn't use, are passed on without being reported, and data in
+/// formats that display-interface adds later is reported with a length of 0.
+///
+/// ```ignore
+/// let interface = TraceInterface::new(interface, |transfer, result| {
+///     log::trace!("{:02X?} {:?}", transfer, result)
+/// });
+/// let mut disp: Ssd1322<_> = Ssd1322::new(interface);
+/// ```
+#[derive(Debug)]
+pub struct TraceInterface<DI, F> {
+    interface: DI,
+    trace: F,
+}
+
+impl<DI, F> TraceInterface<DI, F>
+where
+    DI: WriteOnlyDataCommand,
+    F: FnMut(Transfer<'_>, &Result<(), DisplayError>),
+{
+    /// Creates the interface, reporting the transfers to `interface` to `trace`.
+    pub fn new(interface: DI, trace: F) -> Self {
+        Self { interface, trace }
+    }
+
+    /// Returns the interface and the trace function.
+    pub fn release(self) -> (DI, F) {
+        (self.interface, self.trace)
+    }
+
+    fn send_command_bytes(&mut self, bytes: &[u8]) -> Result<(), DisplayError> {
+        let result = self.interface.send_commands(DataFormat::U8(bytes));
+        (self.trace)(Transfer::Commands(bytes), &result);
+
+        result
+    }
+}
+
+impl<DI, F> WriteOnlyDataCommand for TraceInterface<DI, F>
+where
+    DI: WriteOnlyDataCommand,
+    F: FnMut(Transfer<'_>, &Result<(), DisplayError>),
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        match cmds {
+            DataFormat::U8(bytes) => self.send_command_bytes(bytes),
+            // The bytes are collected so they can be reported.
+            DataFormat::U8Iter(iter) => {
+                let mut chunk = [0; CHUNK_SIZE];
+                let mut len = 0;
+                for byte in iter {
+                    chunk[len] = byte;
+                    len += 1;
+                    if len == CHUNK_SIZE {
+                        self.send_command_bytes(&chunk)?;
+                        len = 0;
+                    }
+                }
+
+                if len == 0 {
+                    return Ok(());
+                }
+                self.send_command_bytes(&chunk[..len])
+            }
+            words => self.interface.send_commands(words),
+        }
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        let mut len = 0;
+        let result = match buf {
+            DataFormat::U8(bytes) => {
+                len = bytes.len();
+                self.interface.send_data(DataFormat::U8(bytes))
+            }
+            DataFormat::U16(words) => {
+                len = words.len() * 2;
+                self.interface.send_data(DataFormat::U16(words))
+            }
+            DataFormat::U16BE(words) => {
+                len = words.len() * 2;
+                self.interface.send_data(DataFormat::U16BE(words))
+            }
+            DataFormat::U16LE(words) => {
+                len = words.len() * 2;
+                self.interface.send_data(DataFormat::U16LE(words))
+            }
+            DataFormat::U8Iter(iter) => self
+                .interface
+                .send_data(DataFormat::U8Iter(&mut iter.inspect(|_| len += 1))),
+            DataFormat::U16BEIter(iter) => self
+                .interface
+                .send_data(DataFormat::U16BEIter(&mut iter.inspect(|_| len += 2))),
+            DataFormat::U16LEIter(iter) => self
+                .interface
+                .send_data(DataFormat::U16LEIter(&mut iter.inspect(|_| len += 2))),
+            // Formats added to display-interface later have an unknown length.
+            words => self.interface.send_data(words),
+        };
+        (self.trace)(Transfer::Data(len), &result);
+
+        result
+    }
+}
+
+/// Tags of the transfers in a recording of a `RecordInterface`.
+pub(crate) const RECORD_COMMANDS: u8 = 0x00;
+pub(crate) const RECORD_DATA: u8 = 0x01;
+
+/// Wraps an interface and records every transfer into a caller-provided buffer, e.g. to capture
+/// the working init sequence of a vendor's driver on one board and resend it with
+/// `Ssd1322::replay` on another.
+///
+/// Each transfer is stored as a tag, 0 for commands and 1 for data, its length as a little
+/// endian `u16` and its bytes. Transfers longer than 65535 bytes are split. If the buffer is
+/// full, the transfer that didn't fit and all later ones are passed on without being recorded
+/// and `is_overflowed` returns true.
+///
+/// ```ignore
+/// let mut buffer = [0; 512];
+/// let mut interface = RecordInterface::new(interface, &mut buffer);
+/// vendor_init(&mut interface)?;
+/// let (interface, recording) = interface.release();
+/// ```
+#[derive(Debug)]
+pub struct RecordInterface<'a, DI> {
+    interface: DI,
+    recording: Recording<'a>,
+}
+
+/// The recorded transfers of a `RecordInterface`.
+#[derive(Debug)]
+struct Recording<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+    /// Start of the transfer being recorded.
+    transfer: usize,
+    /// Start of the header of the record being written.
+    header: usize,
+    overflowed: bool,
+}
+
+impl<'a, DI> RecordInterface<'a, DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    /// Creates the interface, recording the transfers to `interface` into `buffer`.
+    pub fn new(interface: DI, buffer: &'a mut [u8]) -> Self {
+        Self {
+            interface,
+            recording: Recording {
+                buffer,
+                len: 0,
+                transfer: 0,
+                header: 0,
+                overflowed: false,
+            },
+        }
+    }
+
+    /// Returns the recorded transfers.
+    pub fn recording(&self) -> &[u8] {
+        &self.recording.buffer[..self.recording.len]
+    }
+
+    /// Returns true if transfers weren't recorded because the buffer was full.
+    pub fn is_overflowed(&self) -> bool {
+        self.recording.overflowed
+    }
+
+    /// Returns the interface and the recorded transfers.
+    pub fn release(self) -> (DI, &'a [u8]) {
+        let Recording { buffer, len, .. } = self.recording;
+        (self.interface, &buffer[..len])
+    }
+
+    fn record(&mut self, tag: u8, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        let Self {
+            interface,
+            recording,
+        } = self;
+        recording.begin(tag);
+        let result = match buf {
+            DataFormat::U8(bytes) => {
+                bytes.iter().for_each(|&byte| recording.push(tag, byte));
+                send(interface, tag, DataFormat::U8(bytes))
+            }
+            DataFormat::U8Iter(iter) => send(
+                interface,
+                tag,
+                DataFormat::U8Iter(&mut iter.inspect(|&byte| recording.push(tag, byte))),
+            ),
+            DataFormat::U16BE(words) => {
+                words
+                    .iter()
+                    .flat_map(|word| word.to_be_bytes())
+                    .for_each(|byte| recording.push(tag, byte));
+                send(interface, tag, DataFormat::U16BE(words))
+            }
+            // The SSD1322 only takes bytes
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        };
+        recording.end();
+
+        result
+    }
+}
+
+impl<'a, DI> WriteOnlyDataCommand for RecordInterface<'a, DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.record(RECORD_COMMANDS, cmds)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.record(RECORD_DATA, buf)
+    }
+}
+
+impl Recording<'_> {
+    fn begin(&mut self, tag: u8) {
+        self.transfer = self.len;
+        self.header(tag);
+    }
+
+    /// Starts a record of transfer `tag`.
+    fn header(&mut self, tag: u8) {
+        if self.overflowed || self.buffer.len() - self.len < 3 {
+            self.overflow();
+            return;
+        }
+
+        self.header = self.len;
+        self.buffer[self.len..self.len + 3].copy_from_slice(&[tag, 0, 0]);
+        self.len += 3;
+    }
+
+    fn push(&mut self, tag: u8, byte: u8) {
+        if self.overflowed {
+            return;
+        }
+
+        let mut count = self.len - self.header - 3;
+        if count == usize::from(u16::MAX) {
+            self.header(tag);
+            count = 0;
+        }
+        if self.overflowed {
+            return;
+        }
+        if self.len == self.buffer.len() {
+            self.overflow();
+            return;
+        }
+
+        self.buffer[self.len] = byte;
+        self.len += 1;
+        self.buffer[self.header + 1..self.header + 3]
+            .copy_from_slice(&(count as u16 + 1).to_le_bytes());
+    }
+
+    /// Drops an empty transfer, so replaying it doesn't send an empty transfer.
+    fn end(&mut self) {
+        if !self.overflowed && self.len == self.transfer + 3 {
+            self.len = self.transfer;
+        }
+    }
+
+    /// Discards the transfer being recorded and stops recording.
+    fn overflow(&mut self) {
+        if !self.overflowed {
+            self.overflowed = true;
+            self.len = self.transfer;
+        }
+    }
+}
+
+/// Returns the tags and bytes of the transfers of `recording`, or
+/// `DisplayError::InvalidFormatError` at the end if it is truncated.
+pub(crate) fn records(
+    recording: &[u8],
+) -> impl Iterator<Item = Result<(u8, &[u8]), DisplayError>> + '_ {
+    let mut rest = recording;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let record = match rest {
+            [tag, low, high, tail @ ..] => {
+                let len = usize::from(u16::from_le_bytes([*low, *high]));
+                (len <= tail.len()).then(|| (*tag, tail.split_at(len)))
+            }
+            _ => None,
+        };
+        match record {
+            Some((tag, (bytes, tail))) => {
+                rest = tail;
+                Some(Ok((tag, bytes)))
+            }
+            None => {
+                rest = &[];
+                Some(Err(DisplayError::InvalidFormatError))
+            }
+        }
+    })
+}
+
+/// Sends `buf` to `interface` as the transfer `tag` of a recording.
+pub(crate) fn send<DI: WriteOnlyDataCommand>(
+    interface: &mut DI,
+    tag: u8,
+    buf: DataFormat<'_>,
+) -> Result<(), DisplayError> {
+    match tag {
+        RECORD_COMMANDS => interface.send_commands(buf),
+        RECORD_DATA => interface.send_data(buf),
+        _ => Err(DisplayError::InvalidFormatError),
+    }
+}
+
+/// Writes `bytes` to `spi`.
+fn write<SPI: Write<u8>>(spi: &mut SPI, bytes: &[u8]) -> Result<(), DisplayError> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+
+    spi.write(bytes).map_err(|_| DisplayError::BusWriteError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Ssd1322;
+    use crate::mock::RecordingInterface;
+    use core::cell::Cell;
+    use display_interface::DataFormat::{U8Iter, U16, U8};
+
+    /// Records the level of the D/C or chip select pin with each written byte.
+    struct RecordingSpi<'a> {
+        pin: &'a Cell<bool>,
+        bytes: [(bool, u8); 64],
+        len: usize,
+        writes: usize,
+    }
+
+    struct Pin<'a>(&'a Cell<bool>);
+
+    impl Write<u8> for RecordingSpi<'_> {
+        type Error = ();
+
+        fn write(&mut self, words: &[u8]) -> Result<(), ()> {
+            for &b in words {
+                self.bytes[self.len] = (self.pin.get(), b);
+                self.len += 1;
+            }
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    impl OutputPin for Pin<'_> {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), ()> {
+            self.0.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), ()> {
+            self.0.set(true);
+            Ok(())
+        }
+    }
+
+    #[test]
+    /// Tests that commands are sent with D/C low and data with D/C high.
+    fn spi_interface() {
+        let dc = Cell::new(true);
+        let spi = RecordingSpi {
+            pin: &dc,
+            bytes: [(false, 0); 64],
+            len: 0,
+            writes: 0,
+        };
+        let mut interface = SpiInterface::new(spi, Pin(&dc));
+
+        interface.send_commands(U8(&[0x15])).unwrap();
+        interface.send_data(U8(&[0x1C, 0x5B])).unwrap();
+        interface
+            .send_data(U8Iter(&mut (0..40).map(|b| b as u8)))
+            .unwrap();
+        assert!(matches!(
+            interface.send_data(U16(&[0])),
+            Err(DisplayError::DataFormatNotImplemented)
+        ));
+
+        let (spi, _) = interface.release();
+        assert_eq!(spi.bytes[..3], [(false, 0x15), (true, 0x1C), (true, 0x5B)]);
+        assert_eq!(spi.len, 43);
+        assert_eq!(spi.bytes[42], (true, 39));
+        assert_eq!(spi.writes, 4);
+    }
+
+    #[test]
+    /// Tests that a flush in 16 bit words is sent as the same bytes as a flush in bytes.
+    fn spi_interface_u16() {
+        use crate::config::{DataWidth, DisplayConfig};
+
+        let flush = |data_width| {
+            let dc = Cell::new(true);
+            let spi = RecordingSpi {
+                pin: &dc,
+                bytes: [(false, 0); 64],
+                len: 0,
+                writes: 0,
+            };
+            let config = DisplayConfig {
+                data_width,
+                ..DisplayConfig::for_panel(256, 64)
+            };
+            let mut disp: Ssd1322<_> =
+                Ssd1322::new_with_config(SpiInterface::new(spi, Pin(&dc)), config);
+            disp.set_pixel(9, 3, 0x0A);
+            disp.set_pixel(10, 3, 0x05);
+            disp.flush().unwrap();
+            let (spi, _) = disp.release().release();
+            (spi.bytes, spi.len)
+        };
+
+        let (bytes, len) = flush(DataWidth::U16);
+        assert_eq!((bytes, len), flush(DataWidth::U8));
+        assert_eq!(
+            bytes[len - 3..len],
+            [(false, 0x5C), (true, 0x0A), (true, 0x50)]
+        );
+    }
+    #[test]
+    /// Tests that the D/C flag is packed as the first bit of each 9 bit word.
+    fn spi_3wire_interface() {
+        let cs = Cell::new(true);
+        let spi = RecordingSpi {
+            pin: &cs,
+            bytes: [(false, 0); 64],
+            len: 0,
+            writes: 0,
+        };
+        let mut interface = Spi3WireInterface::new(spi, Pin(&cs));
+
+        interface.send_commands(U8(&[0x15])).unwrap();
+        assert!(cs.get());
+        interface
+            .send_data(U8Iter(&mut [0x1C, 0x5B].iter().copied()))
+            .unwrap();
+        interface.send_data(U8(&[0xFF; 8])).unwrap();
+
+        let (spi, _) = interface.release();
+        // The chip select is low while writing.
+        assert!(spi.bytes[..spi.len].iter().all(|&(cs, _)| !cs));
+        let bytes = spi.bytes[..spi.len].iter().map(|&(_, b)| b);
+        assert!(bytes.eq([
+            0x0A, 0x80, // 0_0001_0101
+            0x8E, 0x56, 0xC0, // 1_0001_1100 1_0101_1011
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 8 x 1_1111_1111
+        ]));
+    }
+
+    #[test]
+    /// Tests that the chip select frames each transfer, so the bus can be shared.
+    fn spi_interface_with_cs() {
+        use core::cell::RefCell;
+
+        /// A proxy of a bus that is shared with another device.
+        struct Proxy<'a>(&'a RefCell<RecordingSpi<'a>>);
+
+        impl Write<u8> for Proxy<'_> {
+            type Error = ();
+
+            fn write(&mut self, words: &[u8]) -> Result<(), ()> {
+                self.0.borrow_mut().write(words)
+            }
+        }
+
+        let (dc, cs) = (Cell::new(true), Cell::new(true));
+        let bus = RefCell::new(RecordingSpi {
+            pin: &cs,
+            bytes: [(false, 0); 64],
+            len: 0,
+            writes: 0,
+        });
+        let mut interface = SpiInterface::new_with_cs(Proxy(&bus), Pin(&dc), Pin(&cs));
+
+        interface.send_commands(U8(&[0x15])).unwrap();
+        assert!(cs.get());
+        // Another device on the bus, while the display isn't selected.
+        Proxy(&bus).write(&[0xAA]).unwrap();
+        interface.send_data(U8(&[0x1C, 0x5B])).unwrap();
+        assert!(matches!(
+            interface.send_data(U16(&[0])),
+            Err(DisplayError::DataFormatNotImplemented)
+        ));
+        assert!(cs.get());
+
+        let bus = bus.borrow();
+        assert_eq!(
+            bus.bytes[..bus.len],
+            [(false, 0x15), (true, 0xAA), (false, 0x1C), (false, 0x5B)]
+        );
+    }
+
+    #[test]
+    /// Tests that every transfer is passed on and reported.
+    fn trace_interface() {
+        let dc = Cell::new(true);
+        let spi = RecordingSpi {
+            pin: &dc,
+            bytes: [(false, 0); 64],
+            len: 0,
+            writes: 0,
+        };
+        let mut commands = [0; 8];
+        let mut data = [0; 2];
+        let (mut num_commands, mut num_data) = (0, 0);
+        let mut interface =
+            TraceInterface::new(
+                SpiInterface::new(spi, Pin(&dc)),
+                |transfer, _| match transfer {
+                    Transfer::Commands(bytes) => {
+                        commands[num_commands..][..bytes.len()].copy_from_slice(bytes);
+                        num_commands += bytes.len();
+                    }
+                    Transfer::Data(len) => {
+                        data[num_data] = len;
+                        num_data += 1;
+                    }
+                },
+            );
+
+        interface.send_commands(U8(&[0xAE, 0x15])).unwrap();
+        interface
+            .send_commands(U8Iter(&mut [0x75, 0x5C].iter().copied()))
+            .unwrap();
+        interface.send_data(U8(&[0x1C, 0x5B])).unwrap();
+        interface
+            .send_data(U8Iter(&mut (0..40).map(|b| b as u8)))
+            .unwrap();
+
+        let (spi, _) = interface.release().0.release();
+        assert_eq!(spi.len, 46);
+        assert_eq!(commands[..num_commands], [0xAE, 0x15, 0x75, 0x5C]);
+        assert_eq!(data[..num_data], [2, 40]);
+    }
+
+    #[test]
+    /// Tests that a replayed lock (FDh 16h) locks the driver, which then only replays an
+    /// unlock.
+    fn replay_lock() {
+        let lock = [0x00, 0x01, 0x00, 0xFD, 0x01, 0x01, 0x00, 0x16];
+        let display_on = [0x00, 0x01, 0x00, 0xAF];
+        let unlock = [0x00, 0x01, 0x00, 0xFD, 0x01, 0x01, 0x00, 0x12];
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        disp.replay(&lock).unwrap();
+        assert!(disp.is_locked());
+        assert!(disp.display.sent().eq([(false, 0xFD), (true, 0x16)]));
+
+        disp.replay(&lock).unwrap();
+        assert!(disp.is_locked());
+
+        disp.display.clear();
+        let mut recording = [0; 12];
+        recording[..8].copy_from_slice(&lock);
+        recording[8..].copy_from_slice(&display_on);
+        let result = disp.replay(&recording);
+        assert!(matches!(result, Err(DisplayError::InvalidFormatError)));
+        assert!(matches!(
+            disp.replay(&display_on),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert_eq!(disp.display.sent().next(), None);
+
+        recording[..8].copy_from_slice(&unlock);
+        disp.replay(&recording).unwrap();
+        assert!(!disp.is_locked());
+        assert!(disp.display.has_command(&[0xAF]));
+    }
+
+    #[test]
+    /// Tests that a recorded init sequence is replayed unchanged.
+    fn record_and_replay() {
+        let mut buffer = [0; 256];
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordInterface::new(
+            RecordingInterface::<256>::new(),
+            &mut buffer,
+        ));
+        disp.init().unwrap();
+        let (sent, recording) = disp.release().release();
+        assert_eq!(recording[..6], [0x00, 0x01, 0x00, 0xFD, 0x01, 0x01]);
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<256>::new());
+        disp.replay(recording).unwrap();
+        assert!(disp.release().sent().eq(sent.sent()));
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<256>::new());
+        let result = disp.replay(&recording[..5]);
+        assert!(matches!(result, Err(DisplayError::InvalidFormatError)));
+
+        let mut small = [0; 8];
+        let mut interface = RecordInterface::new(RecordingInterface::<16>::new(), &mut small);
+        interface.send_commands(U8(&[0xAE])).unwrap();
+        interface
+            .send_data(U8Iter(&mut [].iter().copied()))
+            .unwrap();
+        interface.send_commands(U8(&[0x15, 0x1C, 0x5B])).unwrap();
+        interface.send_commands(U8(&[0xAF])).unwrap();
+        assert!(interface.is_overflowed());
+        assert_eq!(interface.recording(), [0x00, 0x01, 0x00, 0xAE]);
+    }
+}
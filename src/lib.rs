@@ -10,5 +10,36 @@
 //! Builder example
 extern crate embedded_hal as hal;
 
-mod command;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "graphics")]
+pub mod adapter;
+pub mod binary;
+pub mod builder;
+pub mod clone;
+pub mod command;
+pub mod config;
+mod dirty;
 pub mod display;
+pub mod interface;
+pub mod limiter;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod mode;
+pub mod parallel;
+#[cfg(feature = "graphics")]
+pub mod pending;
+pub mod power;
+pub mod screensaver;
+pub mod shift;
+#[cfg(any(test, feature = "simulator"))]
+pub mod simulator;
+pub mod split;
+#[cfg(any(test, feature = "stream"))]
+pub mod stream;
+pub mod terminal;
+#[cfg(feature = "graphics")]
+pub mod tiled;
@@ -0,0 +1,163 @@
+//! frame rate limiter module
+use crate::display::Ssd1322;
+use crate::mode::BufferedGraphicsMode;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+use hal::blocking::delay::DelayMs;
+
+/// Throttles the flushes of a render loop to a target frame rate and skips the flushes when
+/// nothing changed, so a tight loop doesn't saturate a slow SPI bus.
+///
+/// ```ignore
+/// let mut limiter = FrameLimiter::new(30);
+/// loop {
+///     draw_ui(&mut disp)?;
+///     limiter.flush(&mut disp, timer.millis())?;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLimiter {
+    interval: u16,
+    last: Option<u32>,
+}
+
+impl FrameLimiter {
+    /// Creates a limiter for up to `fps` frames per second, clamped to 1 to 1000.
+    pub const fn new(fps: u16) -> Self {
+        let fps = if fps == 0 {
+            1
+        } else if fps > 1000 {
+            1000
+        } else {
+            fps
+        };
+
+        Self {
+            interval: 1000 / fps,
+            last: None,
+        }
+    }
+
+    /// Returns the minimum time between two flushes, in milliseconds.
+    pub const fn interval(&self) -> u16 {
+        self.interval
+    }
+
+    /// Flushes the changes of `disp` if a frame interval has passed since the last flush.
+    /// `now` is a millisecond timestamp, e.g. of a monotonic timer, which may wrap around.
+    ///
+    /// Returns true if the display was flushed. If nothing changed or the interval hasn't
+    /// passed yet, the changes are kept for a later call.
+    pub fn flush<DI, const WIDTH: usize, const HEIGHT: usize, B, const TRACKED: bool>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, BufferedGraphicsMode<TRACKED>>,
+        now: u32,
+    ) -> Result<bool, DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        if disp.dirty.is_empty() {
+            return Ok(false);
+        }
+        if matches!(self.last, Some(last) if now.wrapping_sub(last) < u32::from(self.interval)) {
+            return Ok(false);
+        }
+
+        disp.flush()?;
+        self.last = Some(now);
+
+        Ok(true)
+    }
+
+    /// Flushes the changes of `disp` and then waits a frame interval with `delay`, for targets
+    /// without a clock. The rendering time isn't deducted, so the frame rate stays below the
+    /// target.
+    ///
+    /// Returns true if the display was flushed. If nothing changed, it returns immediately.
+    pub fn flush_with_delay<
+        DI,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        B,
+        const TRACKED: bool,
+        DELAY,
+    >(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, BufferedGraphicsMode<TRACKED>>,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+        B: AsRef<[u8]> + AsMut<[u8]>,
+        DELAY: DelayMs<u16>,
+    {
+        if disp.dirty.is_empty() {
+            return Ok(false);
+        }
+
+        disp.flush()?;
+        delay.delay_ms(self.interval);
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    struct Delay(u32);
+
+    impl DelayMs<u16> for Delay {
+        fn delay_ms(&mut self, ms: u16) {
+            self.0 += u32::from(ms);
+        }
+    }
+
+    #[test]
+    /// Tests that flushes are throttled to the frame rate and skipped without changes.
+    fn frame_limiter() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let mut limiter = FrameLimiter::new(50);
+        assert_eq!(limiter.interval(), 20);
+        assert_eq!(FrameLimiter::new(0).interval(), 1000);
+
+        assert!(!limiter.flush(&mut disp, 0).unwrap());
+        disp.set_pixel(0, 0, 0x0F);
+        assert!(limiter.flush(&mut disp, u32::MAX - 4).unwrap());
+        disp.set_pixel(1, 0, 0x0F);
+        assert!(!limiter.flush(&mut disp, 10).unwrap());
+        assert!(limiter.flush(&mut disp, 15).unwrap());
+        assert_eq!(disp.stats().flushes, 2);
+
+        let mut delay = Delay(0);
+        assert!(!limiter.flush_with_delay(&mut disp, &mut delay).unwrap());
+        disp.set_pixel(2, 0, 0x0F);
+        assert!(limiter.flush_with_delay(&mut disp, &mut delay).unwrap());
+        assert_eq!(delay.0, 20);
+    }
+
+    #[test]
+    /// Tests that drawing in untracked mode is flushed, as the whole frame, and only once.
+    fn frame_limiter_untracked() {
+        use crate::mode::UntrackedMode;
+
+        let mut disp: Ssd1322<_, 256, 64, [u8; 8192], UntrackedMode> =
+            Ssd1322::new_untracked(RecordingInterface::<64>::new());
+        let mut limiter = FrameLimiter::new(50);
+
+        assert!(!limiter.flush(&mut disp, 0).unwrap());
+        disp.set_pixel(0, 0, 0x0F);
+        assert!(limiter.flush(&mut disp, 0).unwrap());
+        assert!(!limiter.flush(&mut disp, 20).unwrap());
+        assert_eq!(disp.stats().windows, 1);
+        assert_eq!(disp.stats().bytes, 8192);
+
+        let mut delay = Delay(0);
+        assert!(!limiter.flush_with_delay(&mut disp, &mut delay).unwrap());
+        disp.set_pixel(1, 0, 0x0F);
+        assert!(limiter.flush_with_delay(&mut disp, &mut delay).unwrap());
+        assert_eq!(delay.0, 20);
+    }
+}
@@ -0,0 +1,224 @@
+//! recording interface module
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+/// A `WriteOnlyDataCommand` that records the bytes sent to it instead of driving a display, to
+/// check init sequences, flush windows and partial updates in tests without hardware.
+///
+/// Up to `N` bytes are recorded, later ones are dropped and `is_overflowed` returns true.
+///
+/// ```ignore
+/// let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<256>::new());
+/// disp.init()?;
+/// assert!(disp.release().has_command(&[0xCA, 0x3F]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordingInterface<const N: usize = 1024> {
+    bytes: [u8; N],
+    data: [bool; N],
+    len: usize,
+    transfers: usize,
+    overflowed: bool,
+    fail: bool,
+    failures: usize,
+}
+
+/// A GDDRAM write recorded by a `RecordingInterface`, with the address window set before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window<'a> {
+    /// The first and last GDDRAM column address.
+    pub columns: [u8; 2],
+    /// The first and last GDDRAM row address.
+    pub rows: [u8; 2],
+    /// The bytes written.
+    pub data: &'a [u8],
+}
+
+impl<const N: usize> RecordingInterface<N> {
+    /// Creates an interface that hasn't recorded anything.
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            data: [false; N],
+            len: 0,
+            transfers: 0,
+            overflowed: false,
+            fail: false,
+            failures: 0,
+        }
+    }
+
+    /// Returns the recorded bytes, with true for the ones sent as data.
+    pub fn sent(&self) -> impl Iterator<Item = (bool, u8)> + '_ {
+        self.data[..self.len]
+            .iter()
+            .copied()
+            .zip(self.bytes[..self.len].iter().copied())
+    }
+
+    /// Returns the recorded command bytes, without their arguments.
+    pub fn commands(&self) -> impl Iterator<Item = u8> + '_ {
+        self.sent().filter(|&(data, _)| !data).map(|(_, byte)| byte)
+    }
+
+    /// Returns true if `command`, a command byte followed by its arguments, was sent.
+    pub fn has_command(&self, command: &[u8]) -> bool {
+        let (bytes, data) = (&self.bytes[..self.len], &self.data[..self.len]);
+        (0..bytes.len()).any(|i| {
+            command.len() <= bytes.len() - i
+                && !data[i]
+                && bytes[i..].starts_with(command)
+                && data[i + 1..i + command.len()].iter().all(|&data| data)
+        })
+    }
+
+    /// Returns the GDDRAM writes, in the order they were sent.
+    pub fn windows(&self) -> impl Iterator<Item = Window<'_>> + '_ {
+        let (bytes, data) = (&self.bytes[..self.len], &self.data[..self.len]);
+        let mut i = 0;
+        let (mut command, mut args) = (0, 0);
+        let (mut columns, mut rows) = ([0; 2], [0; 2]);
+        core::iter::from_fn(move || {
+            while i < bytes.len() {
+                if !data[i] {
+                    command = bytes[i];
+                    args = 0;
+                } else if command == 0x5C {
+                    let start = i;
+                    while i < bytes.len() && data[i] {
+                        i += 1;
+                    }
+                    return Some(Window {
+                        columns,
+                        rows,
+                        data: &bytes[start..i],
+                    });
+                } else {
+                    match command {
+                        0x15 if args < 2 => columns[args] = bytes[i],
+                        0x75 if args < 2 => rows[args] = bytes[i],
+                        _ => {}
+                    }
+                    args += 1;
+                }
+                i += 1;
+            }
+
+            None
+        })
+    }
+
+    /// Returns the number of command and data transfers.
+    pub fn transfers(&self) -> usize {
+        self.transfers
+    }
+
+    /// Returns true if bytes were dropped because more than `N` were sent.
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Makes every following transfer fail with `DisplayError::BusWriteError`, e.g. to test
+    /// the error handling of a flush.
+    pub fn set_fail(&mut self, fail: bool) {
+        self.fail = fail;
+    }
+
+    /// Makes the next `count` transfers fail with `DisplayError::BusWriteError`, e.g. to test
+    /// the recovery from a glitch on the bus.
+    pub fn fail_next(&mut self, count: usize) {
+        self.failures = count;
+    }
+
+    /// Discards the recorded bytes.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.transfers = 0;
+        self.overflowed = false;
+    }
+
+    fn record(&mut self, data: bool, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        if self.fail {
+            return Err(DisplayError::BusWriteError);
+        }
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(DisplayError::BusWriteError);
+        }
+
+        self.transfers += 1;
+        match buf {
+            DataFormat::U8(slice) => slice.iter().for_each(|&byte| self.push(data, byte)),
+            DataFormat::U8Iter(iter) => iter.for_each(|byte| self.push(data, byte)),
+            // Recorded in the order the bytes are sent on the bus
+            DataFormat::U16BE(words) => words
+                .iter()
+                .flat_map(|word| word.to_be_bytes())
+                .for_each(|byte| self.push(data, byte)),
+            // The SSD1322 only takes bytes
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, data: bool, byte: u8) {
+        if self.len == N {
+            self.overflowed = true;
+            return;
+        }
+
+        self.bytes[self.len] = byte;
+        self.data[self.len] = data;
+        self.len += 1;
+    }
+}
+
+impl<const N: usize> Default for RecordingInterface<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> WriteOnlyDataCommand for RecordingInterface<N> {
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.record(false, cmds)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.record(true, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Ssd1322;
+
+    #[test]
+    /// Tests that the init sequence and the flush windows are recorded.
+    fn recording_interface() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<256>::new());
+        disp.init().unwrap();
+        disp.set_pixel(4, 1, 0x0F);
+        disp.flush().unwrap();
+
+        let mut iface = disp.release();
+        assert!(!iface.is_overflowed());
+        assert_eq!(iface.commands().next(), Some(0xFD));
+        assert!(iface.has_command(&[0xCA, 0x3F]));
+        assert!(iface.has_command(&[0xAF]));
+        assert!(!iface.has_command(&[0xCA, 0x1F]));
+
+        let window = Window {
+            columns: [0x1D, 0x1D],
+            rows: [1, 1],
+            data: &[0xF0, 0x00],
+        };
+        assert!(iface.windows().eq([window]));
+
+        iface.clear();
+        iface.set_fail(true);
+        assert!(iface.send_data(DataFormat::U8(&[0])).is_err());
+        assert_eq!(iface.sent().count(), 0);
+    }
+}
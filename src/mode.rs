@@ -0,0 +1,434 @@
+//! operating mode module
+use crate::command::Command;
+use crate::config::DisplayConfig;
+#[cfg(feature = "graphics")]
+use crate::display::{update_lower_nibble, update_upper_nibble};
+use crate::display::{BufferSize, Ssd1322};
+use display_interface::{DataFormat::U8, DisplayError, WriteOnlyDataCommand};
+#[cfg(feature = "graphics")]
+use embedded_graphics::{
+    draw_target::DrawTarget, pixelcolor::Gray4, prelude::*, primitives::Rectangle, Pixel,
+};
+
+/// Only commands can be sent to the display. There is no framebuffer and no drawing support,
+/// e.g. for applications that stream their own image data after `WriteRAM`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BasicMode;
+
+/// Drawing goes to a framebuffer in the driver and is sent to the display by the flush methods.
+///
+/// This is the default mode. With `TRACKED` false, see `UntrackedMode`, the changes aren't
+/// tracked while drawing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BufferedGraphicsMode<const TRACKED: bool = true>;
+
+/// Buffered graphics mode without change tracking, for applications that always send the whole
+/// frame.
+///
+/// Drawing writes the pixels without comparing them to the framebuffer or growing the changed
+/// areas, which is decided at compile time. The driver only notes that something was drawn, and
+/// `flush` then sends the whole framebuffer like `flush_all`.
+pub type UntrackedMode = BufferedGraphicsMode<false>;
+
+/// Drawing is written straight to the display GDDRAM without a local framebuffer, for MCUs that
+/// cannot spare the RAM and only draw simple content.
+///
+/// The GDDRAM is written in units of a column address, which covers 4 horizontally adjacent
+/// pixels, and cannot be read back. Pixels that share a column address with a drawn pixel are
+/// therefore set to black, unless they are drawn by the same `draw_iter` call (consecutive
+/// pixels) or are covered by the same `fill_solid` call. This suits text and shapes on a black
+/// background.
+///
+/// Since drawing writes to the display, the `DrawTarget` error type is `DisplayError`, and errors
+/// of the interface are returned by the drawing methods.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectMode;
+
+/// Bytes sent per `send_data` call when generating window contents.
+const CHUNK_SIZE: usize = 32;
+
+/// The pixels of one column address waiting to be written.
+#[cfg(feature = "graphics")]
+#[derive(Clone, Copy)]
+struct Group {
+    col: usize,
+    row: usize,
+    data: [u8; 2],
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize> Ssd1322<DI, WIDTH, HEIGHT, (), BasicMode> {
+    /// Creates the SSD1322 Display in basic mode, which only sends commands.
+    ///
+    /// The panel is assumed to be centred in the GDDRAM. The device needs to be reset before use.
+    pub fn new_basic(display: DI) -> Self {
+        Self::new_basic_with_config(display, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display in basic mode for a panel wired as described by `config`.
+    ///
+    /// The configuration is validated by `init`. The device needs to be reset before use.
+    pub fn new_basic_with_config(display: DI, config: DisplayConfig) -> Self {
+        Self::from_parts(display, (), config, BasicMode)
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> Ssd1322<DI, WIDTH, HEIGHT, B, MODE> {
+    /// Converts the driver into basic mode, dropping the framebuffer.
+    ///
+    /// The display configuration and the content shown on the display are kept.
+    pub fn into_basic_mode(self) -> Ssd1322<DI, WIDTH, HEIGHT, (), BasicMode> {
+        self.into_mode((), BasicMode)
+    }
+
+    /// Converts the driver into direct mode, dropping the framebuffer.
+    ///
+    /// The display configuration and the content shown on the display are kept.
+    pub fn into_direct_mode(self) -> Ssd1322<DI, WIDTH, HEIGHT, (), DirectMode> {
+        self.into_mode((), DirectMode)
+    }
+
+    /// Converts the driver into buffered graphics mode with a new, cleared framebuffer.
+    ///
+    /// The display configuration and the content shown on the display are kept, the content is
+    /// replaced by the next `flush_all`.
+    pub fn into_buffered_graphics_mode<const N: usize>(
+        self,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, [u8; N], BufferedGraphicsMode> {
+        let () = BufferSize::<WIDTH, HEIGHT, N>::CHECK;
+
+        self.into_mode([0; N], BufferedGraphicsMode)
+    }
+
+    /// Converts the driver into buffered graphics mode using an external framebuffer, which is
+    /// cleared. See `into_buffered_graphics_mode`.
+    pub fn into_buffered_graphics_mode_with_buffer<const N: usize>(
+        self,
+        buffer: &mut [u8; N],
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, &mut [u8; N], BufferedGraphicsMode> {
+        let () = BufferSize::<WIDTH, HEIGHT, N>::CHECK;
+
+        buffer.fill(0);
+        self.into_mode(buffer, BufferedGraphicsMode)
+    }
+
+    pub(crate) fn into_mode<B2, MODE2>(
+        self,
+        buffer: B2,
+        mode: MODE2,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, B2, MODE2> {
+        let mut display = Ssd1322::from_parts(self.display, buffer, self.config, mode);
+        display.locked = self.locked;
+        display.start_line = self.start_line;
+        display
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize> Ssd1322<DI, WIDTH, HEIGHT, (), DirectMode> {
+    /// Creates the SSD1322 Display in direct mode, without a framebuffer.
+    ///
+    /// The panel is assumed to be centred in the GDDRAM. The device needs to be reset before use.
+    pub fn new_direct(display: DI) -> Self {
+        Self::new_direct_with_config(display, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display in direct mode for a panel wired as described by `config`.
+    ///
+    /// The configuration is validated by `init`. The device needs to be reset before use.
+    pub fn new_direct_with_config(display: DI, config: DisplayConfig) -> Self {
+        Self::from_parts(display, (), config, DirectMode)
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> Ssd1322<DI, WIDTH, HEIGHT, B, MODE>
+where
+    DI: WriteOnlyDataCommand,
+{
+    /// Writes the GDDRAM columns `x[0]..=x[1]` (in column addresses relative to the panel) of the
+    /// rows `y[0]..=y[1]` without going through the 4 bit framebuffer. `byte` returns the byte at
+    /// an offset within a row with the left pixel in the upper nibble, given the buffer of the
+    /// mode and the row index within the window.
+    pub(crate) fn write_window<F>(
+        &mut self,
+        x: [usize; 2],
+        y: [usize; 2],
+        byte: F,
+    ) -> Result<(), DisplayError>
+    where
+        F: Fn(&B, usize, usize) -> u8,
+    {
+        let col_start = self.col_start();
+        self.send_command(Command::SetColumnAddress(
+            x[0] as u8 + col_start,
+            x[1] as u8 + col_start,
+        ))?;
+        self.send_command(Command::SetRowAddress(y[0] as u8, y[1] as u8))?;
+        self.send_command(Command::WriteRAM)?;
+
+        let order = self.config.nibble_order;
+        let row_bytes = (x[1] - x[0] + 1) * 2;
+        let mut chunk = [0; CHUNK_SIZE];
+        for row in 0..=y[1] - y[0] {
+            for start in (0..row_bytes).step_by(CHUNK_SIZE) {
+                let len = (row_bytes - start).min(CHUNK_SIZE);
+                for (i, b) in chunk[..len].iter_mut().enumerate() {
+                    *b = order.arrange(byte(&self.buffer, row, start + i));
+                }
+                self.display.send_data(U8(&chunk[..len]))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize> Ssd1322<DI, WIDTH, HEIGHT, (), DirectMode>
+where
+    DI: WriteOnlyDataCommand,
+{
+    fn write_group(&mut self, group: Group) -> Result<(), DisplayError> {
+        self.write_window([group.col; 2], [group.row; 2], |_, _, i| group.data[i])
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<DI, const WIDTH: usize, const HEIGHT: usize> DrawTarget
+    for Ssd1322<DI, WIDTH, HEIGHT, (), DirectMode>
+where
+    DI: WriteOnlyDataCommand,
+{
+    type Color = Gray4;
+    type Error = DisplayError;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // Consecutive pixels of the same column address are collected and written together.
+        let mut group: Option<Group> = None;
+
+        for Pixel(coord, color) in pixels.into_iter() {
+            // `DrawTarget` implementation are required to discard any out of bounds pixels
+            // without returning an error or causing a panic.
+            if let Some((x, y)) = self.to_physical(coord) {
+                let col = x / 4;
+                if !matches!(group, Some(g) if g.col == col && g.row == y) {
+                    if let Some(g) = group {
+                        self.write_group(g)?;
+                    }
+                    group = Some(Group {
+                        col,
+                        row: y,
+                        data: [0; 2],
+                    });
+                }
+
+                if let Some(g) = group.as_mut() {
+                    let byte = &mut g.data[(x % 4) / 2];
+                    *byte = if x % 2 == 0 {
+                        update_upper_nibble(*byte, color.luma())
+                    } else {
+                        update_lower_nibble(*byte, color.luma())
+                    };
+                }
+            }
+        }
+
+        match group {
+            Some(g) => self.write_group(g),
+            None => Ok(()),
+        }
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let bottom_right = match area.bottom_right() {
+            Some(bottom_right) => bottom_right,
+            None => return Ok(()),
+        };
+
+        // Both corners are inside the display, so they always map to framebuffer coordinates.
+        let (a, b) = match (
+            self.to_physical(area.top_left),
+            self.to_physical(bottom_right),
+        ) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Ok(()),
+        };
+        let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+        let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+
+        // Pixels of the edge column addresses outside of the area are black.
+        let first = x0 / 4 * 4;
+        let luma = color.luma();
+        self.write_window([x0 / 4, x1 / 4], [y0, y1], |_, _, i| {
+            let x = first + i * 2;
+            let upper = if (x0..=x1).contains(&x) { luma } else { 0 };
+            let lower = if (x0..=x1).contains(&(x + 1)) {
+                luma
+            } else {
+                0
+            };
+            (upper << 4) | lower
+        })
+    }
+
+    fn clear(&mut self, fill: Self::Color) -> Result<(), Self::Error> {
+        let luma = fill.luma();
+        let byte = (luma << 4) | luma;
+
+        self.write_window([0, WIDTH / 4 - 1], [0, HEIGHT - 1], |_, _, _| byte)
+    }
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    #[test]
+    /// Tests that converting between modes keeps the configuration and resets the framebuffer.
+    fn mode_conversion() {
+        let config = DisplayConfig {
+            col_offset: 0x10,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let basic: Ssd1322<_, 256, 64, (), BasicMode> =
+            Ssd1322::new_basic_with_config(RecordingInterface::<256>::new(), config);
+
+        let mut buffered: Ssd1322<_> = basic.into_buffered_graphics_mode();
+        assert_eq!(buffered.config().col_offset, 0x10);
+        Pixel(Point::new(1, 1), Gray4::WHITE)
+            .draw(&mut buffered)
+            .unwrap();
+        buffered.flush().unwrap();
+        let window = buffered.display.windows().last().unwrap();
+        assert_eq!(window.data.len(), 2);
+
+        let mut direct = buffered.into_direct_mode();
+        Pixel(Point::new(1, 1), Gray4::WHITE)
+            .draw(&mut direct)
+            .unwrap();
+        assert_eq!(direct.display.windows().count(), 2);
+
+        let mut buffer = [0xff; 8192];
+        let buffered = direct
+            .into_basic_mode()
+            .into_buffered_graphics_mode_with_buffer(&mut buffer);
+        assert_eq!(buffered.config().col_offset, 0x10);
+        assert_eq!(buffered.dma_buffer()[0], 0);
+    }
+
+    #[test]
+    /// Tests that consecutive pixels of a column address are written together.
+    fn direct_draw_iter() {
+        let mut disp: Ssd1322<_, 256, 64, (), DirectMode> =
+            Ssd1322::new_direct(RecordingInterface::<256>::new());
+
+        disp.draw_iter([
+            Pixel(Point::new(4, 0), Gray4::new(0x3)),
+            Pixel(Point::new(7, 0), Gray4::WHITE),
+            Pixel(Point::new(-1, 0), Gray4::WHITE),
+        ])
+        .unwrap();
+
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(disp.display.windows().count(), 1);
+        assert_eq!(window.data, [0x30, 0x0f]);
+
+        disp.draw_iter([
+            Pixel(Point::new(0, 0), Gray4::WHITE),
+            Pixel(Point::new(0, 1), Gray4::WHITE),
+        ])
+        .unwrap();
+        assert_eq!(disp.display.windows().count(), 3);
+    }
+
+    #[test]
+    /// Tests that a filled rectangle is written as one window with black edge pixels.
+    fn direct_fill_solid() {
+        let mut disp: Ssd1322<_, 256, 64, (), DirectMode> =
+            Ssd1322::new_direct(RecordingInterface::<256>::new());
+
+        disp.fill_solid(
+            &Rectangle::new(Point::new(2, 10), Size::new(5, 2)),
+            Gray4::WHITE,
+        )
+        .unwrap();
+
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(disp.display.windows().count(), 1);
+        assert_eq!(
+            window.data,
+            [0x00, 0xff, 0xff, 0xf0, 0x00, 0xff, 0xff, 0xf0]
+        );
+
+        disp.fill_solid(
+            &Rectangle::new(Point::new(300, 10), Size::new(5, 2)),
+            Gray4::WHITE,
+        )
+        .unwrap();
+        assert_eq!(disp.display.windows().count(), 1);
+    }
+
+    #[test]
+    /// Tests that interface errors are returned by the drawing methods.
+    fn direct_bus_error() {
+        let mut disp: Ssd1322<_, 256, 64, (), DirectMode> =
+            Ssd1322::new_direct(RecordingInterface::<256>::new());
+        disp.display.set_fail(true);
+
+        assert!(matches!(
+            Pixel(Point::new(0, 0), Gray4::WHITE).draw(&mut disp),
+            Err(DisplayError::BusWriteError)
+        ));
+        assert!(matches!(
+            disp.clear(Gray4::BLACK),
+            Err(DisplayError::BusWriteError)
+        ));
+        assert_eq!(disp.display.windows().count(), 0);
+    }
+
+    #[test]
+    /// Tests that generated pixels are written to the panel without a framebuffer.
+    fn flush_iter() {
+        use crate::simulator::Ssd1322Simulator;
+
+        let mut disp: Ssd1322<_, 256, 64, (), DirectMode> =
+            Ssd1322::new_direct(Ssd1322Simulator::<256, 64>::new());
+        disp.init().unwrap();
+        disp.flush_iter(|x, y| (x + y) as u8).unwrap();
+
+        let sim = disp.release();
+        assert_eq!(sim.panel_pixel(0, 0), 0x00);
+        assert_eq!(sim.panel_pixel(5, 2), 0x07);
+        assert_eq!(sim.panel_pixel(255, 63), (255 + 63) as u8 & 0x0F);
+    }
+
+    #[test]
+    /// Tests that streamed bytes are written to their area without a framebuffer.
+    fn flush_raw() {
+        use crate::simulator::Ssd1322Simulator;
+
+        let mut disp: Ssd1322<_, 256, 64, (), DirectMode> =
+            Ssd1322::new_direct(Ssd1322Simulator::<256, 64>::new());
+        disp.init().unwrap();
+        assert!(matches!(
+            disp.flush_raw(2, 0, 4, 1, [0xFF; 2]),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert!(matches!(
+            disp.flush_raw(252, 0, 8, 1, [0xFF; 4]),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+
+        disp.flush_raw(8, 10, 4, 2, [0x12, 0x34, 0x56, 0x78, 0x9A])
+            .unwrap();
+        let sim = disp.release();
+        assert_eq!(sim.panel_pixel(8, 10), 0x01);
+        assert_eq!(sim.panel_pixel(11, 10), 0x04);
+        assert_eq!(sim.panel_pixel(11, 11), 0x08);
+        assert_eq!(sim.panel_pixel(12, 11), 0x00);
+        assert_eq!(sim.panel_pixel(8, 12), 0x00);
+    }
+}
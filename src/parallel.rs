@@ -0,0 +1,678 @@
+//! parallel interface module
+use crate::interface::ReadData;
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+use hal::digital::v2::OutputPin;
+
+/// An 8 bit wide output bus, which the parallel interfaces drive the data lines with.
+pub trait OutputBus {
+    /// Error type of the bus.
+    type Error;
+
+    /// Puts `value` on the data lines.
+    fn set_value(&mut self, value: u8) -> Result<(), Self::Error>;
+}
+
+/// An 8 bit bus that can also read the data lines, which the parallel interfaces need to read
+/// data from the controller. It is usually implemented for the GPIO port the data lines are
+/// wired to, which can switch all lines between output and input at once.
+pub trait InputOutputBus: OutputBus {
+    /// Stops driving the data lines, so the controller can drive them until the next
+    /// `set_value`.
+    fn set_input(&mut self) -> Result<(), Self::Error>;
+
+    /// Reads the value on the data lines.
+    fn get_value(&mut self) -> Result<u8, Self::Error>;
+}
+
+/// An 8 bit output bus of GPIO pins, the first pin drives D0 and the last pin D7.
+///
+/// The pins share a type, e.g. the type erased pins most HALs provide.
+#[derive(Debug)]
+pub struct GpioBus<P> {
+    pins: [P; 8],
+    /// Last value put on the bus, only pins that change are written.
+    last: Option<u8>,
+}
+
+impl<P> GpioBus<P>
+where
+    P: OutputPin,
+{
+    /// Creates a bus from the pins for D0 to D7.
+    pub fn new(pins: [P; 8]) -> Self {
+        Self { pins, last: None }
+    }
+
+    /// Returns the pins.
+    pub fn release(self) -> [P; 8] {
+        self.pins
+    }
+}
+
+impl<P> OutputBus for GpioBus<P>
+where
+    P: OutputPin,
+{
+    type Error = P::Error;
+
+    fn set_value(&mut self, value: u8) -> Result<(), Self::Error> {
+        let changed = self.last.map_or(0xFF, |last| last ^ value);
+        for (i, pin) in self.pins.iter_mut().enumerate() {
+            if changed & (1 << i) != 0 {
+                if value & (1 << i) != 0 {
+                    pin.set_high()?;
+                } else {
+                    pin.set_low()?;
+                }
+            }
+        }
+        self.last = Some(value);
+
+        Ok(())
+    }
+}
+
+/// Stands in for the RD pin of an 8080 interface that only writes, with RD tied high.
+#[derive(Debug, Clone, Copy)]
+pub struct NoRead;
+
+/// An 8080 style parallel interface, for modules with the BS pins strapped for the 8080 MCU
+/// bus: the data lines are latched on the rising edge of WR, D/C selects commands (low) or
+/// data (high).
+///
+/// The pins are bit-banged, so the bus runs as fast as the GPIO allows, which is well within
+/// the timing of the SSD1322. With an RD pin and an `InputOutputBus` the interface implements
+/// `ReadData`, the controller drives the data lines while RD is low.
+#[derive(Debug)]
+pub struct Parallel8080Interface<BUS, DC, WR, CS, RD = NoRead> {
+    bus: BUS,
+    dc: DC,
+    wr: WR,
+    cs: CS,
+    rd: RD,
+}
+
+impl<BUS, DC, WR, CS> Parallel8080Interface<BUS, DC, WR, CS>
+where
+    BUS: OutputBus,
+    DC: OutputPin,
+    WR: OutputPin,
+    CS: OutputPin,
+{
+    /// Creates the interface from the data bus and the D/C, WR and chip select pins.
+    pub fn new(bus: BUS, dc: DC, wr: WR, cs: CS) -> Self {
+        Self {
+            bus,
+            dc,
+            wr,
+            cs,
+            rd: NoRead,
+        }
+    }
+
+    /// Returns the data bus and the D/C, WR and chip select pins.
+    pub fn release(self) -> (BUS, DC, WR, CS) {
+        (self.bus, self.dc, self.wr, self.cs)
+    }
+}
+
+impl<BUS, DC, WR, CS, RD> Parallel8080Interface<BUS, DC, WR, CS, RD>
+where
+    BUS: InputOutputBus,
+    DC: OutputPin,
+    WR: OutputPin,
+    CS: OutputPin,
+    RD: OutputPin,
+{
+    /// Creates the interface from the bidirectional data bus and the D/C, WR, RD and chip
+    /// select pins.
+    pub fn new_with_rd(bus: BUS, dc: DC, wr: WR, rd: RD, cs: CS) -> Self {
+        Self {
+            bus,
+            dc,
+            wr,
+            cs,
+            rd,
+        }
+    }
+
+    /// Returns the data bus and the D/C, WR, RD and chip select pins.
+    pub fn release_with_rd(self) -> (BUS, DC, WR, RD, CS) {
+        (self.bus, self.dc, self.wr, self.rd, self.cs)
+    }
+}
+
+impl<BUS, DC, WR, CS, RD> Parallel8080Interface<BUS, DC, WR, CS, RD>
+where
+    BUS: OutputBus,
+    DC: OutputPin,
+    WR: OutputPin,
+    CS: OutputPin,
+{
+    fn send(&mut self, data: bool, words: DataFormat<'_>) -> Result<(), DisplayError> {
+        let level = if data {
+            self.dc.set_high()
+        } else {
+            self.dc.set_low()
+        };
+        level.map_err(|_| DisplayError::DCError)?;
+
+        self.cs.set_low().map_err(|_| DisplayError::CSError)?;
+        let result = match words {
+            DataFormat::U8(slice) => self.write_all(slice.iter().copied()),
+            DataFormat::U8Iter(iter) => self.write_all(iter),
+            DataFormat::U16BE(words) => self.write_all(words.iter().flat_map(|w| w.to_be_bytes())),
+            // The SSD1322 only takes bytes
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        };
+        self.cs.set_high().map_err(|_| DisplayError::CSError)?;
+
+        result
+    }
+
+    fn write_all<I>(&mut self, bytes: I) -> Result<(), DisplayError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        for byte in bytes {
+            self.wr.set_low().map_err(|_| DisplayError::BusWriteError)?;
+            self.bus
+                .set_value(byte)
+                .map_err(|_| DisplayError::BusWriteError)?;
+            self.wr
+                .set_high()
+                .map_err(|_| DisplayError::BusWriteError)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<BUS, DC, WR, CS, RD> WriteOnlyDataCommand for Parallel8080Interface<BUS, DC, WR, CS, RD>
+where
+    BUS: OutputBus,
+    DC: OutputPin,
+    WR: OutputPin,
+    CS: OutputPin,
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(false, cmds)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(true, buf)
+    }
+}
+
+impl<BUS, DC, WR, CS, RD> ReadData for Parallel8080Interface<BUS, DC, WR, CS, RD>
+where
+    BUS: InputOutputBus,
+    DC: OutputPin,
+    WR: OutputPin,
+    CS: OutputPin,
+    RD: OutputPin,
+{
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        self.read(true, buf)
+    }
+
+    fn read_status(&mut self) -> Result<u8, DisplayError> {
+        let mut status = [0];
+        self.read(false, &mut status)?;
+
+        Ok(status[0])
+    }
+}
+
+impl<BUS, DC, WR, CS, RD> Parallel8080Interface<BUS, DC, WR, CS, RD>
+where
+    BUS: InputOutputBus,
+    DC: OutputPin,
+    CS: OutputPin,
+    RD: OutputPin,
+{
+    fn read(&mut self, data: bool, buf: &mut [u8]) -> Result<(), DisplayError> {
+        let level = if data {
+            self.dc.set_high()
+        } else {
+            self.dc.set_low()
+        };
+        level.map_err(|_| DisplayError::DCError)?;
+        self.bus
+            .set_input()
+            .map_err(|_| DisplayError::BusWriteError)?;
+
+        self.cs.set_low().map_err(|_| DisplayError::CSError)?;
+        let result = buf.iter_mut().try_for_each(|byte| {
+            self.rd.set_low().map_err(|_| DisplayError::BusWriteError)?;
+            *byte = self
+                .bus
+                .get_value()
+                .map_err(|_| DisplayError::BusWriteError)?;
+            self.rd.set_high().map_err(|_| DisplayError::BusWriteError)
+        });
+        self.cs.set_high().map_err(|_| DisplayError::CSError)?;
+
+        result
+    }
+}
+
+/// A 6800 style parallel interface, for modules with the BS pins strapped for the 6800 MCU
+/// bus: R/W is held low for writes and the data lines are latched on the falling edge of E, D/C
+/// selects commands (low) or data (high).
+///
+/// The pins are bit-banged like those of `Parallel8080Interface`. With an `InputOutputBus` the
+/// interface implements `ReadData`, R/W is held high and the controller drives the data lines
+/// while E is high.
+#[derive(Debug)]
+pub struct Parallel6800Interface<BUS, DC, RW, E, CS> {
+    bus: BUS,
+    dc: DC,
+    rw: RW,
+    e: E,
+    cs: CS,
+}
+
+impl<BUS, DC, RW, E, CS> Parallel6800Interface<BUS, DC, RW, E, CS>
+where
+    BUS: OutputBus,
+    DC: OutputPin,
+    RW: OutputPin,
+    E: OutputPin,
+    CS: OutputPin,
+{
+    /// Creates the interface from the data bus and the D/C, R/W, E and chip select pins.
+    pub fn new(bus: BUS, dc: DC, rw: RW, e: E, cs: CS) -> Self {
+        Self { bus, dc, rw, e, cs }
+    }
+
+    /// Returns the data bus and the D/C, R/W, E and chip select pins.
+    pub fn release(self) -> (BUS, DC, RW, E, CS) {
+        (self.bus, self.dc, self.rw, self.e, self.cs)
+    }
+
+    fn send(&mut self, data: bool, words: DataFormat<'_>) -> Result<(), DisplayError> {
+        let level = if data {
+            self.dc.set_high()
+        } else {
+            self.dc.set_low()
+        };
+        level.map_err(|_| DisplayError::DCError)?;
+        self.rw.set_low().map_err(|_| DisplayError::BusWriteError)?;
+
+        self.cs.set_low().map_err(|_| DisplayError::CSError)?;
+        let result = match words {
+            DataFormat::U8(slice) => self.write_all(slice.iter().copied()),
+            DataFormat::U8Iter(iter) => self.write_all(iter),
+            DataFormat::U16BE(words) => self.write_all(words.iter().flat_map(|w| w.to_be_bytes())),
+            // The SSD1322 only takes bytes
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        };
+        self.cs.set_high().map_err(|_| DisplayError::CSError)?;
+
+        result
+    }
+
+    fn write_all<I>(&mut self, bytes: I) -> Result<(), DisplayError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        for byte in bytes {
+            self.bus
+                .set_value(byte)
+                .map_err(|_| DisplayError::BusWriteError)?;
+            self.e.set_high().map_err(|_| DisplayError::BusWriteError)?;
+            self.e.set_low().map_err(|_| DisplayError::BusWriteError)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<BUS, DC, RW, E, CS> WriteOnlyDataCommand for Parallel6800Interface<BUS, DC, RW, E, CS>
+where
+    BUS: OutputBus,
+    DC: OutputPin,
+    RW: OutputPin,
+    E: OutputPin,
+    CS: OutputPin,
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(false, cmds)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.send(true, buf)
+    }
+}
+
+impl<BUS, DC, RW, E, CS> ReadData for Parallel6800Interface<BUS, DC, RW, E, CS>
+where
+    BUS: InputOutputBus,
+    DC: OutputPin,
+    RW: OutputPin,
+    E: OutputPin,
+    CS: OutputPin,
+{
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        self.read(true, buf)
+    }
+
+    fn read_status(&mut self) -> Result<u8, DisplayError> {
+        let mut status = [0];
+        self.read(false, &mut status)?;
+
+        Ok(status[0])
+    }
+}
+
+impl<BUS, DC, RW, E, CS> Parallel6800Interface<BUS, DC, RW, E, CS>
+where
+    BUS: InputOutputBus,
+    DC: OutputPin,
+    RW: OutputPin,
+    E: OutputPin,
+    CS: OutputPin,
+{
+    fn read(&mut self, data: bool, buf: &mut [u8]) -> Result<(), DisplayError> {
+        let level = if data {
+            self.dc.set_high()
+        } else {
+            self.dc.set_low()
+        };
+        level.map_err(|_| DisplayError::DCError)?;
+        self.bus
+            .set_input()
+            .map_err(|_| DisplayError::BusWriteError)?;
+        self.rw
+            .set_high()
+            .map_err(|_| DisplayError::BusWriteError)?;
+
+        self.cs.set_low().map_err(|_| DisplayError::CSError)?;
+        let result = buf.iter_mut().try_for_each(|byte| {
+            self.e.set_high().map_err(|_| DisplayError::BusWriteError)?;
+            *byte = self
+                .bus
+                .get_value()
+                .map_err(|_| DisplayError::BusWriteError)?;
+            self.e.set_low().map_err(|_| DisplayError::BusWriteError)
+        });
+        self.cs.set_high().map_err(|_| DisplayError::CSError)?;
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use display_interface::DataFormat::{U8Iter, U8};
+
+    /// The state of the data lines and control pins, and the bytes latched by the controller.
+    #[derive(Default)]
+    struct Lines {
+        data: Cell<u8>,
+        dc: Cell<bool>,
+        rw: Cell<bool>,
+        strobe: Cell<bool>,
+        cs: Cell<bool>,
+        latched: Cell<[(bool, u8); 8]>,
+        len: Cell<usize>,
+        /// Bytes the controller drives onto the data lines when it is read.
+        output: Cell<[u8; 4]>,
+        read_len: Cell<usize>,
+    }
+
+    impl Lines {
+        fn latch(&self) {
+            let mut latched = self.latched.get();
+            latched[self.len.get()] = (self.dc.get(), self.data.get());
+            self.latched.set(latched);
+            self.len.set(self.len.get() + 1);
+        }
+
+        fn drive(&self) {
+            if !self.cs.get() {
+                self.data.set(self.output.get()[self.read_len.get()]);
+                self.read_len.set(self.read_len.get() + 1);
+            }
+        }
+    }
+
+    /// A GPIO pin driving data line `bit`.
+    struct DataPin<'a>(&'a Lines, u8);
+
+    impl OutputPin for DataPin<'_> {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), ()> {
+            self.0.data.set(self.0.data.get() & !(1 << self.1));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), ()> {
+            self.0.data.set(self.0.data.get() | (1 << self.1));
+            Ok(())
+        }
+    }
+
+    /// A bidirectional bus of the data lines.
+    struct TestBus<'a>(&'a Lines);
+
+    impl OutputBus for TestBus<'_> {
+        type Error = ();
+
+        fn set_value(&mut self, value: u8) -> Result<(), ()> {
+            self.0.data.set(value);
+            Ok(())
+        }
+    }
+
+    impl InputOutputBus for TestBus<'_> {
+        fn set_input(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn get_value(&mut self) -> Result<u8, ()> {
+            Ok(self.0.data.get())
+        }
+    }
+
+    /// A control pin, the WR pin latches on its rising edge and the E pin on its falling edge
+    /// while the chip is selected. The controller drives the data lines on the falling edge of
+    /// RD and on the rising edge of E with R/W high.
+    enum ControlPin<'a> {
+        Dc(&'a Lines),
+        Wr(&'a Lines),
+        Rd(&'a Lines),
+        Rw(&'a Lines),
+        E(&'a Lines),
+        Cs(&'a Lines),
+    }
+
+    impl OutputPin for ControlPin<'_> {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), ()> {
+            match self {
+                ControlPin::Dc(lines) => lines.dc.set(false),
+                ControlPin::Wr(lines) => lines.strobe.set(false),
+                ControlPin::Rd(lines) => lines.drive(),
+                ControlPin::Rw(lines) => lines.rw.set(false),
+                ControlPin::E(lines) => {
+                    if lines.strobe.get() && !lines.cs.get() && !lines.rw.get() {
+                        lines.latch();
+                    }
+                    lines.strobe.set(false);
+                }
+                ControlPin::Cs(lines) => lines.cs.set(false),
+            }
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), ()> {
+            match self {
+                ControlPin::Dc(lines) => lines.dc.set(true),
+                ControlPin::Wr(lines) => {
+                    if !lines.strobe.get() && !lines.cs.get() {
+                        lines.latch();
+                    }
+                    lines.strobe.set(true);
+                }
+                ControlPin::Rd(_) => {}
+                ControlPin::Rw(lines) => lines.rw.set(true),
+                ControlPin::E(lines) => {
+                    if lines.rw.get() {
+                        lines.drive();
+                    }
+                    lines.strobe.set(true);
+                }
+                ControlPin::Cs(lines) => lines.cs.set(true),
+            }
+            Ok(())
+        }
+    }
+
+    fn bus(lines: &Lines) -> GpioBus<DataPin<'_>> {
+        GpioBus::new([0, 1, 2, 3, 4, 5, 6, 7].map(|bit| DataPin(lines, bit)))
+    }
+
+    #[test]
+    /// Tests that bytes are latched on the rising edge of WR with the D/C level.
+    fn parallel_8080() {
+        let lines = Lines::default();
+        let mut interface = Parallel8080Interface::new(
+            bus(&lines),
+            ControlPin::Dc(&lines),
+            ControlPin::Wr(&lines),
+            ControlPin::Cs(&lines),
+        );
+
+        interface.send_commands(U8(&[0x15])).unwrap();
+        interface
+            .send_data(U8Iter(&mut [0x1C, 0x5B, 0x5B].iter().copied()))
+            .unwrap();
+
+        assert!(lines.cs.get());
+        assert_eq!(lines.len.get(), 4);
+        assert_eq!(
+            lines.latched.get()[..4],
+            [(false, 0x15), (true, 0x1C), (true, 0x5B), (true, 0x5B)]
+        );
+    }
+
+    #[test]
+    /// Tests that bytes are latched on the falling edge of E with the D/C level.
+    fn parallel_6800() {
+        let lines = Lines::default();
+        lines.rw.set(true);
+        let mut interface = Parallel6800Interface::new(
+            bus(&lines),
+            ControlPin::Dc(&lines),
+            ControlPin::Rw(&lines),
+            ControlPin::E(&lines),
+            ControlPin::Cs(&lines),
+        );
+
+        interface.send_commands(U8(&[0x15])).unwrap();
+        interface
+            .send_data(U8Iter(&mut [0x1C, 0x5B, 0x5B].iter().copied()))
+            .unwrap();
+
+        assert!(lines.cs.get());
+        assert!(!lines.strobe.get());
+        assert_eq!(lines.len.get(), 4);
+        assert_eq!(
+            lines.latched.get()[..4],
+            [(false, 0x15), (true, 0x1C), (true, 0x5B), (true, 0x5B)]
+        );
+    }
+
+    #[test]
+    /// Tests reading the GDDRAM over the 8080 bus, after the command and the dummy read.
+    fn read_ram() {
+        use crate::display::Ssd1322;
+
+        let lines = Lines::default();
+        lines.output.set([0xFF, 0x12, 0x34, 0x00]);
+        let interface = Parallel8080Interface::new_with_rd(
+            TestBus(&lines),
+            ControlPin::Dc(&lines),
+            ControlPin::Wr(&lines),
+            ControlPin::Rd(&lines),
+            ControlPin::Cs(&lines),
+        );
+        let mut disp: Ssd1322<_> = Ssd1322::new(interface);
+
+        let mut buf = [0; 2];
+        disp.read_ram(9, 2, &mut buf).unwrap();
+        assert_eq!(buf, [0x12, 0x34]);
+        assert_eq!(lines.read_len.get(), 3);
+        assert_eq!(
+            lines.latched.get()[..lines.len.get()],
+            [
+                (false, 0x15),
+                (true, 0x1E),
+                (true, 0x5B),
+                (false, 0x75),
+                (true, 0x02),
+                (true, 0x3F),
+                (false, 0x5D),
+            ]
+        );
+
+        assert!(matches!(
+            disp.read_ram(256, 0, &mut buf),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+    }
+
+    #[test]
+    /// Tests that bytes are read while E is high with R/W high.
+    fn parallel_6800_read() {
+        let lines = Lines::default();
+        lines.output.set([0x12, 0x34, 0x00, 0x00]);
+        let mut interface = Parallel6800Interface::new(
+            TestBus(&lines),
+            ControlPin::Dc(&lines),
+            ControlPin::Rw(&lines),
+            ControlPin::E(&lines),
+            ControlPin::Cs(&lines),
+        );
+
+        let mut buf = [0; 2];
+        interface.read_data(&mut buf).unwrap();
+        assert_eq!(buf, [0x12, 0x34]);
+        assert!(lines.rw.get() && lines.dc.get() && lines.cs.get());
+        // Reading doesn't latch bytes.
+        assert_eq!(lines.len.get(), 0);
+    }
+
+    #[test]
+    /// Tests reading the status register with D/C low.
+    fn read_status() {
+        use crate::display::{Ssd1322, Status};
+
+        let lines = Lines::default();
+        lines.output.set([0x40, 0x00, 0x00, 0x00]);
+        let interface = Parallel6800Interface::new(
+            TestBus(&lines),
+            ControlPin::Dc(&lines),
+            ControlPin::Rw(&lines),
+            ControlPin::E(&lines),
+            ControlPin::Cs(&lines),
+        );
+        let mut disp: Ssd1322<_> = Ssd1322::new(interface);
+
+        assert_eq!(
+            disp.read_status().unwrap(),
+            Status {
+                busy: false,
+                display_off: true,
+                raw: 0x40,
+            }
+        );
+        assert!(!lines.dc.get());
+    }
+}
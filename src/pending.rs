@@ -0,0 +1,144 @@
+//! pending updates module
+use crate::display::Ssd1322;
+use display_interface::WriteOnlyDataCommand;
+use embedded_graphics::{
+    geometry::Point, pixelcolor::Gray4, primitives::Rectangle, Drawable, Pixel,
+};
+
+/// A change of the framebuffer queued in `PendingUpdates`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    /// Marks the area as changed, like `Ssd1322::mark_dirty`.
+    Dirty(Rectangle),
+    /// Fills the area with the color, like `Ssd1322::clear_region`.
+    Fill(Rectangle, Gray4),
+    /// Draws the pixel with the color, combined by the draw mode of the display.
+    Pixel(Point, Gray4),
+}
+
+/// A fixed size queue of framebuffer changes, so interrupt handlers can report changes or
+/// queue small draws while the main loop owns the display and flushes it.
+///
+/// The queue has no locking of its own. It is shared in a mutex of the firmware, e.g. a
+/// `critical_section::Mutex<RefCell<PendingUpdates<N>>>`, and the main loop moves the updates
+/// out with `take` and applies them outside of the critical section, so the interrupts are
+/// never blocked by drawing or the bus.
+///
+/// ```ignore
+/// static PENDING: Mutex<RefCell<PendingUpdates<8>>> =
+///     Mutex::new(RefCell::new(PendingUpdates::new()));
+///
+/// #[interrupt]
+/// fn EXTI0() {
+///     critical_section::with(|cs| {
+///         let _ = PENDING.borrow_ref_mut(cs).push(Update::Fill(LED_AREA, Gray4::WHITE));
+///     });
+/// }
+///
+/// loop {
+///     let mut updates = critical_section::with(|cs| PENDING.borrow_ref_mut(cs).take());
+///     updates.apply(&mut disp);
+///     disp.flush()?;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingUpdates<const N: usize> {
+    updates: [Option<Update>; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for PendingUpdates<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> PendingUpdates<N> {
+    /// Creates an empty queue.
+    pub const fn new() -> Self {
+        Self {
+            updates: [None; N],
+            len: 0,
+        }
+    }
+
+    /// Returns the number of queued updates.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no update is queued.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Queues `update`, or returns it if the queue is full.
+    pub fn push(&mut self, update: Update) -> Result<(), Update> {
+        match self.updates.get_mut(self.len) {
+            Some(slot) => {
+                *slot = Some(update);
+                self.len += 1;
+                Ok(())
+            }
+            None => Err(update),
+        }
+    }
+
+    /// Moves the queued updates out and leaves the queue empty.
+    pub fn take(&mut self) -> Self {
+        core::mem::take(self)
+    }
+
+    /// Applies the queued updates to the framebuffer of `disp` in the order they were queued
+    /// and empties the queue. The display is flushed by the caller.
+    pub fn apply<DI, const WIDTH: usize, const HEIGHT: usize, B>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B>,
+    ) where
+        DI: WriteOnlyDataCommand,
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        for update in self.updates[..self.len].iter_mut().filter_map(Option::take) {
+            match update {
+                Update::Dirty(area) => disp.mark_dirty(area),
+                Update::Fill(area, color) => disp.clear_region(area, color),
+                Update::Pixel(point, color) => {
+                    let Ok(()) = Pixel(point, color).draw(disp);
+                }
+            }
+        }
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+    use embedded_graphics::{geometry::Size, pixelcolor::GrayColor};
+
+    #[test]
+    /// Tests that queued updates are applied in order and a full queue rejects updates.
+    fn pending_updates() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let mut pending = PendingUpdates::<2>::new();
+        let area = Rectangle::new(Point::new(8, 2), Size::new(4, 2));
+        pending.push(Update::Fill(area, Gray4::WHITE)).unwrap();
+        pending
+            .push(Update::Pixel(Point::new(9, 2), Gray4::BLACK))
+            .unwrap();
+        let rejected = Update::Dirty(area);
+        assert_eq!(pending.push(rejected), Err(rejected));
+
+        let mut updates = pending.take();
+        assert!(pending.is_empty());
+        assert_eq!(updates.len(), 2);
+        updates.apply(&mut disp);
+
+        assert!(updates.is_empty());
+        assert_eq!(disp.get_pixel(Point::new(8, 2)), Some(Gray4::WHITE));
+        assert_eq!(disp.get_pixel(Point::new(9, 2)), Some(Gray4::BLACK));
+        assert_eq!(disp.get_pixel(Point::new(11, 3)), Some(Gray4::WHITE));
+        assert_eq!(disp.dirty_area(), Some(area));
+    }
+}
@@ -0,0 +1,166 @@
+//! panel power sequencing module
+use crate::command::Command;
+use crate::display::Ssd1322;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+use hal::blocking::delay::DelayMs;
+use hal::digital::v2::OutputPin;
+
+/// Time for the logic supply to settle before the reset, in milliseconds.
+const VDD_SETTLE_MS: u8 = 1;
+
+/// Time for the panel supply to settle before the display is turned on, in milliseconds.
+const VCC_SETTLE_MS: u8 = 100;
+
+/// Time for the panel supply to discharge before the logic supply is turned off, in
+/// milliseconds (tOFF in the datasheet).
+const VCC_OFF_MS: u8 = 100;
+
+/// The enable pins of the logic (VDD/VCI) and panel (VCC) supplies of a module whose rails are
+/// switched by the host, both active high.
+///
+/// Turning the panel supply on before the controller is initialized, or the logic supply off
+/// while the panel supply is still on, can damage the OLED. `power_up` and `power_down` follow
+/// the sequence of the datasheet:
+///
+/// - power up: enable VDD, reset, `init_display_off`, enable VCC, turn the display on
+/// - power down: turn the display off, disable VCC, wait for it to discharge, disable VDD
+///
+/// ```ignore
+/// let mut power = PowerPins { vdd_en, vcc_en };
+/// power.power_up(&mut disp, &mut rst, &mut delay)?;
+/// disp.flush_all()?;
+/// // ...
+/// power.power_down(&mut disp, &mut delay)?;
+/// ```
+#[derive(Debug)]
+pub struct PowerPins<VDD, VCC> {
+    /// Enables the logic supply.
+    pub vdd_en: VDD,
+    /// Enables the panel supply.
+    pub vcc_en: VCC,
+}
+
+impl<VDD, VCC> PowerPins<VDD, VCC>
+where
+    VDD: OutputPin,
+    VCC: OutputPin,
+{
+    /// Powers the panel up: enables VDD, resets and initializes `disp`, then enables VCC and
+    /// turns the display on. The display is initialized like `init`, with its errors.
+    pub fn power_up<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE, RST, DELAY>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>,
+        rst: &mut RST,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+    {
+        self.vdd_en
+            .set_high()
+            .map_err(|_| DisplayError::BusWriteError)?;
+        delay.delay_ms(VDD_SETTLE_MS);
+        disp.reset(rst, delay)?;
+
+        // The display is only turned on once VCC is enabled.
+        disp.init_display_off()?;
+
+        self.vcc_en
+            .set_high()
+            .map_err(|_| DisplayError::BusWriteError)?;
+        delay.delay_ms(VCC_SETTLE_MS);
+        disp.send_command(Command::DisplayOn)
+    }
+
+    /// Powers the panel down: turns the display off, disables VCC and then VDD once VCC has
+    /// discharged. The GDDRAM content is lost, so the panel needs a `power_up` and a full flush
+    /// afterwards.
+    pub fn power_down<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE, DELAY>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+        DELAY: DelayMs<u8>,
+    {
+        disp.send_command(Command::DisplayOff)?;
+
+        self.vcc_en
+            .set_low()
+            .map_err(|_| DisplayError::BusWriteError)?;
+        delay.delay_ms(VCC_OFF_MS);
+        self.vdd_en
+            .set_low()
+            .map_err(|_| DisplayError::BusWriteError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    /// Records the time of the last change and the level of the pin.
+    struct Pin<'a>(&'a Cell<u32>, u32, bool);
+
+    impl OutputPin for Pin<'_> {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            (self.1, self.2) = (self.0.get(), false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            (self.1, self.2) = (self.0.get(), true);
+            Ok(())
+        }
+    }
+
+    struct Delay<'a>(&'a Cell<u32>);
+
+    impl DelayMs<u8> for Delay<'_> {
+        fn delay_ms(&mut self, ms: u8) {
+            self.0.set(self.0.get() + u32::from(ms));
+        }
+    }
+
+    #[test]
+    /// Tests that the supplies are switched in the order of the datasheet.
+    fn power_sequence() {
+        let time = Cell::new(0);
+        let mut power = PowerPins {
+            vdd_en: Pin(&time, 0, false),
+            vcc_en: Pin(&time, 0, false),
+        };
+        let mut rst = Pin(&time, 0, false);
+        let mut delay = Delay(&time);
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<128>::new());
+
+        power.power_up(&mut disp, &mut rst, &mut delay).unwrap();
+        assert_eq!((power.vdd_en.1, power.vdd_en.2), (0, true));
+        assert_eq!((rst.1, rst.2), (11, true));
+        assert_eq!((power.vcc_en.1, power.vcc_en.2), (211, true));
+        assert_eq!(time.get(), 311);
+
+        power.power_down(&mut disp, &mut delay).unwrap();
+        assert_eq!((power.vcc_en.1, power.vcc_en.2), (311, false));
+        assert_eq!((power.vdd_en.1, power.vdd_en.2), (411, false));
+
+        // The display is turned on (AFh) once, after VCC was enabled.
+        let iface = disp.release();
+        let commands: [u8; 3] = [0xA6, 0xAF, 0xAE];
+        let len = iface.commands().count();
+        assert!(iface.commands().skip(len - 3).eq(commands));
+        assert_eq!(
+            iface.commands().filter(|&command| command == 0xAF).count(),
+            1
+        );
+        assert!(!iface.is_overflowed());
+    }
+}
@@ -0,0 +1,184 @@
+//! screensaver module
+use crate::command::Command;
+use crate::display::Ssd1322;
+use crate::mode::BufferedGraphicsMode;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+
+/// The state of the panel managed by a `Screensaver`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenState {
+    /// The panel is shown with the configured contrast.
+    Active,
+    /// The panel is dimmed to the contrast of `Screensaver::dim_contrast`.
+    Dimmed,
+    /// The panel is asleep.
+    Asleep,
+}
+
+/// Dims and then puts the panel to sleep after a time without drawing, to extend the lifetime
+/// of the OLED in appliances, and brings it back on the next draw or `touch`.
+///
+/// `update` is called from the main loop with a millisecond timestamp, e.g. of a monotonic
+/// timer, which may wrap around. Changes drawn to the framebuffer and flushes count as
+/// activity. The dimming doesn't change the configured contrast, so the panel comes back with
+/// the brightness it had.
+///
+/// ```ignore
+/// let mut saver = Screensaver::new(30_000, 120_000);
+/// loop {
+///     if button.is_pressed() {
+///         saver.touch(&mut disp, timer.millis())?;
+///     }
+///     draw_ui(&mut disp)?;
+///     saver.update(&mut disp, timer.millis())?;
+///     disp.flush()?;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Screensaver {
+    dim_after: u32,
+    sleep_after: u32,
+    dim_contrast: u8,
+    last_activity: Option<u32>,
+    windows: u32,
+    state: ScreenState,
+}
+
+impl Screensaver {
+    /// Creates a screensaver that dims the panel `dim_after` milliseconds after the last
+    /// activity and puts it to sleep after `sleep_after` milliseconds.
+    pub const fn new(dim_after: u32, sleep_after: u32) -> Self {
+        Self {
+            dim_after,
+            sleep_after,
+            dim_contrast: 0x10,
+            last_activity: None,
+            windows: 0,
+            state: ScreenState::Active,
+        }
+    }
+
+    /// Sets the contrast current of the dimmed panel, 0x10 by default.
+    pub const fn dim_contrast(self, dim_contrast: u8) -> Self {
+        Self {
+            dim_contrast,
+            ..self
+        }
+    }
+
+    /// Returns the state of the panel.
+    pub const fn state(&self) -> ScreenState {
+        self.state
+    }
+
+    /// Records activity at `now`, e.g. a button press, and brings the panel back if it was
+    /// dimmed or asleep.
+    pub fn touch<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>,
+        now: u32,
+    ) -> Result<(), DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+    {
+        self.last_activity = Some(now);
+        match self.state {
+            ScreenState::Active => return Ok(()),
+            ScreenState::Dimmed => {
+                disp.send_command(Command::SetContrastCurrent(disp.config().contrast))?
+            }
+            ScreenState::Asleep => disp.wake()?,
+        }
+        self.state = ScreenState::Active;
+
+        Ok(())
+    }
+
+    /// Dims or puts the panel to sleep once the times have passed since the last activity at
+    /// `now`, or brings it back if something was drawn or flushed since the last call.
+    ///
+    /// Returns the state of the panel.
+    pub fn update<DI, const WIDTH: usize, const HEIGHT: usize, B, const TRACKED: bool>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, BufferedGraphicsMode<TRACKED>>,
+        now: u32,
+    ) -> Result<ScreenState, DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let windows = disp.stats().windows;
+        let last_activity = match self.last_activity {
+            Some(last_activity) if disp.dirty.is_empty() && windows == self.windows => {
+                last_activity
+            }
+            _ => {
+                self.windows = windows;
+                self.touch(disp, now)?;
+                return Ok(self.state);
+            }
+        };
+
+        let idle = now.wrapping_sub(last_activity);
+        if idle >= self.sleep_after && self.state != ScreenState::Asleep {
+            disp.sleep()?;
+            self.state = ScreenState::Asleep;
+        } else if idle >= self.dim_after && self.state == ScreenState::Active {
+            disp.send_command(Command::SetContrastCurrent(self.dim_contrast))?;
+            self.state = ScreenState::Dimmed;
+        }
+
+        Ok(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    #[test]
+    /// Tests that the panel is dimmed, put to sleep and brought back by drawing.
+    fn screensaver() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let mut saver = Screensaver::new(100, 300).dim_contrast(0x08);
+        assert_eq!(saver.update(&mut disp, 0).unwrap(), ScreenState::Active);
+        assert_eq!(saver.update(&mut disp, 99).unwrap(), ScreenState::Active);
+        assert_eq!(saver.update(&mut disp, 100).unwrap(), ScreenState::Dimmed);
+        assert_eq!(saver.update(&mut disp, 200).unwrap(), ScreenState::Dimmed);
+        assert_eq!(saver.update(&mut disp, 300).unwrap(), ScreenState::Asleep);
+
+        disp.set_pixel(0, 0, 0x0F);
+        disp.flush().unwrap();
+        assert_eq!(saver.update(&mut disp, 310).unwrap(), ScreenState::Active);
+        assert_eq!(saver.update(&mut disp, 409).unwrap(), ScreenState::Active);
+        assert_eq!(saver.update(&mut disp, 410).unwrap(), ScreenState::Dimmed);
+        saver.touch(&mut disp, 420).unwrap();
+        assert_eq!(saver.state(), ScreenState::Active);
+        assert_eq!(disp.config().contrast, 0xCF);
+
+        let iface = disp.release();
+        assert!(iface.has_command(&[0xC1, 0x08]));
+        assert!(iface.has_command(&[0xAE]));
+        assert!(iface.has_command(&[0xC1, 0xCF]));
+    }
+
+    #[test]
+    /// Tests that drawing in untracked mode counts as activity, before and after a flush.
+    fn screensaver_untracked() {
+        use crate::mode::UntrackedMode;
+
+        let mut disp: Ssd1322<_, 256, 64, [u8; 8192], UntrackedMode> =
+            Ssd1322::new_untracked(RecordingInterface::<64>::new());
+        let mut saver = Screensaver::new(100, 300);
+        assert_eq!(saver.update(&mut disp, 0).unwrap(), ScreenState::Active);
+        assert_eq!(saver.update(&mut disp, 100).unwrap(), ScreenState::Dimmed);
+
+        disp.set_pixel(0, 0, 0x0F);
+        assert_eq!(saver.update(&mut disp, 150).unwrap(), ScreenState::Active);
+        disp.flush().unwrap();
+        assert_eq!(saver.update(&mut disp, 200).unwrap(), ScreenState::Active);
+        assert_eq!(saver.update(&mut disp, 299).unwrap(), ScreenState::Active);
+        assert_eq!(saver.update(&mut disp, 300).unwrap(), ScreenState::Dimmed);
+    }
+}
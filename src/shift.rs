@@ -0,0 +1,130 @@
+//! pixel shift module
+use crate::command::Command;
+use crate::display::Ssd1322;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+
+/// The vertical offsets cycled through, in rows.
+const SHIFTS: [i8; 4] = [0, 1, 0, -1];
+
+/// Number of GDDRAM rows.
+const GDDRAM_ROWS: i16 = 128;
+
+/// Moves the displayed image up and down by a row at a fixed interval, to spread the wear of
+/// the OLED pixels on always-on status displays and reduce burn-in.
+///
+/// The image is moved with the display offset (command A2h), so it is transparent to the
+/// application: nothing is redrawn or flushed, and the configured `row_offset` is unchanged.
+/// The row moving in at the edge shows the GDDRAM row next to the framebuffer, which is blank
+/// unless it was written, e.g. by `flush_page_flip`. The column address moves in steps of 4
+/// pixels, so the image isn't shifted horizontally.
+///
+/// ```ignore
+/// let mut shift = PixelShift::new(60_000);
+/// loop {
+///     shift.update(&mut disp, timer.millis())?;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelShift {
+    interval: u32,
+    last: Option<u32>,
+    step: usize,
+}
+
+impl PixelShift {
+    /// Creates a pixel shift that moves the image every `interval` milliseconds.
+    pub const fn new(interval: u32) -> Self {
+        Self {
+            interval,
+            last: None,
+            step: 0,
+        }
+    }
+
+    /// Returns the current offset of the image, in rows, positive for down.
+    pub const fn offset(&self) -> i8 {
+        SHIFTS[self.step]
+    }
+
+    /// Moves the image to the next offset if the interval has passed since the last move.
+    /// `now` is a millisecond timestamp, e.g. of a monotonic timer, which may wrap around.
+    ///
+    /// Returns the offset of the image.
+    pub fn update<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>,
+        now: u32,
+    ) -> Result<i8, DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+    {
+        match self.last {
+            None => self.last = Some(now),
+            Some(last) if now.wrapping_sub(last) >= self.interval => {
+                let step = (self.step + 1) % SHIFTS.len();
+                Self::apply(disp, SHIFTS[step])?;
+                self.step = step;
+                self.last = Some(now);
+            }
+            Some(_) => {}
+        }
+
+        Ok(self.offset())
+    }
+
+    /// Moves the image back to its configured position.
+    pub fn reset<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE>(
+        &mut self,
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>,
+    ) -> Result<(), DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+    {
+        Self::apply(disp, 0)?;
+        self.step = 0;
+        self.last = None;
+
+        Ok(())
+    }
+
+    /// Sets the display offset to the configured row offset moved by `offset` rows.
+    fn apply<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE>(
+        disp: &mut Ssd1322<DI, WIDTH, HEIGHT, B, MODE>,
+        offset: i8,
+    ) -> Result<(), DisplayError>
+    where
+        DI: WriteOnlyDataCommand,
+    {
+        // The display offset moves the image up, so a positive offset is subtracted.
+        let row = (i16::from(disp.config().row_offset) - i16::from(offset)).rem_euclid(GDDRAM_ROWS);
+        disp.send_command(Command::SetDisplayOffset(row as u8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    #[test]
+    /// Tests that the display offset cycles at the interval and is reset.
+    fn pixel_shift() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let mut shift = PixelShift::new(1000);
+        assert_eq!(shift.update(&mut disp, 0).unwrap(), 0);
+        assert_eq!(shift.update(&mut disp, 999).unwrap(), 0);
+        assert_eq!(shift.update(&mut disp, 1000).unwrap(), 1);
+        assert_eq!(shift.update(&mut disp, 2000).unwrap(), 0);
+        assert_eq!(shift.update(&mut disp, 3000).unwrap(), -1);
+        assert_eq!(shift.update(&mut disp, 4000).unwrap(), 0);
+        shift.update(&mut disp, 5000).unwrap();
+        shift.reset(&mut disp).unwrap();
+        assert_eq!(shift.offset(), 0);
+
+        let iface = disp.release();
+        let bytes: [u8; 12] = [
+            0xA2, 0x7F, 0xA2, 0x00, 0xA2, 0x01, 0xA2, 0x00, 0xA2, 0x7F, 0xA2, 0x00,
+        ];
+        assert!(iface.sent().map(|(_, byte)| byte).eq(bytes));
+    }
+}
@@ -0,0 +1,369 @@
+//! controller simulator module
+use crate::interface::ReadData;
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+#[cfg(feature = "graphics")]
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Point, Size},
+    pixelcolor::Gray4,
+    Pixel,
+};
+
+/// Number of GDDRAM column addresses, each covering 4 pixels.
+const COLUMNS: usize = 120;
+/// Number of GDDRAM rows.
+const ROWS: usize = 128;
+
+/// Simulates an SSD1322 and its GDDRAM in memory, to develop and test UIs on a desktop with
+/// the driver API unchanged. It is used as the display interface of `Ssd1322`, and renders the
+/// panel as the controller would show it, e.g. onto an embedded-graphics-simulator window.
+///
+/// The GDDRAM writes and reads, the address windows and increment, the remap, start line, mux
+/// ratio, partial display, display modes and the command lock are simulated. The panel is
+/// assumed to be wired like most modules, which show the GDDRAM upright with the remap bytes
+/// 0x14, 0x11. The driving and gray scale registers don't change the rendered gray levels.
+///
+/// ```ignore
+/// let mut disp: Ssd1322<_> = Ssd1322::new(Ssd1322Simulator::<256, 64>::new());
+/// disp.init()?;
+/// Text::new("Hello", Point::new(0, 10), style).draw(&mut disp)?;
+/// disp.flush()?;
+/// disp.release().draw(&mut window_display)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Ssd1322Simulator<const WIDTH: usize = 256, const HEIGHT: usize = 64> {
+    gddram: [u8; COLUMNS * ROWS * 2],
+    col_offset: usize,
+    command: u8,
+    args: [u8; 2],
+    num_args: usize,
+    columns: [usize; 2],
+    rows: [usize; 2],
+    address: (usize, usize, usize),
+    dummy_read: bool,
+    remap: [u8; 2],
+    start_line: usize,
+    mux_ratio: usize,
+    partial: Option<[usize; 2]>,
+    mode: u8,
+    on: bool,
+    locked: bool,
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Ssd1322Simulator<WIDTH, HEIGHT> {
+    /// Creates the simulator in its reset state, for a panel that is centred in the GDDRAM.
+    pub const fn new() -> Self {
+        Self::new_with_col_offset(((COLUMNS - WIDTH / 4) / 2) as u8)
+    }
+
+    /// Creates the simulator in its reset state, for a panel whose first column is the GDDRAM
+    /// column address `col_offset`.
+    pub const fn new_with_col_offset(col_offset: u8) -> Self {
+        Self {
+            gddram: [0; COLUMNS * ROWS * 2],
+            col_offset: col_offset as usize,
+            command: 0,
+            args: [0; 2],
+            num_args: 0,
+            columns: [0, COLUMNS - 1],
+            rows: [0, ROWS - 1],
+            address: (0, 0, 0),
+            dummy_read: false,
+            remap: [0x00, 0x01],
+            start_line: 0,
+            mux_ratio: ROWS,
+            partial: None,
+            mode: 0xA6,
+            on: false,
+            locked: false,
+        }
+    }
+
+    /// Returns the gray level (0 to 15) stored for GDDRAM pixel `x`, `y`.
+    pub fn gddram_pixel(&self, x: usize, y: usize) -> u8 {
+        let byte = self.gddram[y * COLUMNS * 2 + x / 2];
+        if x.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        }
+    }
+
+    /// Returns the gray level (0 to 15) shown at pixel `x`, `y` of the panel, or 0 outside of
+    /// the panel.
+    pub fn panel_pixel(&self, x: usize, y: usize) -> u8 {
+        if x >= WIDTH || y >= HEIGHT || !self.on || y >= self.mux_ratio {
+            return 0;
+        }
+        if matches!(self.partial, Some([start, end]) if y < start || y > end) {
+            return 0;
+        }
+
+        let com = if self.remap[0] & 0x10 != 0 {
+            y
+        } else {
+            self.mux_ratio - 1 - y
+        };
+        let row = (self.start_line + com) % ROWS;
+
+        let mut segment = self.col_offset * 4 + x;
+        if self.remap[0] & 0x02 != 0 {
+            segment = COLUMNS * 4 - 1 - segment;
+        }
+        // Without the nibble remap the 4 pixels of a column address are reversed.
+        if self.remap[0] & 0x04 == 0 {
+            segment = segment / 4 * 4 + 3 - segment % 4;
+        }
+        let gray = self.gddram_pixel(segment, row);
+
+        match self.mode {
+            0xA4 => 0,
+            0xA5 => 15,
+            0xA7 => 15 - gray,
+            _ => gray,
+        }
+    }
+
+    /// Returns true if the display is on.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Returns the GDDRAM row shown at the top of the panel.
+    pub fn start_line(&self) -> u8 {
+        self.start_line as u8
+    }
+
+    /// Returns true while the command interface is locked.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    fn command(&mut self, command: u8) {
+        self.num_args = 0;
+        self.command = command;
+        if self.locked && command != 0xFD {
+            return;
+        }
+
+        match command {
+            0x5C | 0x5D => {
+                self.address = (self.columns[0], self.rows[0], 0);
+                self.dummy_read = command == 0x5D;
+            }
+            0xA4..=0xA7 => self.mode = command,
+            0xA9 => self.partial = None,
+            0xAE => self.on = false,
+            0xAF => self.on = true,
+            _ => {}
+        }
+    }
+
+    fn data(&mut self, byte: u8) {
+        if self.locked && self.command != 0xFD {
+            return;
+        }
+        if self.command == 0x5C {
+            let (col, row, i) = self.address;
+            self.gddram[(row * COLUMNS + col) * 2 + i] = byte;
+            self.advance();
+            return;
+        }
+
+        if self.num_args < self.args.len() {
+            self.args[self.num_args] = byte;
+        }
+        self.num_args += 1;
+        let [a, b] = self.args;
+        let clamp = |value: u8, len: usize| usize::from(value).min(len - 1);
+        match (self.command, self.num_args) {
+            (0x15, 2) => self.columns = [clamp(a, COLUMNS), clamp(b, COLUMNS)],
+            (0x75, 2) => self.rows = [clamp(a, ROWS), clamp(b, ROWS)],
+            (0xA0, 2) => self.remap = [a, b],
+            (0xA1, 1) => self.start_line = usize::from(a) % ROWS,
+            (0xA8, 2) => self.partial = Some([usize::from(a), usize::from(b)]),
+            (0xCA, 1) => self.mux_ratio = usize::from(a) + 1,
+            (0xFD, 1) => self.locked = a & 0x04 != 0,
+            _ => {}
+        }
+    }
+
+    /// Moves the address to the next byte of the window.
+    fn advance(&mut self) {
+        let (mut col, mut row, i) = self.address;
+        if i == 0 {
+            self.address.2 = 1;
+            return;
+        }
+
+        let wrap = |value: usize, [start, end]: [usize; 2]| {
+            if value >= end {
+                (start, true)
+            } else {
+                (value + 1, false)
+            }
+        };
+        if self.remap[0] & 0x01 == 0 {
+            let (next, wrapped) = wrap(col, self.columns);
+            col = next;
+            if wrapped {
+                row = wrap(row, self.rows).0;
+            }
+        } else {
+            let (next, wrapped) = wrap(row, self.rows);
+            row = next;
+            if wrapped {
+                col = wrap(col, self.columns).0;
+            }
+        }
+        self.address = (col, row, 0);
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Default for Ssd1322Simulator<WIDTH, HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> WriteOnlyDataCommand
+    for Ssd1322Simulator<WIDTH, HEIGHT>
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        match cmds {
+            DataFormat::U8(slice) => slice.iter().for_each(|&cmd| self.command(cmd)),
+            DataFormat::U8Iter(iter) => iter.for_each(|cmd| self.command(cmd)),
+            DataFormat::U16BE(words) => words
+                .iter()
+                .flat_map(|word| word.to_be_bytes())
+                .for_each(|cmd| self.command(cmd)),
+            // The SSD1322 only takes bytes
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        }
+
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        match buf {
+            DataFormat::U8(slice) => slice.iter().for_each(|&byte| self.data(byte)),
+            DataFormat::U8Iter(iter) => iter.for_each(|byte| self.data(byte)),
+            DataFormat::U16BE(words) => words
+                .iter()
+                .flat_map(|word| word.to_be_bytes())
+                .for_each(|byte| self.data(byte)),
+            // The SSD1322 only takes bytes
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        }
+
+        Ok(())
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> ReadData for Ssd1322Simulator<WIDTH, HEIGHT> {
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        for byte in buf.iter_mut() {
+            if self.dummy_read {
+                self.dummy_read = false;
+                *byte = 0;
+                continue;
+            }
+
+            let (col, row, i) = self.address;
+            *byte = self.gddram[(row * COLUMNS + col) * 2 + i];
+            self.advance();
+        }
+
+        Ok(())
+    }
+
+    fn read_status(&mut self) -> Result<u8, DisplayError> {
+        Ok(if self.on { 0x00 } else { 0x40 })
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<const WIDTH: usize, const HEIGHT: usize> Ssd1322Simulator<WIDTH, HEIGHT> {
+    /// Draws the panel as it is shown onto `target`, e.g. a window or an image to compare in a
+    /// test.
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        target.draw_iter((0..HEIGHT).flat_map(|y| {
+            (0..WIDTH).map(move |x| {
+                Pixel(
+                    Point::new(x as i32, y as i32),
+                    Gray4::new(self.panel_pixel(x, y)),
+                )
+            })
+        }))
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<const WIDTH: usize, const HEIGHT: usize> OriginDimensions for Ssd1322Simulator<WIDTH, HEIGHT> {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Ssd1322;
+
+    #[test]
+    /// Tests that the simulated panel shows what the driver flushed.
+    fn simulator() {
+        let mut disp: Ssd1322<_> = Ssd1322::new(Ssd1322Simulator::<256, 64>::new());
+        disp.init().unwrap();
+        disp.set_pixel(1, 2, 0x0F);
+        disp.set_pixel(254, 63, 0x08);
+        disp.flush().unwrap();
+        assert!(!disp.read_status().unwrap().display_off);
+
+        let mut buf = [0; 2];
+        disp.read_ram(0, 2, &mut buf).unwrap();
+        assert_eq!(buf, [0x0F, 0x00]);
+
+        disp.set_mirror(true, false).unwrap();
+        disp.flush_all().unwrap();
+        disp.scroll_to_line(1).unwrap();
+        disp.invert(true).unwrap();
+
+        // The pixels are mirrored, scrolled up by a row and inverted.
+        let sim = disp.release();
+        assert!(sim.is_on());
+        assert_eq!(sim.start_line(), 1);
+        assert_eq!(sim.gddram_pixel(0x1C * 4 + 1, 2), 0x0F);
+        assert_eq!(sim.panel_pixel(254, 1), 0x00);
+        assert_eq!(sim.panel_pixel(1, 62), 0x07);
+        assert_eq!(sim.panel_pixel(0, 0), 0x0F);
+    }
+
+    #[test]
+    /// Tests that a flush in 16 bit words reaches the GDDRAM in the order of the bytes.
+    fn simulator_u16() {
+        use crate::config::{DataWidth, DisplayConfig};
+
+        let config = DisplayConfig {
+            data_width: DataWidth::U16,
+            ..DisplayConfig::for_panel(256, 64)
+        };
+        let mut disp: Ssd1322<_> =
+            Ssd1322::new_with_config(Ssd1322Simulator::<256, 64>::new(), config);
+        disp.init().unwrap();
+        disp.set_pixel(1, 2, 0x0F);
+        disp.set_pixel(6, 2, 0x08);
+        disp.flush().unwrap();
+        disp.set_pixel(255, 63, 0x03);
+        disp.flush_all().unwrap();
+
+        let sim = disp.release();
+        assert_eq!(sim.panel_pixel(1, 2), 0x0F);
+        assert_eq!(sim.panel_pixel(6, 2), 0x08);
+        assert_eq!(sim.panel_pixel(7, 2), 0x00);
+        assert_eq!(sim.panel_pixel(255, 63), 0x03);
+    }
+}
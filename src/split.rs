@@ -0,0 +1,141 @@
+//! split driver module
+use crate::command::Command;
+use crate::display::Ssd1322;
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+/// The display interface of a `FrameBuffer`, which has no bus. A driver with it draws into the
+/// framebuffer but can't send anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Detached;
+
+/// The drawing half of a split driver, see `Ssd1322::split`. It draws with embedded-graphics,
+/// `set_pixel` and the blit methods, and tracks the changes for the `CommandPort`.
+pub type FrameBuffer<const WIDTH: usize = 256, const HEIGHT: usize = 64, B = [u8; 8192]> =
+    Ssd1322<Detached, WIDTH, HEIGHT, B>;
+
+/// The bus half of a split driver, see `Ssd1322::split`. It owns the display interface and
+/// flushes a `FrameBuffer` it is lent.
+#[derive(Debug)]
+pub struct CommandPort<DI> {
+    display: DI,
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B> Ssd1322<DI, WIDTH, HEIGHT, B> {
+    /// Splits the driver into a `CommandPort` that owns the display interface and a
+    /// `FrameBuffer` that owns the framebuffer, e.g. so RTIC tasks of different priorities can
+    /// draw and flush without sharing the whole driver as one resource.
+    ///
+    /// ```ignore
+    /// let (mut port, mut fb) = disp.split();
+    /// Text::new("Hello", Point::new(0, 10), style).draw(&mut fb)?;
+    /// port.flush(&mut fb)?;
+    /// let disp = port.join(fb);
+    /// ```
+    pub fn split(self) -> (CommandPort<DI>, FrameBuffer<WIDTH, HEIGHT, B>) {
+        let (framebuffer, display) = self.into_interface(Detached);
+
+        (CommandPort { display }, framebuffer)
+    }
+}
+
+impl<DI> CommandPort<DI> {
+    /// Joins the port and `framebuffer` back into a driver.
+    pub fn join<const WIDTH: usize, const HEIGHT: usize, B>(
+        self,
+        framebuffer: FrameBuffer<WIDTH, HEIGHT, B>,
+    ) -> Ssd1322<DI, WIDTH, HEIGHT, B> {
+        framebuffer.into_interface(self.display).0
+    }
+}
+
+impl<DI> CommandPort<DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    /// Sends a command to the display, without the command lock of the driver.
+    pub fn send_command(&mut self, command: Command) -> Result<(), DisplayError> {
+        command.send(&mut self.display)
+    }
+
+    /// Calls `f` with a driver that sends to this port and works on `framebuffer`, e.g. to
+    /// initialize the display or change the contrast. Changes of the configuration and the
+    /// tracked changes are kept in `framebuffer`.
+    pub fn with_framebuffer<const WIDTH: usize, const HEIGHT: usize, B, R, F>(
+        &mut self,
+        framebuffer: &mut FrameBuffer<WIDTH, HEIGHT, B>,
+        f: F,
+    ) -> R
+    where
+        B: AsMut<[u8]>,
+        F: FnOnce(&mut Ssd1322<&mut Self, WIDTH, HEIGHT, &mut [u8]>) -> R,
+    {
+        framebuffer.with_interface(self, f)
+    }
+
+    /// Flushes the changed portion of `framebuffer` to the display, see `Ssd1322::flush`.
+    pub fn flush<const WIDTH: usize, const HEIGHT: usize, B>(
+        &mut self,
+        framebuffer: &mut FrameBuffer<WIDTH, HEIGHT, B>,
+    ) -> Result<(), DisplayError>
+    where
+        B: AsMut<[u8]>,
+    {
+        self.with_framebuffer(framebuffer, |disp| disp.flush())
+    }
+
+    /// Flushes the entire `framebuffer` to the display, see `Ssd1322::flush_all`.
+    pub fn flush_all<const WIDTH: usize, const HEIGHT: usize, B>(
+        &mut self,
+        framebuffer: &mut FrameBuffer<WIDTH, HEIGHT, B>,
+    ) -> Result<(), DisplayError>
+    where
+        B: AsMut<[u8]>,
+    {
+        self.with_framebuffer(framebuffer, |disp| disp.flush_all())
+    }
+}
+
+impl<DI> WriteOnlyDataCommand for &mut CommandPort<DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.display.send_commands(cmd)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.display.send_data(buf)
+    }
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+    use embedded_graphics::{pixelcolor::Gray4, prelude::*};
+
+    #[test]
+    /// Tests that the framebuffer drawn apart from the port is flushed through it.
+    fn split() {
+        let disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let (mut port, mut fb) = disp.split();
+        Pixel(Point::new(10, 1), Gray4::WHITE)
+            .draw(&mut fb)
+            .unwrap();
+        port.flush(&mut fb).unwrap();
+        let stats = port.with_framebuffer(&mut fb, |disp| disp.stats());
+        assert_eq!((stats.flushes, stats.windows), (1, 1));
+
+        port.with_framebuffer(&mut fb, |disp| disp.set_contrast(0x40))
+            .unwrap();
+        let disp = port.join(fb);
+        assert_eq!(disp.config().contrast, 0x40);
+        assert_eq!(disp.dirty_area(), None);
+
+        let iface = disp.release();
+        let window = iface.windows().next().unwrap();
+        assert_eq!((window.columns, window.rows), ([0x1E, 0x1E], [1, 1]));
+        assert_eq!(window.data, [0x00, 0xF0]);
+        assert!(iface.has_command(&[0xC1, 0x40]));
+    }
+}
@@ -0,0 +1,363 @@
+//! framebuffer streaming module
+//!
+//! Serializes the framebuffer into frames that can be sent over a UART or RTT channel, to watch
+//! the display content of a headless device, and decodes them on the host with `FrameDecoder`.
+//!
+//! A frame starts with the `SYNC` bytes, followed by the first framebuffer byte column, the
+//! first row, the number of byte columns and the number of rows as little endian `u16`s. The
+//! bytes of the rows come next, two pixels per byte with the left pixel in the upper nibble,
+//! and last the CRC-16/CCITT-FALSE of everything after the `SYNC` bytes, little endian.
+use crate::display::Ssd1322;
+
+/// The bytes that start every frame.
+pub const SYNC: [u8; 2] = [0xA5, 0x5A];
+
+/// Length of the frame header after the `SYNC` bytes.
+const HEADER_LEN: usize = 8;
+
+/// Bytes collected before each call of the write function.
+const CHUNK_SIZE: usize = 32;
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B> Ssd1322<DI, WIDTH, HEIGHT, B>
+where
+    B: AsRef<[u8]>,
+{
+    /// Writes the framebuffer as one frame to `write`, e.g. a UART or RTT write.
+    pub fn stream_all<F, E>(&self, write: F) -> Result<(), E>
+    where
+        F: FnMut(&[u8]) -> Result<(), E>,
+    {
+        self.stream_area([0, WIDTH / 2 - 1], [0, HEIGHT - 1], write)
+    }
+
+    /// Writes the area that changed since the last flush as one frame to `write`, or nothing if
+    /// nothing changed. Call it before the flush, which clears the changes.
+    pub fn stream_dirty<F, E>(&self, write: F) -> Result<(), E>
+    where
+        F: FnMut(&[u8]) -> Result<(), E>,
+    {
+        match self.dirty.union() {
+            Some((cols, rows)) => self.stream_area(
+                [usize::from(cols[0]), usize::from(cols[1])],
+                [usize::from(rows[0]), usize::from(rows[1])],
+                write,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the byte columns `cols` of the rows `rows` as a frame.
+    fn stream_area<F, E>(&self, cols: [usize; 2], rows: [usize; 2], mut write: F) -> Result<(), E>
+    where
+        F: FnMut(&[u8]) -> Result<(), E>,
+    {
+        let mut header = [0; HEADER_LEN];
+        let fields = [
+            cols[0],
+            rows[0],
+            cols[1] - cols[0] + 1,
+            rows[1] - rows[0] + 1,
+        ];
+        for (bytes, field) in header.chunks_exact_mut(2).zip(fields) {
+            bytes.copy_from_slice(&(field as u16).to_le_bytes());
+        }
+        write(&SYNC)?;
+        write(&header)?;
+        let mut crc = header.iter().fold(CRC_INIT, |crc, &byte| crc16(crc, byte));
+
+        let order = self.config.nibble_order;
+        let mut chunk = [0; CHUNK_SIZE];
+        for row in rows[0]..=rows[1] {
+            let line = &self.buffer.as_ref()[row * WIDTH / 2..][cols[0]..=cols[1]];
+            for bytes in line.chunks(CHUNK_SIZE) {
+                for (out, &byte) in chunk.iter_mut().zip(bytes) {
+                    *out = order.arrange(byte);
+                    crc = crc16(crc, *out);
+                }
+                write(&chunk[..bytes.len()])?;
+            }
+        }
+
+        write(&crc.to_le_bytes())
+    }
+}
+
+/// Where a `FrameDecoder` is in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Sync(usize),
+    Header(usize),
+    Data(usize),
+    Crc(usize),
+}
+
+/// Decodes the frames written by `Ssd1322::stream_all` and `stream_dirty` into a copy of the
+/// framebuffer of a `WIDTH` x `HEIGHT` panel, e.g. on the host to show it in a window.
+///
+/// Frames are only applied once their CRC matches, corrupted or truncated frames are skipped up
+/// to the next `SYNC` bytes.
+///
+/// ```ignore
+/// let mut decoder = FrameDecoder::<256, 64, 8192>::new();
+/// for byte in serial.bytes() {
+///     if decoder.push(byte?) {
+///         show(decoder.framebuffer());
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameDecoder<const WIDTH: usize, const HEIGHT: usize, const N: usize> {
+    framebuffer: [u8; N],
+    data: [u8; N],
+    header: [u8; HEADER_LEN],
+    state: State,
+    crc: u16,
+    received_crc: [u8; 2],
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> FrameDecoder<WIDTH, HEIGHT, N> {
+    const BUFFER_CHECK: () = assert!(
+        N == WIDTH * HEIGHT / 2,
+        "the framebuffer must hold WIDTH * HEIGHT / 2 bytes"
+    );
+
+    /// Creates the decoder with a cleared framebuffer.
+    pub fn new() -> Self {
+        let () = Self::BUFFER_CHECK;
+
+        Self {
+            framebuffer: [0; N],
+            data: [0; N],
+            header: [0; HEADER_LEN],
+            state: State::Sync(0),
+            crc: CRC_INIT,
+            received_crc: [0; 2],
+        }
+    }
+
+    /// Decodes the next received byte. Returns true if it completed a frame, which was applied
+    /// to the framebuffer.
+    pub fn push(&mut self, byte: u8) -> bool {
+        self.state = match self.state {
+            State::Sync(i) if byte == SYNC[i] => {
+                if i + 1 == SYNC.len() {
+                    self.crc = CRC_INIT;
+                    State::Header(0)
+                } else {
+                    State::Sync(i + 1)
+                }
+            }
+            State::Sync(_) => State::Sync(usize::from(byte == SYNC[0])),
+            State::Header(i) => {
+                self.header[i] = byte;
+                self.crc = crc16(self.crc, byte);
+                if i + 1 < HEADER_LEN {
+                    State::Header(i + 1)
+                } else if self.area().is_some() {
+                    State::Data(0)
+                } else {
+                    State::Sync(0)
+                }
+            }
+            State::Data(i) => {
+                self.data[i] = byte;
+                self.crc = crc16(self.crc, byte);
+                match self.area() {
+                    Some((_, _, cols, rows)) if i + 1 < cols * rows => State::Data(i + 1),
+                    _ => State::Crc(0),
+                }
+            }
+            State::Crc(0) => {
+                self.received_crc[0] = byte;
+                State::Crc(1)
+            }
+            State::Crc(_) => {
+                self.received_crc[1] = byte;
+                let applied = u16::from_le_bytes(self.received_crc) == self.crc;
+                if applied {
+                    self.apply();
+                }
+                self.state = State::Sync(0);
+                return applied;
+            }
+        };
+
+        false
+    }
+
+    /// Returns the framebuffer, two pixels per byte with the left pixel in the upper nibble, as
+    /// the frames hold it. This is the format of `Ssd1322::framebuffer` unless the streaming
+    /// driver's `DisplayConfig::nibble_order` is `LeftLower`, whose bytes are swapped.
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    /// Returns the gray level (0 to 15) of pixel `x`, `y`, or 0 outside of the panel.
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        if x >= WIDTH || y >= HEIGHT {
+            return 0;
+        }
+
+        let byte = self.framebuffer[y * WIDTH / 2 + x / 2];
+        if x.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        }
+    }
+
+    /// Returns the first byte column, first row, byte columns and rows of the frame header, or
+    /// `None` if they are outside of the framebuffer.
+    fn area(&self) -> Option<(usize, usize, usize, usize)> {
+        let mut fields = self
+            .header
+            .chunks_exact(2)
+            .map(|bytes| usize::from(u16::from_le_bytes([bytes[0], bytes[1]])));
+        let (col, row) = (fields.next()?, fields.next()?);
+        let (cols, rows) = (fields.next()?, fields.next()?);
+
+        (cols > 0 && rows > 0 && col + cols <= WIDTH / 2 && row + rows <= HEIGHT)
+            .then_some((col, row, cols, rows))
+    }
+
+    /// Copies the received rows to the framebuffer.
+    fn apply(&mut self) {
+        if let Some((col, row, cols, rows)) = self.area() {
+            for (i, line) in self.data[..cols * rows].chunks_exact(cols).enumerate() {
+                let start = (row + i) * WIDTH / 2 + col;
+                self.framebuffer[start..start + cols].copy_from_slice(line);
+            }
+        }
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> Default
+    for FrameDecoder<WIDTH, HEIGHT, N>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const CRC_INIT: u16 = 0xFFFF;
+
+/// Adds `byte` to the CRC-16/CCITT-FALSE `crc`.
+fn crc16(crc: u16, byte: u8) -> u16 {
+    let mut crc = crc ^ (u16::from(byte) << 8);
+    for _ in 0..8 {
+        crc = if crc & 0x8000 != 0 {
+            (crc << 1) ^ 0x1021
+        } else {
+            crc << 1
+        };
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+
+    /// Writes frames into a byte array.
+    struct Link {
+        bytes: [u8; 9000],
+        len: usize,
+    }
+
+    impl Link {
+        fn write(&mut self, bytes: &[u8]) -> Result<(), ()> {
+            self.bytes[self.len..][..bytes.len()].copy_from_slice(bytes);
+            self.len += bytes.len();
+            Ok(())
+        }
+    }
+
+    #[test]
+    /// Tests that streamed frames are decoded into the framebuffer and corrupted ones skipped.
+    fn stream_frames() {
+        let check = b"123456789"
+            .iter()
+            .fold(CRC_INIT, |crc, &byte| crc16(crc, byte));
+        assert_eq!(check, 0x29B1);
+
+        let mut disp: Ssd1322<_> = Ssd1322::new(RecordingInterface::<64>::new());
+        let mut link = Link {
+            bytes: [0; 9000],
+            len: 0,
+        };
+        let mut decoder = FrameDecoder::<256, 64, 8192>::new();
+        disp.stream_dirty(|bytes| link.write(bytes)).unwrap();
+        assert_eq!(link.len, 0);
+
+        disp.set_pixel(5, 3, 0x0F);
+        disp.set_pixel(7, 4, 0x08);
+        disp.stream_dirty(|bytes| link.write(bytes)).unwrap();
+        assert_eq!(link.len, 2 + HEADER_LEN + 4 + 2);
+        assert_eq!(link.bytes[..6], [0xA5, 0x5A, 2, 0, 3, 0]);
+
+        // A corrupted copy of the frame after some noise is skipped.
+        let frame = link.bytes;
+        let mut corrupted = frame;
+        corrupted[HEADER_LEN + 3] ^= 0x01;
+        let noise = [0x00, 0xA5, 0x17];
+        let applied = noise
+            .iter()
+            .chain(&corrupted[..link.len])
+            .filter(|&&byte| decoder.push(byte))
+            .count();
+        assert_eq!(applied, 0);
+        assert_eq!(decoder.pixel(5, 3), 0x00);
+
+        let applied = frame[..link.len]
+            .iter()
+            .filter(|&&byte| decoder.push(byte))
+            .count();
+        assert_eq!(applied, 1);
+        assert_eq!(decoder.pixel(5, 3), 0x0F);
+        assert_eq!(decoder.pixel(7, 4), 0x08);
+
+        link.len = 0;
+        disp.set_pixel(255, 63, 0x01);
+        disp.stream_all(|bytes| link.write(bytes)).unwrap();
+        assert_eq!(link.len, 2 + HEADER_LEN + 8192 + 2);
+        for &byte in &link.bytes[..link.len] {
+            decoder.push(byte);
+        }
+        assert_eq!(decoder.framebuffer(), disp.framebuffer());
+        // Streaming doesn't use the display interface.
+        assert_eq!(disp.release().transfers(), 0);
+    }
+
+    #[test]
+    /// Tests that the frames of a driver with the left pixel in the lower nibble are decoded with
+    /// the left pixel in the upper nibble.
+    fn stream_left_lower() {
+        use crate::builder::Ssd1322Builder;
+        use crate::config::NibbleOrder;
+
+        let mut disp: Ssd1322<_> = Ssd1322Builder::new()
+            .nibble_order(NibbleOrder::LeftLower)
+            .build(RecordingInterface::<64>::new());
+        let mut link = Link {
+            bytes: [0; 9000],
+            len: 0,
+        };
+        let mut decoder = FrameDecoder::<256, 64, 8192>::new();
+        disp.set_pixel(4, 3, 0x0F);
+        disp.set_pixel(5, 3, 0x02);
+        assert_eq!(disp.framebuffer()[3 * 128 + 2], 0x2F);
+
+        disp.stream_all(|bytes| link.write(bytes)).unwrap();
+        for &byte in &link.bytes[..link.len] {
+            decoder.push(byte);
+        }
+        assert_eq!(decoder.framebuffer()[3 * 128 + 2], 0xF2);
+        assert_eq!((decoder.pixel(4, 3), decoder.pixel(5, 3)), (0x0F, 0x02));
+        assert!(decoder
+            .framebuffer()
+            .iter()
+            .zip(disp.framebuffer())
+            .all(|(&decoded, &byte)| decoded == byte.rotate_left(4)));
+    }
+}
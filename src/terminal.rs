@@ -0,0 +1,369 @@
+//! text terminal mode module
+use core::fmt;
+
+use crate::config::DisplayConfig;
+use crate::display::Ssd1322;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+
+/// Width and height of a character cell in pixels.
+const CELL: usize = 8;
+
+/// Number of rows in the SSD1322 GDDRAM, which the hardware scrolling wraps around.
+const GDDRAM_ROWS: usize = 128;
+
+/// A text terminal drawn with a built-in 8x8 font, without a framebuffer.
+///
+/// `Ssd1322` implements `core::fmt::Write` in this mode, so `write!` prints at the cursor. Lines
+/// wrap at the right edge and the terminal scrolls in hardware, by moving the display start line,
+/// once the cursor passes the last line. `'\n'` starts a new line and `'\r'` returns to the
+/// start of the line, characters outside of printable ASCII are shown as `'?'`.
+///
+/// Characters are laid out in the unrotated orientation, a 90 or 270 degree rotation of the
+/// display configuration is ignored. Call `clear` after `init` to start from a blank screen.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalMode {
+    col: usize,
+    row: usize,
+}
+
+struct TerminalSize<const WIDTH: usize, const HEIGHT: usize>;
+
+impl<const WIDTH: usize, const HEIGHT: usize> TerminalSize<WIDTH, HEIGHT> {
+    const CHECK: () = assert!(
+        WIDTH >= CELL && HEIGHT >= CELL,
+        "the terminal needs a panel of at least 8x8 pixels"
+    );
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize> Ssd1322<DI, WIDTH, HEIGHT, (), TerminalMode> {
+    /// Number of characters per line.
+    pub const COLUMNS: usize = WIDTH / CELL;
+
+    /// Number of lines.
+    pub const LINES: usize = HEIGHT / CELL;
+
+    /// Creates the SSD1322 Display in terminal mode.
+    ///
+    /// The panel is assumed to be centred in the GDDRAM. The device needs to be reset before use.
+    pub fn new_terminal(display: DI) -> Self {
+        Self::new_terminal_with_config(display, DisplayConfig::for_panel(WIDTH, HEIGHT))
+    }
+
+    /// Creates the SSD1322 Display in terminal mode for a panel wired as described by `config`.
+    ///
+    /// The configuration is validated by `init`. The device needs to be reset before use.
+    pub fn new_terminal_with_config(display: DI, config: DisplayConfig) -> Self {
+        let () = TerminalSize::<WIDTH, HEIGHT>::CHECK;
+
+        Self::from_parts(display, (), config, TerminalMode::default())
+    }
+
+    /// Returns the cursor position as column and line.
+    pub fn position(&self) -> (usize, usize) {
+        (self.mode.col, self.mode.row)
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, MODE> Ssd1322<DI, WIDTH, HEIGHT, B, MODE> {
+    /// Converts the driver into terminal mode, dropping the framebuffer.
+    ///
+    /// The display configuration is kept. Call `clear` to reset the screen and the cursor.
+    pub fn into_terminal_mode(self) -> Ssd1322<DI, WIDTH, HEIGHT, (), TerminalMode> {
+        let () = TerminalSize::<WIDTH, HEIGHT>::CHECK;
+
+        self.into_mode((), TerminalMode::default())
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize> Ssd1322<DI, WIDTH, HEIGHT, (), TerminalMode>
+where
+    DI: WriteOnlyDataCommand,
+{
+    /// Clears the screen, undoes any scrolling and moves the cursor to the top left corner.
+    pub fn clear(&mut self) -> Result<(), DisplayError> {
+        self.write_window([0, WIDTH / 4 - 1], [0, GDDRAM_ROWS - 1], |_, _, _| 0)?;
+        self.scroll_to_line(0)?;
+
+        self.mode = TerminalMode::default();
+
+        Ok(())
+    }
+
+    /// Moves the cursor to `col` and `line`.
+    ///
+    /// Returns `DisplayError::OutOfBoundsError` if the position is outside of the terminal.
+    pub fn set_position(&mut self, col: usize, line: usize) -> Result<(), DisplayError> {
+        if col >= Self::COLUMNS || line >= Self::LINES {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+
+        self.mode.col = col;
+        self.mode.row = line;
+
+        Ok(())
+    }
+
+    /// Prints a character at the cursor and advances the cursor.
+    pub fn print_char(&mut self, c: char) -> Result<(), DisplayError> {
+        match c {
+            '\n' => self.new_line(),
+            '\r' => {
+                self.mode.col = 0;
+                Ok(())
+            }
+            c => {
+                if self.mode.col == Self::COLUMNS {
+                    self.new_line()?;
+                }
+
+                let glyph = glyph(c);
+                let x = self.mode.col * CELL / 4;
+                let y = self.line_address(self.mode.row);
+                self.write_window([x, x + 1], [y, y + CELL - 1], |_, row, i| {
+                    let bits = glyph[row] << (i * 2);
+                    (if bits & 0x80 != 0 { 0xF0 } else { 0 })
+                        | if bits & 0x40 != 0 { 0x0F } else { 0 }
+                })?;
+
+                self.mode.col += 1;
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Moves the cursor to the start of the next line, scrolling if it is past the last line.
+    fn new_line(&mut self) -> Result<(), DisplayError> {
+        self.mode.col = 0;
+
+        if self.mode.row + 1 < Self::LINES {
+            self.mode.row += 1;
+            return Ok(());
+        }
+
+        self.scroll_by(CELL as i8)?;
+
+        // Clear the new last line and any rows below it that don't hold a whole line.
+        let first = self.line_address(Self::LINES - 1);
+        let count = HEIGHT - (Self::LINES - 1) * CELL;
+        let wrapped = (first + count).saturating_sub(GDDRAM_ROWS);
+        self.write_window(
+            [0, WIDTH / 4 - 1],
+            [first, first + count - wrapped - 1],
+            |_, _, _| 0,
+        )?;
+        if wrapped > 0 {
+            self.write_window([0, WIDTH / 4 - 1], [0, wrapped - 1], |_, _, _| 0)?;
+        }
+
+        Ok(())
+    }
+
+    /// The GDDRAM row of the top of `line`.
+    fn line_address(&self, line: usize) -> usize {
+        (usize::from(self.start_line) + line * CELL) % GDDRAM_ROWS
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize> fmt::Write
+    for Ssd1322<DI, WIDTH, HEIGHT, (), TerminalMode>
+where
+    DI: WriteOnlyDataCommand,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.print_char(c).map_err(|_| fmt::Error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the glyph of a character, one byte per row with the leftmost pixel in the MSB.
+fn glyph(c: char) -> &'static [u8; CELL] {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+
+    &FONT[index]
+}
+
+/// Printable ASCII characters of the X11 misc-fixed 5x8 font, placed in 8x8 cells.
+#[rustfmt::skip]
+const FONT: [[u8; CELL]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x00], // '!'
+    [0x00, 0x28, 0x28, 0x28, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x28, 0x28, 0x7C, 0x28, 0x7C, 0x28, 0x28, 0x00], // '#'
+    [0x10, 0x38, 0x50, 0x38, 0x14, 0x38, 0x10, 0x00], // '$'
+    [0x00, 0x20, 0x28, 0x10, 0x28, 0x08, 0x00, 0x00], // '%'
+    [0x20, 0x50, 0x50, 0x20, 0x50, 0x50, 0x28, 0x00], // '&'
+    [0x00, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x00, 0x10, 0x20, 0x20, 0x20, 0x20, 0x10, 0x00], // '('
+    [0x00, 0x20, 0x10, 0x10, 0x10, 0x10, 0x20, 0x00], // ')'
+    [0x00, 0x00, 0x48, 0x30, 0x78, 0x30, 0x48, 0x00], // '*'
+    [0x00, 0x00, 0x10, 0x10, 0x7C, 0x10, 0x10, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x10, 0x20], // ','
+    [0x00, 0x00, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10], // '.'
+    [0x00, 0x08, 0x08, 0x10, 0x20, 0x40, 0x40, 0x00], // '/'
+    [0x00, 0x10, 0x28, 0x28, 0x28, 0x28, 0x10, 0x00], // '0'
+    [0x00, 0x10, 0x30, 0x10, 0x10, 0x10, 0x38, 0x00], // '1'
+    [0x00, 0x30, 0x48, 0x08, 0x30, 0x40, 0x78, 0x00], // '2'
+    [0x00, 0x78, 0x10, 0x30, 0x08, 0x48, 0x30, 0x00], // '3'
+    [0x00, 0x10, 0x30, 0x50, 0x78, 0x10, 0x10, 0x00], // '4'
+    [0x00, 0x78, 0x40, 0x70, 0x08, 0x48, 0x30, 0x00], // '5'
+    [0x00, 0x30, 0x40, 0x70, 0x48, 0x48, 0x30, 0x00], // '6'
+    [0x00, 0x78, 0x08, 0x10, 0x10, 0x20, 0x20, 0x00], // '7'
+    [0x00, 0x30, 0x48, 0x30, 0x48, 0x48, 0x30, 0x00], // '8'
+    [0x00, 0x30, 0x48, 0x48, 0x38, 0x08, 0x30, 0x00], // '9'
+    [0x00, 0x00, 0x30, 0x30, 0x00, 0x30, 0x30, 0x00], // ':'
+    [0x00, 0x00, 0x18, 0x18, 0x00, 0x18, 0x10, 0x20], // ';'
+    [0x00, 0x08, 0x10, 0x20, 0x20, 0x10, 0x08, 0x00], // '<'
+    [0x00, 0x00, 0x00, 0x78, 0x00, 0x78, 0x00, 0x00], // '='
+    [0x00, 0x20, 0x10, 0x08, 0x08, 0x10, 0x20, 0x00], // '>'
+    [0x00, 0x10, 0x28, 0x08, 0x10, 0x00, 0x10, 0x00], // '?'
+    [0x18, 0x24, 0x4C, 0x54, 0x54, 0x48, 0x20, 0x18], // '@'
+    [0x00, 0x30, 0x48, 0x48, 0x78, 0x48, 0x48, 0x00], // 'A'
+    [0x00, 0x70, 0x48, 0x70, 0x48, 0x48, 0x70, 0x00], // 'B'
+    [0x00, 0x30, 0x48, 0x40, 0x40, 0x48, 0x30, 0x00], // 'C'
+    [0x00, 0x70, 0x48, 0x48, 0x48, 0x48, 0x70, 0x00], // 'D'
+    [0x00, 0x78, 0x40, 0x70, 0x40, 0x40, 0x78, 0x00], // 'E'
+    [0x00, 0x78, 0x40, 0x70, 0x40, 0x40, 0x40, 0x00], // 'F'
+    [0x00, 0x30, 0x48, 0x40, 0x58, 0x48, 0x30, 0x00], // 'G'
+    [0x00, 0x48, 0x48, 0x78, 0x48, 0x48, 0x48, 0x00], // 'H'
+    [0x00, 0x38, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'I'
+    [0x00, 0x38, 0x10, 0x10, 0x10, 0x50, 0x20, 0x00], // 'J'
+    [0x00, 0x48, 0x50, 0x60, 0x50, 0x50, 0x48, 0x00], // 'K'
+    [0x00, 0x40, 0x40, 0x40, 0x40, 0x40, 0x78, 0x00], // 'L'
+    [0x00, 0x48, 0x78, 0x78, 0x48, 0x48, 0x48, 0x00], // 'M'
+    [0x00, 0x48, 0x68, 0x78, 0x58, 0x58, 0x48, 0x00], // 'N'
+    [0x00, 0x30, 0x48, 0x48, 0x48, 0x48, 0x30, 0x00], // 'O'
+    [0x00, 0x70, 0x48, 0x48, 0x70, 0x40, 0x40, 0x00], // 'P'
+    [0x00, 0x30, 0x48, 0x48, 0x68, 0x58, 0x30, 0x08], // 'Q'
+    [0x00, 0x70, 0x48, 0x48, 0x70, 0x48, 0x48, 0x00], // 'R'
+    [0x00, 0x30, 0x48, 0x20, 0x10, 0x48, 0x30, 0x00], // 'S'
+    [0x00, 0x38, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // 'T'
+    [0x00, 0x48, 0x48, 0x48, 0x48, 0x48, 0x30, 0x00], // 'U'
+    [0x00, 0x48, 0x48, 0x48, 0x48, 0x30, 0x30, 0x00], // 'V'
+    [0x00, 0x48, 0x48, 0x48, 0x78, 0x78, 0x48, 0x00], // 'W'
+    [0x00, 0x48, 0x48, 0x30, 0x30, 0x48, 0x48, 0x00], // 'X'
+    [0x00, 0x44, 0x44, 0x28, 0x10, 0x10, 0x10, 0x00], // 'Y'
+    [0x00, 0x78, 0x08, 0x10, 0x20, 0x40, 0x78, 0x00], // 'Z'
+    [0x00, 0x38, 0x20, 0x20, 0x20, 0x20, 0x38, 0x00], // '['
+    [0x00, 0x40, 0x40, 0x20, 0x10, 0x08, 0x08, 0x00], // '\\'
+    [0x00, 0x38, 0x08, 0x08, 0x08, 0x08, 0x38, 0x00], // ']'
+    [0x00, 0x10, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x78], // '_'
+    [0x00, 0x20, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x00, 0x38, 0x48, 0x48, 0x38, 0x00], // 'a'
+    [0x00, 0x40, 0x40, 0x70, 0x48, 0x48, 0x70, 0x00], // 'b'
+    [0x00, 0x00, 0x00, 0x18, 0x20, 0x20, 0x18, 0x00], // 'c'
+    [0x00, 0x08, 0x08, 0x38, 0x48, 0x48, 0x38, 0x00], // 'd'
+    [0x00, 0x00, 0x00, 0x30, 0x58, 0x60, 0x30, 0x00], // 'e'
+    [0x00, 0x10, 0x28, 0x20, 0x70, 0x20, 0x20, 0x00], // 'f'
+    [0x00, 0x00, 0x00, 0x30, 0x48, 0x38, 0x08, 0x30], // 'g'
+    [0x00, 0x40, 0x40, 0x70, 0x48, 0x48, 0x48, 0x00], // 'h'
+    [0x00, 0x10, 0x00, 0x30, 0x10, 0x10, 0x38, 0x00], // 'i'
+    [0x00, 0x08, 0x00, 0x08, 0x08, 0x08, 0x28, 0x10], // 'j'
+    [0x00, 0x40, 0x40, 0x48, 0x70, 0x48, 0x48, 0x00], // 'k'
+    [0x00, 0x30, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'l'
+    [0x00, 0x00, 0x00, 0x68, 0x54, 0x54, 0x54, 0x00], // 'm'
+    [0x00, 0x00, 0x00, 0x70, 0x48, 0x48, 0x48, 0x00], // 'n'
+    [0x00, 0x00, 0x00, 0x30, 0x48, 0x48, 0x30, 0x00], // 'o'
+    [0x00, 0x00, 0x00, 0x70, 0x48, 0x70, 0x40, 0x40], // 'p'
+    [0x00, 0x00, 0x00, 0x38, 0x48, 0x38, 0x08, 0x08], // 'q'
+    [0x00, 0x00, 0x00, 0x50, 0x68, 0x40, 0x40, 0x00], // 'r'
+    [0x00, 0x00, 0x00, 0x18, 0x30, 0x08, 0x30, 0x00], // 's'
+    [0x00, 0x20, 0x20, 0x70, 0x20, 0x28, 0x10, 0x00], // 't'
+    [0x00, 0x00, 0x00, 0x48, 0x48, 0x48, 0x38, 0x00], // 'u'
+    [0x00, 0x00, 0x00, 0x28, 0x28, 0x28, 0x10, 0x00], // 'v'
+    [0x00, 0x00, 0x00, 0x44, 0x54, 0x54, 0x28, 0x00], // 'w'
+    [0x00, 0x00, 0x00, 0x48, 0x30, 0x30, 0x48, 0x00], // 'x'
+    [0x00, 0x00, 0x00, 0x48, 0x48, 0x38, 0x48, 0x30], // 'y'
+    [0x00, 0x00, 0x00, 0x78, 0x10, 0x20, 0x78, 0x00], // 'z'
+    [0x18, 0x20, 0x10, 0x60, 0x10, 0x20, 0x18, 0x00], // '{'
+    [0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // '|'
+    [0x60, 0x10, 0x20, 0x18, 0x20, 0x10, 0x60, 0x00], // '}'
+    [0x00, 0x28, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+    use core::fmt::Write;
+
+    /// Returns the argument of the last start line command.
+    fn start_line<const N: usize>(iface: &RecordingInterface<N>) -> Option<u8> {
+        iface
+            .sent()
+            .zip(iface.sent().skip(1))
+            .filter(|&(command, (data, _))| command == (false, 0xA1) && data)
+            .map(|(_, (_, line))| line)
+            .last()
+    }
+
+    #[test]
+    /// Tests that characters are rendered with the built-in font and advance the cursor.
+    fn print_char() {
+        let mut disp: Ssd1322<_, 256, 64, (), TerminalMode> =
+            Ssd1322::new_terminal(RecordingInterface::<256>::new());
+        assert_eq!(
+            Ssd1322::<RecordingInterface<256>, 256, 64, (), TerminalMode>::COLUMNS,
+            32
+        );
+
+        write!(disp, "!").unwrap();
+        assert_eq!(disp.position(), (1, 0));
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(window.rows, [0, 7]);
+        assert_eq!(window.data.len(), 32);
+        // Row 1 of '!' is a single pixel in the 4th column of the cell.
+        assert_eq!(window.data[4..8], [0x00, 0x0F, 0x00, 0x00]);
+
+        write!(disp, "\r\n{}", 42).unwrap();
+        assert_eq!(disp.position(), (2, 1));
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(window.rows, [8, 15]);
+        assert!(!disp.display.is_overflowed());
+    }
+
+    #[test]
+    /// Tests that lines wrap at the right edge and that the terminal scrolls past the last line.
+    fn wrap_and_scroll() {
+        let mut disp: Ssd1322<_, 256, 64, (), TerminalMode> =
+            Ssd1322::new_terminal(RecordingInterface::<16384>::new());
+
+        for _ in 0..33 {
+            disp.print_char('x').unwrap();
+        }
+        assert_eq!(disp.position(), (1, 1));
+
+        disp.display.clear();
+        for _ in 0..7 {
+            writeln!(disp).unwrap();
+        }
+        assert_eq!(disp.position(), (0, 7));
+        assert!(!disp.display.is_overflowed());
+        assert_eq!(start_line(&disp.display), Some(8));
+        // The new last line is cleared.
+        let window = disp.display.windows().last().unwrap();
+        assert_eq!(window.rows, [64, 71]);
+
+        disp.set_position(31, 7).unwrap();
+        disp.print_char('y').unwrap();
+        assert!(disp.set_position(32, 0).is_err());
+
+        disp.display.clear();
+        for _ in 0..15 {
+            writeln!(disp).unwrap();
+        }
+        assert!(!disp.display.is_overflowed());
+        assert_eq!(start_line(&disp.display), Some(0));
+
+        disp.clear().unwrap();
+        assert_eq!(disp.position(), (0, 0));
+    }
+}
@@ -0,0 +1,196 @@
+//! multi-panel display module
+use crate::display::Ssd1322;
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Point, Size},
+    pixelcolor::Gray4,
+    primitives::Rectangle,
+    Pixel,
+};
+
+/// Composes `N` panels placed side by side into one draw target, e.g. two 256x64 panels into a
+/// 512x64 display. Drawing is split across the framebuffers of the panels and each flush method
+/// flushes every panel in turn.
+///
+/// The panels are placed left to right in the order given, each with the size it has in its
+/// rotation. The display is as tall as the shortest panel.
+///
+/// ```ignore
+/// let mut tiled = TiledDisplay::new([Ssd1322::new(left), Ssd1322::new(right)]);
+/// tiled.init()?;
+/// Text::new("Hello", Point::new(250, 10), style).draw(&mut tiled)?;
+/// tiled.flush()?;
+/// ```
+pub struct TiledDisplay<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize> {
+    panels: [Ssd1322<DI, WIDTH, HEIGHT, B>; N],
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize>
+    TiledDisplay<DI, WIDTH, HEIGHT, B, N>
+{
+    /// Creates the display from the panels, left to right.
+    pub fn new(panels: [Ssd1322<DI, WIDTH, HEIGHT, B>; N]) -> Self {
+        Self { panels }
+    }
+
+    /// Returns the panels.
+    pub fn panels(&self) -> &[Ssd1322<DI, WIDTH, HEIGHT, B>; N] {
+        &self.panels
+    }
+
+    /// Returns the panels, e.g. to change the brightness of a single one.
+    pub fn panels_mut(&mut self) -> &mut [Ssd1322<DI, WIDTH, HEIGHT, B>; N] {
+        &mut self.panels
+    }
+
+    /// Returns the panels.
+    pub fn release(self) -> [Ssd1322<DI, WIDTH, HEIGHT, B>; N] {
+        self.panels
+    }
+
+    /// Returns each panel with the area it covers in the display.
+    fn tiles(
+        &mut self,
+    ) -> impl Iterator<Item = (Rectangle, &mut Ssd1322<DI, WIDTH, HEIGHT, B>)> + '_ {
+        let mut x = 0;
+        self.panels.iter_mut().map(move |panel| {
+            let area = Rectangle::new(Point::new(x, 0), panel.size());
+            x += area.size.width as i32;
+            (area, panel)
+        })
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize>
+    TiledDisplay<DI, WIDTH, HEIGHT, B, N>
+where
+    DI: WriteOnlyDataCommand,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Initializes every panel.
+    pub fn init(&mut self) -> Result<(), DisplayError> {
+        self.panels.iter_mut().try_for_each(|panel| panel.init())
+    }
+
+    /// Flushes the changed portion of every panel.
+    pub fn flush(&mut self) -> Result<(), DisplayError> {
+        self.panels.iter_mut().try_for_each(|panel| panel.flush())
+    }
+
+    /// Flushes every panel entirely.
+    pub fn flush_all(&mut self) -> Result<(), DisplayError> {
+        self.panels
+            .iter_mut()
+            .try_for_each(|panel| panel.flush_all())
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize> DrawTarget
+    for TiledDisplay<DI, WIDTH, HEIGHT, B, N>
+where
+    B: AsMut<[u8]>,
+{
+    type Color = Gray4;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels.into_iter() {
+            // Pixels outside of every panel are discarded.
+            if let Some((area, panel)) = self.tiles().find(|(area, _)| area.contains(point)) {
+                panel.draw_iter(core::iter::once(Pixel(point - area.top_left, color)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        for (tile, panel) in self.tiles() {
+            let part = area.intersection(&tile);
+            if !part.is_zero_sized() {
+                let part = Rectangle::new(part.top_left - tile.top_left, part.size);
+                panel.fill_solid(&part, color)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.panels
+            .iter_mut()
+            .try_for_each(|panel| panel.clear(color))
+    }
+}
+
+impl<DI, const WIDTH: usize, const HEIGHT: usize, B, const N: usize> OriginDimensions
+    for TiledDisplay<DI, WIDTH, HEIGHT, B, N>
+{
+    fn size(&self) -> Size {
+        self.panels.iter().fold(Size::zero(), |size, panel| {
+            let panel = panel.size();
+            let height = if size.width == 0 {
+                panel.height
+            } else {
+                size.height.min(panel.height)
+            };
+            Size::new(size.width + panel.width, height)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingInterface;
+    use embedded_graphics::prelude::*;
+
+    #[test]
+    /// Tests that drawing is split across the panels and flushed on each.
+    fn tiled_display() {
+        let mut tiled = TiledDisplay::new([
+            Ssd1322::<_>::new(RecordingInterface::<64>::new()),
+            Ssd1322::<_>::new(RecordingInterface::<64>::new()),
+        ]);
+        assert_eq!(tiled.size(), Size::new(512, 64));
+
+        Pixel(Point::new(300, 5), Gray4::WHITE)
+            .draw(&mut tiled)
+            .unwrap();
+        Pixel(Point::new(512, 5), Gray4::WHITE)
+            .draw(&mut tiled)
+            .unwrap();
+        tiled
+            .fill_solid(
+                &Rectangle::new(Point::new(254, 10), Size::new(4, 2)),
+                Gray4::new(0x08),
+            )
+            .unwrap();
+
+        let [left, right] = tiled.panels();
+        assert_eq!(right.get_pixel(Point::new(44, 5)), Some(Gray4::WHITE));
+        assert_eq!(left.get_pixel(Point::new(255, 11)), Some(Gray4::new(0x08)));
+        assert_eq!(right.get_pixel(Point::new(1, 11)), Some(Gray4::new(0x08)));
+        assert_eq!(right.get_pixel(Point::new(2, 11)), Some(Gray4::BLACK));
+        assert_eq!(
+            left.dirty_area(),
+            Some(Rectangle::new(Point::new(254, 10), Size::new(2, 2)))
+        );
+
+        tiled.flush().unwrap();
+        assert!(tiled
+            .panels()
+            .iter()
+            .all(|panel| panel.dirty_area().is_none()));
+
+        let [left, right] = tiled.panels();
+        let window = left.display.windows().next().unwrap();
+        assert_eq!((window.columns, window.rows), ([0x5B, 0x5B], [10, 11]));
+        assert_eq!(window.data, [0x00, 0x88, 0x00, 0x88]);
+        assert_eq!(right.display.windows().count(), 2);
+    }
+}